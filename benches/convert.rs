@@ -0,0 +1,74 @@
+//! Converts a chunk full of chests, which is dominated by the nested conversions of the items in the chests. Run with
+//! `cargo bench --bench convert`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use valence_nbt::{compound, jcompound};
+use world_transmuter::{convert_map, types};
+use world_transmuter_engine::{JCompound, JList};
+
+const CHESTS: usize = 64;
+const ITEMS_PER_CHEST: usize = 27;
+const ITERATIONS: u32 = 20;
+
+fn item(slot: usize) -> JCompound {
+    jcompound! {
+        "id" => "minecraft:diamond_sword",
+        "Count" => 1i8,
+        "Slot" => slot as i8,
+        "tag" => jcompound! {
+            "Damage" => 5,
+            "display" => jcompound! {
+                "Name" => r#"{"text":"Sword"}"#,
+                "Lore" => JList::String(vec![r#"{"text":"Sharp"}"#.into()]),
+            },
+            "Enchantments" => JList::Compound(vec![jcompound! {
+                "id" => "minecraft:sharpness",
+                "lvl" => 5i16,
+            }]),
+        },
+    }
+}
+
+fn chunk() -> JCompound {
+    let chests = (0..CHESTS)
+        .map(|index| {
+            jcompound! {
+                "id" => "minecraft:chest",
+                "x" => (index % 16) as i32,
+                "y" => 64,
+                "z" => (index / 16) as i32,
+                "Items" => JList::Compound((0..ITEMS_PER_CHEST).map(item).collect()),
+            }
+        })
+        .collect();
+    jcompound! {
+        "DataVersion" => 3465,
+        "xPos" => 0,
+        "yPos" => -4,
+        "zPos" => 0,
+        "Status" => "minecraft:full",
+        "sections" => JList::Compound(Vec::new()),
+        "block_entities" => JList::Compound(chests),
+    }
+}
+
+fn main() {
+    let chunk = chunk();
+    // the first conversion also builds the registry
+    convert_map(&*types::chunk(), &mut chunk.clone(), 3465, 4059);
+
+    let mut total = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        let mut chunk = chunk.clone();
+        let start = Instant::now();
+        convert_map(&*types::chunk(), &mut chunk, 3465, 4059);
+        total += start.elapsed();
+        black_box(chunk);
+    }
+    let per_chunk = total / ITERATIONS;
+    println!(
+        "{CHESTS} chests of {ITEMS_PER_CHEST} items: {per_chunk:?} per chunk, {:?} per item",
+        per_chunk / (CHESTS * ITEMS_PER_CHEST) as u32
+    );
+}
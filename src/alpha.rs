@@ -0,0 +1,162 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Parses the name of a chunk file of an Alpha world, such as `c.-1.a.dat`, into its chunk coordinates, which are in
+/// base 36.
+pub fn parse_alpha_chunk_file_name(name: &str) -> Option<(i32, i32)> {
+    let mut parts = name.strip_prefix("c.")?.strip_suffix(".dat")?.split('.');
+    let x = i32::from_str_radix(parts.next()?, 36).ok()?;
+    let z = i32::from_str_radix(parts.next()?, 36).ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((x, z))
+}
+
+/// Returns the path of a chunk in an Alpha world, which is stored in directories named after its coordinates modulo
+/// 64, such as `1q/a/c.-2.a.dat` for chunk -2, 10.
+pub fn alpha_chunk_path(dimension_dir: &Path, x: i32, z: i32) -> PathBuf {
+    dimension_dir
+        .join(to_base36(x & 63))
+        .join(to_base36(z & 63))
+        .join(format!("c.{}.{}.dat", to_base36(x), to_base36(z)))
+}
+
+/// Finds the chunk files of an Alpha world in a dimension directory, sorted by their coordinates. Each file is a gzip
+/// compressed chunk in the same layout as chunks in MCRegion files.
+pub fn find_alpha_chunks(dimension_dir: &Path) -> Vec<((i32, i32), PathBuf)> {
+    fn sub_dirs(dir: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.is_dir()
+                    && path
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .and_then(|name| (name.len() <= 2).then(|| i32::from_str_radix(name, 36)))
+                        .is_some_and(|coord| coord.is_ok_and(|coord| (0..64).contains(&coord)))
+            })
+            .collect()
+    }
+
+    let mut chunks = Vec::new();
+    for x_dir in sub_dirs(dimension_dir) {
+        for z_dir in sub_dirs(&x_dir) {
+            let Ok(entries) = fs::read_dir(&z_dir) else {
+                continue;
+            };
+            for path in entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+            {
+                let pos = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(parse_alpha_chunk_file_name);
+                if let Some(pos) = pos {
+                    if path.is_file() {
+                        chunks.push((pos, path));
+                    }
+                }
+            }
+        }
+    }
+    chunks.sort();
+    chunks
+}
+
+fn to_base36(value: i32) -> String {
+    let mut digits = Vec::new();
+    let mut remaining = value.unsigned_abs();
+    loop {
+        digits.push(char::from_digit(remaining % 36, 36).unwrap());
+        remaining /= 36;
+        if remaining == 0 {
+            break;
+        }
+    }
+    if value < 0 {
+        digits.push('-');
+    }
+    digits.iter().rev().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt_io::{read_nbt_file, write_nbt_file, CompressionType};
+    use crate::region::RegionFile;
+    use crate::test_util::TempDir;
+    use crate::world::WorldUpgrader;
+    use valence_nbt::{compound, jcompound};
+    use world_transmuter_engine::{JList, JValue};
+
+    #[test]
+    fn test_alpha_chunk_file_names() {
+        assert_eq!(
+            PathBuf::from("1q/a/c.-2.a.dat"),
+            alpha_chunk_path(Path::new(""), -2, 10)
+        );
+        assert_eq!(Some((-2, 10)), parse_alpha_chunk_file_name("c.-2.a.dat"));
+        assert_eq!(Some((1332, 0)), parse_alpha_chunk_file_name("c.110.0.dat"));
+        assert_eq!(None, parse_alpha_chunk_file_name("c.0.dat"));
+    }
+
+    #[test]
+    fn test_upgrade_alpha_world() {
+        let dir = TempDir::new("alpha-world");
+        let level = jcompound! {
+            "Data" => jcompound! {
+                "LevelName" => "alpha",
+                "SpawnX" => 0,
+                "SpawnY" => 64,
+                "SpawnZ" => 0,
+            },
+        };
+        write_nbt_file(dir.path().join("level.dat"), &level, CompressionType::Gzip).unwrap();
+
+        let mut blocks = vec![0i8; 32768];
+        blocks[64] = 1;
+        let chunk = jcompound! {
+            "Level" => jcompound! {
+                "xPos" => -2,
+                "zPos" => 10,
+                "Blocks" => blocks,
+                "Data" => vec![0i8; 16384],
+                "SkyLight" => vec![0i8; 16384],
+                "BlockLight" => vec![0i8; 16384],
+                "HeightMap" => vec![65i8; 256],
+                "Entities" => JList::Compound(Vec::new()),
+                "TileEntities" => JList::Compound(Vec::new()),
+            },
+        };
+        let path = alpha_chunk_path(dir.path(), -2, 10);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        write_nbt_file(&path, &chunk, CompressionType::Gzip).unwrap();
+
+        let report = WorldUpgrader::new(dir.path(), 1343).upgrade().unwrap();
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        assert_eq!(1, report.chunks_upgraded);
+
+        let mut region = RegionFile::open(dir.path().join("region/r.-1.0.mca")).unwrap();
+        let chunk = region.read_chunk(-2, 10).unwrap().unwrap();
+        assert_eq!(Some(&JValue::Int(1343)), chunk.get("DataVersion"));
+        let Some(JValue::Compound(level)) = chunk.get("Level") else {
+            panic!("chunk lost its Level tag");
+        };
+        let Some(JValue::List(JList::Compound(sections))) = level.get("Sections") else {
+            panic!("chunk has no sections");
+        };
+        assert_eq!(1, sections.len());
+
+        let (level, _) = read_nbt_file(dir.path().join("level.dat")).unwrap();
+        let Some(JValue::Compound(data)) = level.get("Data") else {
+            panic!("level.dat lost its Data tag");
+        };
+        assert_eq!(Some(&JValue::Int(19133)), data.get("version"));
+    }
+}
@@ -0,0 +1,260 @@
+use crate::helpers::bit_storage::ceil_log2;
+use crate::types::TypeKind;
+use world_transmuter_engine::{JCompound, JList, JValue};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Confidence {
+    /// The data has no features that narrow down its version, so this is only a fallback.
+    Low,
+    /// The shape of the data matches a range of versions.
+    Medium,
+    /// The data has a `DataVersion`, or features that only existed in a narrow range of versions.
+    High,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct DetectedVersion {
+    /// The earliest data version whose format matches the data, which can be used as the version to convert from.
+    pub data_version: u32,
+    pub confidence: Confidence,
+}
+
+impl DetectedVersion {
+    fn new(data_version: u32, confidence: Confidence) -> Self {
+        Self {
+            data_version,
+            confidence,
+        }
+    }
+}
+
+/// Guesses the data version of data of the given type, preferring its `DataVersion` if it has a valid one. Only chunks,
+/// item stacks, entities and tile entities have heuristics, so `None` is returned for other types without a valid
+/// `DataVersion`.
+pub fn detect_version(kind: TypeKind, data: &JCompound) -> Option<DetectedVersion> {
+    if let Some(data_version) = data
+        .get("DataVersion")
+        .and_then(|data_version| data_version.as_i32())
+        .and_then(|data_version| u32::try_from(data_version).ok())
+    {
+        return Some(DetectedVersion::new(data_version, Confidence::High));
+    }
+    match kind {
+        TypeKind::Chunk => Some(detect_chunk_version(data)),
+        TypeKind::ItemStack => Some(detect_item_stack_version(data)),
+        TypeKind::Entity => Some(detect_entity_version(data)),
+        TypeKind::TileEntity => Some(detect_tile_entity_version(data)),
+        _ => None,
+    }
+}
+
+fn detect_chunk_version(data: &JCompound) -> DetectedVersion {
+    let Some(JValue::Compound(level)) = data.get("Level") else {
+        // 21w43a removed the Level compound
+        return if let Some(JValue::List(JList::Compound(sections))) = data.get("sections") {
+            if sections
+                .iter()
+                .any(|section| section.contains_key("block_states"))
+            {
+                DetectedVersion::new(2842, Confidence::Medium)
+            } else {
+                DetectedVersion::new(2842, Confidence::Low)
+            }
+        } else {
+            DetectedVersion::new(99, Confidence::Low)
+        };
+    };
+
+    let sections = match level.get("Sections") {
+        Some(JValue::List(JList::Compound(sections))) => &sections[..],
+        _ => &[],
+    };
+    if sections
+        .iter()
+        .any(|section| section.contains_key("Blocks"))
+    {
+        // Chunks have had a DataVersion since 15w32a, but the block ids stayed numeric until the flattening
+        return DetectedVersion::new(99, Confidence::Medium);
+    }
+
+    let flattened = sections
+        .iter()
+        .any(|section| section.contains_key("Palette"));
+    if !flattened {
+        return if level.contains_key("TerrainPopulated") {
+            DetectedVersion::new(99, Confidence::Low)
+        } else {
+            DetectedVersion::new(1466, Confidence::Low)
+        };
+    }
+
+    if sections.iter().any(has_padded_block_states) {
+        return DetectedVersion::new(2527, Confidence::Medium);
+    }
+    match level.get("Biomes") {
+        Some(JValue::IntArray(biomes)) if biomes.len() > 256 => {
+            DetectedVersion::new(2202, Confidence::Medium)
+        }
+        _ if level.contains_key("Status") => DetectedVersion::new(1466, Confidence::Medium),
+        _ => DetectedVersion::new(1451, Confidence::Medium),
+    }
+}
+
+/// Since 20w17a, block states no longer span across longs. The two packings can only be told apart when the number of
+/// bits per block doesn't divide 64.
+fn has_padded_block_states(section: &JCompound) -> bool {
+    let (Some(JValue::List(palette)), Some(JValue::LongArray(states))) =
+        (section.get("Palette"), section.get("BlockStates"))
+    else {
+        return false;
+    };
+    let bits = 4.max(ceil_log2(palette.len() as u32)) as usize;
+    if bits.is_power_of_two() {
+        return false;
+    }
+    states.len() == 4096usize.div_ceil(64 / bits)
+}
+
+fn detect_item_stack_version(data: &JCompound) -> DetectedVersion {
+    if data.contains_key("components") || data.contains_key("count") {
+        return DetectedVersion::new(3819, Confidence::Medium);
+    }
+    match data.get("id") {
+        // Item ids became strings in 15w32b
+        Some(id) if id.is_number() => DetectedVersion::new(99, Confidence::High),
+        Some(JValue::String(_)) if data.contains_key("Damage") => {
+            DetectedVersion::new(102, Confidence::Medium)
+        }
+        // 1.20.5 replaced the tag compound with components
+        Some(JValue::String(_)) if matches!(data.get("tag"), Some(JValue::Compound(_))) => {
+            DetectedVersion::new(1451, Confidence::Medium)
+        }
+        Some(JValue::String(_)) => DetectedVersion::new(1451, Confidence::Low),
+        _ => DetectedVersion::new(99, Confidence::Low),
+    }
+}
+
+fn detect_entity_version(data: &JCompound) -> DetectedVersion {
+    let Some(JValue::String(id)) = data.get("id") else {
+        return DetectedVersion::new(99, Confidence::Low);
+    };
+    if !id.contains(':') {
+        // CamelCase ids such as PigZombie were replaced by namespaced ids in 1.11
+        return DetectedVersion::new(99, Confidence::Medium);
+    }
+    if matches!(data.get("UUID"), Some(JValue::IntArray(_))) {
+        DetectedVersion::new(2514, Confidence::Medium)
+    } else {
+        DetectedVersion::new(705, Confidence::Medium)
+    }
+}
+
+fn detect_tile_entity_version(data: &JCompound) -> DetectedVersion {
+    match data.get("id") {
+        Some(JValue::String(id)) if !id.contains(':') => {
+            DetectedVersion::new(99, Confidence::Medium)
+        }
+        Some(JValue::String(_)) => DetectedVersion::new(704, Confidence::Medium),
+        _ => DetectedVersion::new(99, Confidence::Low),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use valence_nbt::{compound, jcompound};
+
+    #[test]
+    fn test_detect_chunk() {
+        let pre_flattening = jcompound! {
+            "Level" => jcompound! {
+                "Sections" => JList::Compound(vec![jcompound! {
+                    "Blocks" => vec![0i8; 4096],
+                }]),
+            },
+        };
+        assert_eq!(
+            DetectedVersion::new(99, Confidence::Medium),
+            detect_version(TypeKind::Chunk, &pre_flattening).unwrap()
+        );
+
+        // 5 bits per block
+        let padded = jcompound! {
+            "Level" => jcompound! {
+                "Sections" => JList::Compound(vec![jcompound! {
+                    "Palette" => JList::Compound(vec![JCompound::new(); 17]),
+                    "BlockStates" => vec![0i64; 342],
+                }]),
+                "Biomes" => vec![0i32; 1024],
+            },
+        };
+        assert_eq!(
+            2527,
+            detect_version(TypeKind::Chunk, &padded)
+                .unwrap()
+                .data_version
+        );
+
+        let mut versioned = padded;
+        versioned.insert("DataVersion", 2586);
+        assert_eq!(
+            DetectedVersion::new(2586, Confidence::High),
+            detect_version(TypeKind::Chunk, &versioned).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_detect_item_stack() {
+        let numeric = jcompound! {
+            "id" => 1i16,
+            "Count" => 1i8,
+        };
+        assert_eq!(
+            DetectedVersion::new(99, Confidence::High),
+            detect_version(TypeKind::ItemStack, &numeric).unwrap()
+        );
+        let components = jcompound! {
+            "id" => "minecraft:stone",
+            "count" => 1,
+            "components" => JCompound::new(),
+        };
+        assert_eq!(
+            3819,
+            detect_version(TypeKind::ItemStack, &components)
+                .unwrap()
+                .data_version
+        );
+        let tagged = jcompound! {
+            "id" => "minecraft:diamond_sword",
+            "Count" => 1i8,
+            "tag" => jcompound! {
+                "Damage" => 5,
+            },
+        };
+        assert_eq!(
+            DetectedVersion::new(1451, Confidence::Medium),
+            detect_version(TypeKind::ItemStack, &tagged).unwrap()
+        );
+        let untagged = jcompound! {
+            "id" => "minecraft:stone",
+            "Count" => 1i8,
+        };
+        assert_eq!(
+            DetectedVersion::new(1451, Confidence::Low),
+            detect_version(TypeKind::ItemStack, &untagged).unwrap()
+        );
+        let negative = jcompound! {
+            "DataVersion" => -1,
+            "id" => "minecraft:stone",
+            "count" => 1,
+        };
+        assert_eq!(
+            DetectedVersion::new(3819, Confidence::Medium),
+            detect_version(TypeKind::ItemStack, &negative).unwrap()
+        );
+        assert_eq!(
+            None,
+            detect_version(TypeKind::Biome, &jcompound! { "DataVersion" => -1, })
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use crate::instrument;
+use crate::trace;
+use crate::types::TypeKind;
+use java_string::JavaString;
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use tracing::{error, warn};
+use world_transmuter_engine::DataVersion;
+
+thread_local! {
+    static COLLECTOR: RefCell<Option<Vec<Diagnostic>>> = const { RefCell::new(None) };
+    static OBJECTS: RefCell<Vec<TypeKind>> = const { RefCell::new(Vec::new()) };
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Severity {
+    /// The data was malformed, but nothing was lost.
+    Warning,
+    /// The converter had to guess how to convert the data.
+    Guess,
+    /// Some of the data was thrown away.
+    DataLoss,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum DiagnosticKind {
+    DuplicateBlockEntity,
+    DuplicateChunkSection,
+    InvalidChunkSection,
+    UnknownStructure,
+    UnknownLegacyId,
+    ParticleParseFailure,
+    UnsupportedCustomWorld,
+    UnknownPlayer,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub severity: Severity,
+    /// The data version of the converter that reported the diagnostic.
+    pub version: DataVersion,
+    /// The type the converter that reported the diagnostic was registered with.
+    pub typ: TypeKind,
+    /// The id the converter that reported the diagnostic is specific to, if any.
+    pub id: Option<JavaString>,
+    /// The path to the offending data, relative to the object of type `typ` being converted.
+    pub path: String,
+    pub message: String,
+    /// The types of the objects that were being converted, from the top-level object to the one of type `typ`, such
+    /// as `[Chunk, TileEntity, ItemStack]` for an item in a chest. Objects that aren't converted through a
+    /// [`TypeLock`](crate::types::TypeLock) or [`TypeRef`](crate::types::TypeRef) are missing. The objects are only
+    /// known while diagnostics are being [collected](collect), and are empty in diagnostics that are only logged.
+    pub objects: Vec<TypeKind>,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (", self.message)?;
+        trace::write_converter(f, self.version, &self.id)?;
+        write!(f, ") in {:?}", self.typ)?;
+        if !self.path.is_empty() {
+            write!(f, " at {}", self.path)?;
+        }
+        if let Some((_, outer)) = self.objects.split_last() {
+            for (index, kind) in outer.iter().rev().enumerate() {
+                let separator = if index == 0 { ", inside" } else { " in" };
+                write!(f, "{separator} {kind:?}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reports a diagnostic from the running converter, logging it and adding it to the diagnostics being collected on
+/// this thread, if any. `path` is relative to the object the converter is converting.
+pub(crate) fn report(
+    kind: DiagnosticKind,
+    severity: Severity,
+    path: impl Into<String>,
+    message: impl Into<String>,
+) {
+    let diagnostic = trace::running_converter(|converter| {
+        let converter = converter.expect("diagnostics should only be reported by converters");
+        Diagnostic {
+            kind,
+            severity,
+            version: converter.version,
+            typ: converter.kind,
+            id: converter.id.clone(),
+            path: path.into(),
+            message: message.into(),
+            objects: OBJECTS.with_borrow(|objects| objects.clone()),
+        }
+    });
+    match diagnostic.severity {
+        Severity::DataLoss => error!("{diagnostic}"),
+        Severity::Warning | Severity::Guess => warn!("{diagnostic}"),
+    }
+    COLLECTOR.with_borrow_mut(|collector| {
+        if let Some(collector) = collector {
+            collector.push(diagnostic);
+        }
+    });
+}
+
+/// Runs `f`, which converts an object of type `kind`, so that diagnostics reported while it runs know which objects
+/// they were reported in.
+pub(crate) fn in_object<R>(kind: TypeKind, f: impl FnOnce() -> R) -> R {
+    struct PopGuard;
+
+    impl Drop for PopGuard {
+        fn drop(&mut self) {
+            OBJECTS.with_borrow_mut(|objects| objects.pop());
+        }
+    }
+
+    OBJECTS.with_borrow_mut(|objects| objects.push(kind));
+    let _guard = PopGuard;
+    f()
+}
+
+/// Runs `f`, collecting all diagnostics reported on this thread while it runs. Collection can be nested, in which case
+/// the outer collection also receives the diagnostics of the inner collection.
+pub fn collect<R>(f: impl FnOnce() -> R) -> (R, Vec<Diagnostic>) {
+    struct RestoreGuard(Option<Vec<Diagnostic>>);
+
+    impl Drop for RestoreGuard {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            COLLECTOR.with_borrow_mut(|collector| *collector = previous);
+        }
+    }
+
+    let mut guard =
+        RestoreGuard(COLLECTOR.with_borrow_mut(|collector| collector.replace(Vec::new())));
+    let result = instrument::activate(f);
+    let diagnostics = COLLECTOR
+        .with_borrow_mut(|collector| collector.take())
+        .unwrap_or_default();
+    if let Some(previous) = &mut guard.0 {
+        previous.extend(diagnostics.iter().cloned());
+    }
+    (result, diagnostics)
+}
+
+/// The error returned by strict conversions, containing the diagnostics that caused the conversion to be rejected.
+#[derive(Clone, Debug)]
+pub struct LossyConversionError {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Display for LossyConversionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Lossy conversion")?;
+        if let Some(diagnostic) = self.diagnostics.first() {
+            write!(f, ": {diagnostic}")?;
+        }
+        if self.diagnostics.len() > 1 {
+            write!(f, " (and {} more)", self.diagnostics.len() - 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LossyConversionError {}
+
+/// Runs the conversion `f` on a copy of `data`, and only replaces `data` with the result if no diagnostics of severity
+/// [`Severity::Guess`] or worse were reported. Otherwise, `data` is left untouched and those diagnostics are returned.
+pub fn run_strict<T: Clone>(
+    data: &mut T,
+    f: impl FnOnce(&mut T),
+) -> Result<(), LossyConversionError> {
+    let mut converted = data.clone();
+    let ((), diagnostics) = collect(|| f(&mut converted));
+    let diagnostics: Vec<_> = diagnostics
+        .into_iter()
+        .filter(|diagnostic| diagnostic.severity >= Severity::Guess)
+        .collect();
+    if !diagnostics.is_empty() {
+        return Err(LossyConversionError { diagnostics });
+    }
+    *data = converted;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{convert_map, convert_map_strict, convert_map_with_diagnostics, types};
+    use valence_nbt::{compound, jcompound};
+    use world_transmuter_engine::{JList, JValue};
+
+    #[test]
+    fn test_unknown_legacy_id() {
+        let mut item = jcompound! {
+            "id" => 999i16,
+            "Count" => 1i8,
+        };
+        let diagnostics = convert_map_with_diagnostics(&*types::item_stack(), &mut item, 99, 102);
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(DiagnosticKind::UnknownLegacyId, diagnostics[0].kind);
+        assert_eq!(Severity::DataLoss, diagnostics[0].severity);
+        assert_eq!(102, diagnostics[0].version.get_version());
+        assert_eq!(TypeKind::ItemStack, diagnostics[0].typ);
+        assert_eq!("id", diagnostics[0].path);
+    }
+
+    #[test]
+    fn test_nested_objects() {
+        let mut chest = jcompound! {
+            "id" => "Chest",
+            "Items" => JList::Compound(vec![jcompound! {
+                "id" => 999i16,
+                "Count" => 1i8,
+                "Slot" => 0i8,
+            }]),
+        };
+        let diagnostics =
+            convert_map_with_diagnostics(types::tile_entity_ref(), &mut chest, 99, 102);
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(TypeKind::ItemStack, diagnostics[0].typ);
+        assert_eq!("id", diagnostics[0].path);
+        assert_eq!(
+            vec![TypeKind::TileEntity, TypeKind::ItemStack],
+            diagnostics[0].objects
+        );
+        assert!(diagnostics[0]
+            .to_string()
+            .ends_with(" at id, inside TileEntity"));
+    }
+
+    #[test]
+    fn test_nested_collect() {
+        let report_test = || {
+            let mut item = jcompound! {
+                "id" => 999i16,
+                "Count" => 1i8,
+            };
+            convert_map(types::item_stack_ref(), &mut item, 99, 102);
+        };
+        let (inner, outer) = collect(|| {
+            report_test();
+            collect(report_test).1
+        });
+        assert_eq!(1, inner.len());
+        assert_eq!(2, outer.len());
+        report_test();
+    }
+
+    #[test]
+    fn test_strict() {
+        let mut item = jcompound! {
+            "id" => 999i16,
+            "Count" => 1i8,
+        };
+        let original = item.clone();
+        let err = convert_map_strict(&*types::item_stack(), &mut item, 99, 102).unwrap_err();
+        assert!(err.diagnostics[0]
+            .to_string()
+            .contains(" (V102) in ItemStack"));
+        assert_eq!(original, item);
+
+        let mut item = jcompound! {
+            "id" => 1i16,
+            "Count" => 1i8,
+        };
+        convert_map_strict(&*types::item_stack(), &mut item, 99, 102).unwrap();
+        assert_eq!(Some(&JValue::from("minecraft:stone")), item.get("id"));
+    }
+}
@@ -38,6 +38,11 @@ pub(crate) fn get_state_for_id_raw(id: u16) -> Option<&'static BlockState<'stati
     data.flattened_by_id[id as usize].map(|index| &data.states[index as usize])
 }
 
+/// The ids that have an explicit mapping, in ascending order. The other ids map to the state of their block's metadata 0.
+pub(crate) fn get_registered_ids() -> &'static [u16] {
+    &block_state_data().registered_ids
+}
+
 pub(crate) fn get_nbt_for_id(id: u16) -> JCompound {
     get_state_for_id_raw(id).map_or_else(
         || {
@@ -52,6 +57,7 @@ pub(crate) fn get_nbt_for_id(id: u16) -> JCompound {
 struct BlockStateData {
     states: Vec<BlockState<'static>>,
     flattened_by_id: [Option<u16>; 4096],
+    registered_ids: Vec<u16>,
     block_defaults: [Option<u16>; 256],
     index_by_old_nbt: AHashMap<BlockState<'static>, u16>,
     index_by_old_name: AHashMap<&'static JavaStr, u16>,
@@ -64,6 +70,7 @@ fn block_state_data() -> &'static BlockStateData {
         let mut states = Vec::new();
         const NONE: Option<u16> = None;
         let mut flattened_by_id = [NONE; 4096];
+        let mut registered_ids = Vec::new();
         let mut block_defaults = [NONE; 256];
         let mut index_by_old_nbt = AHashMap::new();
         let mut index_by_old_name = AHashMap::new();
@@ -96,6 +103,7 @@ fn block_state_data() -> &'static BlockStateData {
             );
 
             flattened_by_id[id as usize] = Some(new);
+            registered_ids.push(id);
 
             // it's important that we register ids from smallest to largest, so that
             // the default is going to be correct
@@ -119,6 +127,7 @@ fn block_state_data() -> &'static BlockStateData {
         BlockStateData {
             states,
             flattened_by_id,
+            registered_ids,
             block_defaults,
             index_by_old_nbt,
             index_by_old_name,
@@ -1,16 +1,18 @@
+use crate::diagnostics::{DiagnosticKind, Severity};
 use crate::helpers::bit_storage::{
     bitset_size, ceil_log2, BitStorage, BitStorageMut, BitStorageOwned, ChunkNibbleArray, LocalPos,
     PackedBitStorage,
 };
 use crate::helpers::block_state::{BlockState, BlockStateOwned};
 use crate::helpers::{block_flattening_v1450, item_name_v102};
-use crate::{block_state, block_state_owned, make_bit_arr};
+use crate::legacy::id_map;
+use crate::{block_state, block_state_owned, diagnostics, make_bit_arr};
 use ahash::{AHashMap, AHashSet};
 use bitvec::prelude::*;
 use java_string::{JavaStr, JavaString};
 use std::collections::BTreeMap;
 use std::sync::OnceLock;
-use tracing::{error, warn};
+use tracing::error;
 use world_transmuter_engine::{DataVersion, JCompound, JList, JValue, MapDataConverterFunc};
 
 const VIRTUAL_SET: BitArray<[usize; bitset_size(256)]> = make_bit_arr![256;
@@ -500,7 +502,7 @@ impl<'a> UpgradeChunk<'a> {
         let block_z = level.get("zPos").and_then(|v| v.as_i32()).unwrap_or(0) << 4;
         let mut tile_entities_map = AHashMap::new();
         if let Some(JValue::List(JList::Compound(tile_entities))) = level.get("TileEntities") {
-            for tile_entity in tile_entities {
+            for (index, tile_entity) in tile_entities.iter().enumerate() {
                 let x = ((tile_entity.get("x").and_then(|v| v.as_i32()).unwrap_or(0) - block_x)
                     & 15) as u8;
                 let y = tile_entity.get("y").and_then(|v| v.as_i8()).unwrap_or(0) as u8;
@@ -510,7 +512,12 @@ impl<'a> UpgradeChunk<'a> {
                     .insert(LocalPos::new(x, y, z), tile_entity)
                     .is_some()
                 {
-                    warn!("In chunk: {}x{} found a duplicate block entity at position (ConverterFlattenChunk): [{}, {}, {}]", block_x, block_z, x, y, z);
+                    diagnostics::report(
+                        DiagnosticKind::DuplicateBlockEntity,
+                        Severity::Guess,
+                        format!("Level.TileEntities[{index}]"),
+                        format!("In chunk: {}x{} found a duplicate block entity at position: [{}, {}, {}]", block_x, block_z, x, y, z),
+                    );
                 }
             }
         }
@@ -522,16 +529,33 @@ impl<'a> UpgradeChunk<'a> {
         const NONE: Option<Section> = None;
         let mut sections_arr = [NONE; 16];
         let mut sides = 0;
+        let mut modded_states = AHashMap::new();
         if let Some(JValue::List(JList::Compound(sections))) = level.get("Sections") {
-            for section in sections {
-                let section = Section::from_nbt(section, &mut sides);
+            for (index, section) in sections.iter().enumerate() {
+                let section = Section::from_nbt(section, &mut sides, &mut modded_states);
                 let section_y = section.y;
                 if !(0..=15).contains(&section_y) {
-                    warn!("In chunk: {}x{} found an invalid chunk section y (ConverterFlattenChunk): {}", block_x, block_z, section_y);
+                    diagnostics::report(
+                        DiagnosticKind::InvalidChunkSection,
+                        Severity::DataLoss,
+                        format!("Level.Sections[{index}]"),
+                        format!(
+                            "In chunk: {}x{} found an invalid chunk section y: {}",
+                            block_x, block_z, section_y
+                        ),
+                    );
                     continue;
                 }
                 if sections_arr[section_y as usize].is_some() {
-                    warn!("In chunk: {}x{} found a duplicate chunk section (ConverterFlattenChunk): {}", block_x, block_z, section_y);
+                    diagnostics::report(
+                        DiagnosticKind::DuplicateChunkSection,
+                        Severity::DataLoss,
+                        format!("Level.Sections[{index}]"),
+                        format!(
+                            "In chunk: {}x{} found a duplicate chunk section: {}",
+                            block_x, block_z, section_y
+                        ),
+                    );
                 }
                 sections_arr[section_y as usize] = Some(section);
             }
@@ -931,13 +955,16 @@ struct Section {
 }
 
 impl Section {
-    fn from_nbt(nbt: &JCompound, sides: &mut u8) -> Self {
-        let mut palette = AHashMap::new();
-        let mut palette_states = Vec::new();
+    /// `modded_states` caches the states of block ids that are resolved with the [`id_map`], for the whole chunk.
+    fn from_nbt(
+        nbt: &JCompound,
+        sides: &mut u8,
+        modded_states: &mut AHashMap<u16, Option<BlockStateOwned>>,
+    ) -> Self {
         let mut to_fix = AHashMap::<_, Vec<LocalPos>>::new();
         let mut update = Vec::new();
         let y = nbt.get("Y").and_then(|v| v.as_i32()).unwrap_or(0);
-        let buffer = nbt
+        let state_ids = nbt
             .get("Blocks")
             .and_then(|o| match o {
                 JValue::ByteArray(arr) => Some(arr),
@@ -954,12 +981,7 @@ impl Section {
                 let data = ChunkNibbleArray::wrap(nbt, "Data");
                 let add = ChunkNibbleArray::wrap(nbt, "Add");
 
-                palette.insert(air(), 0);
-                palette_states.push(air().to_owned());
-
-                std::array::from_fn(|index| {
-                    let pos = LocalPos::from_raw(index as u16);
-
+                std::array::from_fn::<u16, 4096, _>(|index| {
                     let mut state_id = (blocks[index] as u8 as u16) << 4;
                     if let Some(data) = &data {
                         state_id |= data.get(index as u16) as u16;
@@ -967,41 +989,71 @@ impl Section {
                     if let Some(add) = &add {
                         state_id |= (add.get(index as u16) as u16) << 12;
                     }
-                    if *IDS_NEEDING_FIX_SET
-                        .get((state_id >> 4) as usize)
-                        .as_deref()
-                        .unwrap_or(&false)
-                    {
-                        to_fix.entry(state_id >> 4).or_default().push(pos);
-                    }
-                    if *VIRTUAL_SET
-                        .get((state_id >> 4) as usize)
-                        .as_deref()
-                        .unwrap_or(&false)
-                    {
-                        let additional_sides =
-                            get_side_mask(pos.x() == 0, pos.x() == 15, pos.z() == 0, pos.z() == 15);
-                        if additional_sides == 0 {
-                            update.push(pos);
-                        } else {
-                            *sides |= additional_sides;
-                        }
-                    }
-
-                    let mut state = block_flattening_v1450::get_state_for_id_raw(state_id)
-                        .unwrap_or_else(|| air());
-                    if state.name == "minecraft:%%FILTER_ME%%" {
-                        state = air();
-                    }
-                    let next_palette_index = palette.len() as u16;
-                    let palette_index = *palette.entry(state).or_insert_with(|| {
-                        palette_states.push(state.to_owned());
-                        next_palette_index
-                    });
-                    palette_index
+                    state_id
                 })
             });
 
+        // resolve the blocks vanilla doesn't know before the palette borrows their states
+        if let Some(state_ids) = &state_ids {
+            for &state_id in state_ids {
+                if block_flattening_v1450::get_state_for_id_raw(state_id).is_none() {
+                    modded_states
+                        .entry(state_id)
+                        .or_insert_with(|| id_map::block_state(state_id));
+                }
+            }
+        }
+        let modded_states: AHashMap<u16, BlockState> = modded_states
+            .iter()
+            .filter_map(|(&state_id, state)| Some((state_id, BlockState::from(state.as_ref()?))))
+            .collect();
+
+        let mut palette = AHashMap::new();
+        let mut palette_states = Vec::new();
+        let buffer = state_ids.map(|state_ids| {
+            palette.insert(air(), 0);
+            palette_states.push(air().to_owned());
+
+            std::array::from_fn(|index| {
+                let pos = LocalPos::from_raw(index as u16);
+                let state_id = state_ids[index];
+
+                if *IDS_NEEDING_FIX_SET
+                    .get((state_id >> 4) as usize)
+                    .as_deref()
+                    .unwrap_or(&false)
+                {
+                    to_fix.entry(state_id >> 4).or_default().push(pos);
+                }
+                if *VIRTUAL_SET
+                    .get((state_id >> 4) as usize)
+                    .as_deref()
+                    .unwrap_or(&false)
+                {
+                    let additional_sides =
+                        get_side_mask(pos.x() == 0, pos.x() == 15, pos.z() == 0, pos.z() == 15);
+                    if additional_sides == 0 {
+                        update.push(pos);
+                    } else {
+                        *sides |= additional_sides;
+                    }
+                }
+
+                let mut state = block_flattening_v1450::get_state_for_id_raw(state_id)
+                    .or_else(|| modded_states.get(&state_id))
+                    .unwrap_or_else(|| air());
+                if state.name == "minecraft:%%FILTER_ME%%" {
+                    state = air();
+                }
+                let next_palette_index = palette.len() as u16;
+                let palette_index = *palette.entry(state).or_insert_with(|| {
+                    palette_states.push(state.to_owned());
+                    next_palette_index
+                });
+                palette_index
+            })
+        });
+
         let palette: AHashMap<_, _> = palette
             .into_iter()
             .map(|(k, v)| (k.to_owned(), v))
@@ -738,6 +738,15 @@ pub(crate) fn flatten_item(
     }
 }
 
+/// The items that were split by their damage value, as `(old name, damage, new name)`.
+pub(crate) fn get_flattened_items() -> impl Iterator<Item = (&'static JavaStr, u8, &'static JavaStr)>
+{
+    flatten_data()
+        .flatten_map
+        .iter()
+        .map(|((data, old_name), new_name)| (*old_name, *data, *new_name))
+}
+
 pub(crate) struct ConverterFlattenItemStack;
 
 impl MapDataConverterFunc for ConverterFlattenItemStack {
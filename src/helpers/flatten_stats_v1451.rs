@@ -193,7 +193,7 @@ impl<'a> StatType<'a> {
     }
 }
 
-fn convert_legacy_key(key: &JavaStr) -> Option<StatType> {
+fn convert_legacy_key(key: &JavaStr) -> Option<StatType<'_>> {
     if skip_stats().contains(key) {
         return None;
     }
@@ -24,7 +24,7 @@ pub struct FixedGsonLenient<'a> {
     pub fixed_str: Cow<'a, JavaStr>,
 }
 
-pub fn fix_gson_lenient(input: &JavaStr) -> Result<FixedGsonLenient, Error<&[u8]>> {
+pub fn fix_gson_lenient(input: &JavaStr) -> Result<FixedGsonLenient<'_>, Error<&[u8]>> {
     let (_, (_, _, (value_type, fixed_str), _, _)) = tuple((
         opt(tag(b")]}'\n")),
         space,
@@ -52,7 +52,7 @@ pub fn fix_gson_lenient(input: &JavaStr) -> Result<FixedGsonLenient, Error<&[u8]
     })
 }
 
-fn space(i: &[u8]) -> IResult<&[u8], Option<Cow<[u8]>>> {
+fn space(i: &[u8]) -> IResult<&[u8], Option<Cow<'_, [u8]>>> {
     map(
         many0(alt((
             map(is_a(&b" \t\r\n"[..]), |str| Some(Cow::Borrowed(str))),
@@ -81,7 +81,7 @@ fn slash_star_comment(i: &[u8]) -> IResult<&[u8], ()> {
     value((), tuple((tag(b"/*"), take_until(&b"*/"[..]), tag(b"*/"))))(i)
 }
 
-fn number(i: &[u8]) -> IResult<&[u8], Cow<[u8]>> {
+fn number(i: &[u8]) -> IResult<&[u8], Cow<'_, [u8]>> {
     map(recognize_float, Cow::Borrowed)(i)
 }
 
@@ -99,7 +99,7 @@ fn string_of_type<'a>(quote: u8) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], &'a [
     }
 }
 
-fn string(i: &[u8]) -> IResult<&[u8], Cow<[u8]>> {
+fn string(i: &[u8]) -> IResult<&[u8], Cow<'_, [u8]>> {
     map(
         recognize(alt((string_of_type(b'"'), string_of_type(b'\'')))),
         |str| {
@@ -138,7 +138,7 @@ fn unquoted_string<'a>(i: &'a [u8]) -> IResult<&'a [u8], Cow<'a, [u8]>> {
     )(i)
 }
 
-fn keyword(i: &[u8]) -> IResult<&[u8], Cow<[u8]>> {
+fn keyword(i: &[u8]) -> IResult<&[u8], Cow<'_, [u8]>> {
     alt((
         map(
             alt((tag(b"true"), tag(b"false"), tag(b"null"))),
@@ -150,11 +150,11 @@ fn keyword(i: &[u8]) -> IResult<&[u8], Cow<[u8]>> {
     ))(i)
 }
 
-fn json_name(i: &[u8]) -> IResult<&[u8], Cow<[u8]>> {
+fn json_name(i: &[u8]) -> IResult<&[u8], Cow<'_, [u8]>> {
     alt((string, unquoted_string))(i)
 }
 
-fn json_name_value(i: &[u8]) -> IResult<&[u8], Cow<[u8]>> {
+fn json_name_value(i: &[u8]) -> IResult<&[u8], Cow<'_, [u8]>> {
     map(
         tuple((
             json_name,
@@ -184,14 +184,14 @@ fn json_name_value(i: &[u8]) -> IResult<&[u8], Cow<[u8]>> {
     )(i)
 }
 
-fn comma(i: &[u8]) -> IResult<&[u8], Cow<[u8]>> {
+fn comma(i: &[u8]) -> IResult<&[u8], Cow<'_, [u8]>> {
     alt((
         map(tag(b","), Cow::Borrowed),
         map(tag(b";"), |_| Cow::Owned(vec![b','])),
     ))(i)
 }
 
-fn json_object(i: &[u8]) -> IResult<&[u8], Cow<[u8]>> {
+fn json_object(i: &[u8]) -> IResult<&[u8], Cow<'_, [u8]>> {
     map(
         tuple((
             tag(b"{"),
@@ -240,11 +240,11 @@ fn json_object(i: &[u8]) -> IResult<&[u8], Cow<[u8]>> {
     )(i)
 }
 
-fn json_array_elem(i: &[u8]) -> IResult<&[u8], Cow<[u8]>> {
+fn json_array_elem(i: &[u8]) -> IResult<&[u8], Cow<'_, [u8]>> {
     alt((json_value, map(tag(b""), |_| Cow::Owned(b"null".to_vec()))))(i)
 }
 
-fn json_array(i: &[u8]) -> IResult<&[u8], Cow<[u8]>> {
+fn json_array(i: &[u8]) -> IResult<&[u8], Cow<'_, [u8]>> {
     map(
         tuple((
             tag(b"["),
@@ -293,7 +293,7 @@ fn json_array(i: &[u8]) -> IResult<&[u8], Cow<[u8]>> {
     )(i)
 }
 
-fn json_value(i: &[u8]) -> IResult<&[u8], Cow<[u8]>> {
+fn json_value(i: &[u8]) -> IResult<&[u8], Cow<'_, [u8]>> {
     alt((
         keyword,
         number,
@@ -327,7 +327,7 @@ unsafe fn concat_strings<'a>(a: Cow<'a, [u8]>, b: Cow<'a, [u8]>) -> Cow<'a, [u8]
 mod test {
     use super::*;
 
-    fn fix_gson_lenient(input: &str) -> Result<FixedGsonLenient, Error<&[u8]>> {
+    fn fix_gson_lenient(input: &str) -> Result<FixedGsonLenient<'_>, Error<&[u8]>> {
         super::fix_gson_lenient(JavaStr::from_str(input))
     }
 
@@ -496,8 +496,21 @@ pub fn get_name_from_id(id: i32) -> Option<&'static JavaStr> {
     item_names().get(&id).map(|str| JavaStr::from_str(str))
 }
 
+pub(crate) fn get_item_ids() -> impl Iterator<Item = (i32, &'static JavaStr)> {
+    item_names()
+        .iter()
+        .map(|(id, name)| (*id, JavaStr::from_str(name)))
+}
+
 pub fn get_potion_name_from_id(id: i32) -> Option<&'static JavaStr> {
     potion_names()[(id & 127) as usize]
         .as_ref()
         .map(|str| JavaStr::from_str(str))
 }
+
+pub(crate) fn get_potion_ids() -> impl Iterator<Item = (i32, &'static JavaStr)> {
+    potion_names()
+        .iter()
+        .enumerate()
+        .filter_map(|(id, name)| name.map(|name| (id as i32, JavaStr::from_str(name))))
+}
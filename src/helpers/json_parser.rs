@@ -246,6 +246,19 @@ pub fn parse_compound(json: &JavaStr, round_trip: bool) -> Result<JCompound, Par
         })
 }
 
+/// Like [`parse_compound`], but for any JSON value, such as the arrays of player lists.
+pub fn parse_value(json: &JavaStr, round_trip: bool) -> Result<JValue, ParseError> {
+    preceded(space, |i| any(i, round_trip))(json.as_bytes())
+        .finish()
+        .map(|(_, o)| o)
+        .map_err(|err| {
+            ParseError(Error {
+                input: String::from_utf8_lossy(err.input).into_owned(),
+                code: err.code,
+            })
+        })
+}
+
 fn space(i: &[u8]) -> IResult<&[u8], ()> {
     value((), many0(is_a(&b" \t\r\n"[..])))(i)
 }
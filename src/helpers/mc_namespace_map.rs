@@ -42,7 +42,7 @@ impl<'a, V> McNamespaceMap<'a, V> {
         self.0.len()
     }
 
-    pub fn iter_mc_to_value(&self) -> std::collections::btree_map::Iter<&'a JavaStr, V> {
+    pub fn iter_mc_to_value(&self) -> std::collections::btree_map::Iter<'_, &'a JavaStr, V> {
         self.0.iter()
     }
 }
@@ -1,15 +1,25 @@
+use crate::diagnostics;
+use crate::diagnostics::{DiagnosticKind, Severity};
 use crate::helpers::resource_location::ResourceLocation;
 use java_string::{JavaCodePoint, JavaStr, JavaString};
 use std::borrow::Cow;
 use std::fmt::Display;
 use std::str::FromStr;
-use tracing::warn;
 use valence_nbt::{compound, jcompound, snbt, Value};
 use world_transmuter_engine::{compound_to_java, JCompound, JList};
 
+fn report_failure(message: String) {
+    diagnostics::report(
+        DiagnosticKind::ParticleParseFailure,
+        Severity::DataLoss,
+        "",
+        message,
+    );
+}
+
 fn parse_nbt(flat: &JavaStr) -> Option<JCompound> {
     fn fail(flat: impl Display, reason: impl Display) -> Option<JCompound> {
-        warn!("Failed to parse nbt ({reason}): {flat}");
+        report_failure(format!("Failed to parse nbt ({reason}): {flat}"));
         None
     }
 
@@ -44,7 +54,7 @@ fn convert_item(nbt: &mut JCompound, data: &JavaStr) {
 
 fn parse_properties(input: &JavaStr) -> Option<JCompound> {
     fn fail(input: &JavaStr) -> Option<JCompound> {
-        warn!("Failed to parse block properties: {input}");
+        report_failure(format!("Failed to parse block properties: {input}"));
         None
     }
 
@@ -126,7 +136,7 @@ fn parse_float_vector(input: &JavaStr) -> Option<(JList, &JavaStr)> {
 
 fn convert_dust(nbt: &mut JCompound, data: &JavaStr) {
     fn fail(data: &JavaStr) {
-        warn!("Failed to parse dust particle: {data}");
+        report_failure(format!("Failed to parse dust particle: {data}"));
     }
 
     let reader = data;
@@ -148,7 +158,9 @@ fn convert_dust(nbt: &mut JCompound, data: &JavaStr) {
 
 fn convert_color_dust(nbt: &mut JCompound, data: &JavaStr) {
     fn fail(data: &JavaStr) {
-        warn!("Failed to parse color transition dust particle: {data}");
+        report_failure(format!(
+            "Failed to parse color transition dust particle: {data}"
+        ));
     }
 
     let reader = data;
@@ -178,7 +190,7 @@ fn convert_color_dust(nbt: &mut JCompound, data: &JavaStr) {
 
 fn convert_sculk(nbt: &mut JCompound, data: &JavaStr) {
     let Some((roll, _)) = read_number::<f32>(data) else {
-        warn!("Failed to parse sculk particle: {data}");
+        report_failure(format!("Failed to parse sculk particle: {data}"));
         return;
     };
 
@@ -187,7 +199,7 @@ fn convert_sculk(nbt: &mut JCompound, data: &JavaStr) {
 
 fn convert_vibration(nbt: &mut JCompound, data: &JavaStr) {
     fn fail(data: &JavaStr) {
-        warn!("Failed to parse vibration particle: {data}");
+        report_failure(format!("Failed to parse vibration particle: {data}"));
     }
 
     let reader = data;
@@ -229,7 +241,7 @@ fn convert_vibration(nbt: &mut JCompound, data: &JavaStr) {
 
 fn convert_shriek(nbt: &mut JCompound, data: &JavaStr) {
     let Some((delay, _)) = read_number::<i32>(data) else {
-        warn!("Failed to read shriek particle: {data}");
+        report_failure(format!("Failed to read shriek particle: {data}"));
         return;
     };
 
@@ -272,8 +284,11 @@ fn read_number<T: FromStr>(input: &JavaStr) -> Option<(T, &JavaStr)> {
 }
 
 /// A function to read a possibly quoted string that matches brigadier's `readString` function
-fn read_string(input: &JavaStr) -> Option<(Cow<JavaStr>, &JavaStr)> {
-    fn read_quoted_string(mut input: &JavaStr, quote: char) -> Option<(Cow<JavaStr>, &JavaStr)> {
+fn read_string(input: &JavaStr) -> Option<(Cow<'_, JavaStr>, &JavaStr)> {
+    fn read_quoted_string(
+        mut input: &JavaStr,
+        quote: char,
+    ) -> Option<(Cow<'_, JavaStr>, &JavaStr)> {
         input = &input[1..];
 
         // check if there is any need to allocate a new string
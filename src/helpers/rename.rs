@@ -5,9 +5,9 @@ use world_transmuter_engine::{
     JCompound, JList, JValue, JValueMut,
 };
 
-pub(crate) fn rename_entity(
+pub fn rename_entity(
     version: impl Into<DataVersion>,
-    renamer: impl 'static + Copy + Fn(&JavaStr) -> Option<JavaString>,
+    renamer: impl 'static + Copy + Send + Sync + Fn(&JavaStr) -> Option<JavaString>,
 ) {
     let version = version.into();
     types::entity_mut().add_structure_converter(
@@ -32,9 +32,9 @@ pub(crate) fn rename_entity(
     );
 }
 
-pub(crate) fn rename_tile_entity(
+pub fn rename_tile_entity(
     version: impl Into<DataVersion>,
-    renamer: impl 'static + Copy + Fn(&JavaStr) -> Option<JavaString>,
+    renamer: impl 'static + Copy + Send + Sync + Fn(&JavaStr) -> Option<JavaString>,
 ) {
     types::tile_entity_mut().add_structure_converter(
         version,
@@ -49,9 +49,9 @@ pub(crate) fn rename_tile_entity(
     );
 }
 
-pub(crate) fn rename_block(
+pub fn rename_block(
     version: impl Into<DataVersion>,
-    renamer: impl 'static + Copy + Fn(&JavaStr) -> Option<JavaString>,
+    renamer: impl 'static + Copy + Send + Sync + Fn(&JavaStr) -> Option<JavaString>,
 ) {
     let version = version.into();
     types::block_state_mut().add_structure_converter(
@@ -96,9 +96,9 @@ pub(crate) fn rename_block(
     );
 }
 
-pub(crate) fn rename_item(
+pub fn rename_item(
     version: impl Into<DataVersion>,
-    renamer: impl 'static + Copy + Fn(&JavaStr) -> Option<JavaString>,
+    renamer: impl 'static + Copy + Send + Sync + Fn(&JavaStr) -> Option<JavaString>,
 ) {
     types::item_name_mut().add_structure_converter(
         version,
@@ -112,9 +112,9 @@ pub(crate) fn rename_item(
     );
 }
 
-pub(crate) fn rename_advancement(
+pub fn rename_advancement(
     version: impl Into<DataVersion>,
-    renamer: impl 'static + Copy + Fn(&JavaStr) -> Option<JavaString>,
+    renamer: impl 'static + Copy + Send + Sync + Fn(&JavaStr) -> Option<JavaString>,
 ) {
     types::advancements_mut().add_structure_converter(
         version,
@@ -124,9 +124,9 @@ pub(crate) fn rename_advancement(
     );
 }
 
-pub(crate) fn rename_attribute(
+pub fn rename_attribute(
     version: impl Into<DataVersion>,
-    renamer: impl 'static + Copy + Fn(&JavaStr) -> Option<JavaString>,
+    renamer: impl 'static + Copy + Send + Sync + Fn(&JavaStr) -> Option<JavaString>,
 ) {
     let version = version.into();
 
@@ -168,9 +168,9 @@ pub(crate) fn rename_attribute(
         };
 }
 
-pub(crate) fn rename_attribute_old(
+pub fn rename_attribute_old(
     version: impl Into<DataVersion>,
-    renamer: impl 'static + Copy + Fn(&JavaStr) -> Option<JavaString>,
+    renamer: impl 'static + Copy + Send + Sync + Fn(&JavaStr) -> Option<JavaString>,
 ) {
     let version = version.into();
 
@@ -210,10 +210,10 @@ pub(crate) fn rename_attribute_old(
     );
 }
 
-pub(crate) fn rename_criteria(
+pub fn rename_criteria(
     version: impl Into<DataVersion>,
     advancement: &'static str,
-    renamer: impl 'static + Copy + Fn(&JavaStr) -> Option<JavaString>,
+    renamer: impl 'static + Copy + Send + Sync + Fn(&JavaStr) -> Option<JavaString>,
 ) {
     types::advancements_mut().add_structure_converter(
         version,
@@ -229,9 +229,9 @@ pub(crate) fn rename_criteria(
     );
 }
 
-pub(crate) fn rename_enchantment(
+pub fn rename_enchantment(
     version: impl Into<DataVersion>,
-    renamer: impl 'static + Copy + Fn(&JavaStr) -> Option<JavaString>,
+    renamer: impl 'static + Copy + Send + Sync + Fn(&JavaStr) -> Option<JavaString>,
 ) {
     fn rename_enchantment_id(
         enchantment: &mut JCompound,
@@ -273,9 +273,9 @@ pub(crate) fn rename_enchantment(
     );
 }
 
-pub(crate) fn rename_recipe(
+pub fn rename_recipe(
     version: impl Into<DataVersion>,
-    renamer: impl 'static + Copy + Fn(&JavaStr) -> Option<JavaString>,
+    renamer: impl 'static + Copy + Send + Sync + Fn(&JavaStr) -> Option<JavaString>,
 ) {
     types::recipe_mut().add_structure_converter(
         version,
@@ -289,9 +289,9 @@ pub(crate) fn rename_recipe(
     );
 }
 
-pub(crate) fn rename_stat(
+pub fn rename_stat(
     version: impl Into<DataVersion>,
-    renamer: impl 'static + Copy + Fn(&JavaStr) -> Option<JavaString>,
+    renamer: impl 'static + Copy + Send + Sync + Fn(&JavaStr) -> Option<JavaString>,
 ) {
     let version = version.into();
     types::objective_mut().add_structure_converter(version, map_data_converter_func(move |data, _from_version, _to_version| {
@@ -316,9 +316,9 @@ pub(crate) fn rename_stat(
     );
 }
 
-pub(crate) fn rename_option(
+pub fn rename_option(
     version: impl Into<DataVersion>,
-    renamer: impl 'static + Copy + Fn(&JavaStr) -> Option<JavaString>,
+    renamer: impl 'static + Copy + Send + Sync + Fn(&JavaStr) -> Option<JavaString>,
 ) {
     types::options_mut().add_structure_converter(
         version,
@@ -328,9 +328,9 @@ pub(crate) fn rename_option(
     );
 }
 
-pub(crate) fn rename_poi(
+pub fn rename_poi(
     version: impl Into<DataVersion>,
-    renamer: impl 'static + Copy + Fn(&JavaStr) -> Option<JavaString>,
+    renamer: impl 'static + Copy + Send + Sync + Fn(&JavaStr) -> Option<JavaString>,
 ) {
     types::poi_chunk_mut().add_structure_converter(
         version,
@@ -360,7 +360,7 @@ pub(crate) fn rename_poi(
     );
 }
 
-pub(crate) fn simple_rename<'a>(
+pub fn simple_rename<'a>(
     from: &'a (impl AsRef<JavaStr> + ?Sized),
     to: &'a (impl AsRef<JavaStr> + ?Sized),
 ) -> impl 'a + Copy + Fn(&JavaStr) -> Option<JavaString> {
@@ -375,7 +375,7 @@ pub(crate) fn simple_rename<'a>(
     }
 }
 
-pub(crate) fn rename_keys_in_map(
+pub fn rename_keys_in_map(
     typ: impl AbstractValueDataType,
     owning_map: &mut JCompound,
     key: &(impl AsRef<JavaStr> + ?Sized),
@@ -387,7 +387,7 @@ pub(crate) fn rename_keys_in_map(
     }
 }
 
-pub(crate) fn rename_keys(
+pub fn rename_keys(
     typ: impl AbstractValueDataType,
     map: &mut JCompound,
     from_version: DataVersion,
@@ -77,3 +77,7 @@ fn spawn_egg_names() -> &'static AHashMap<u8, &'static str> {
 pub(crate) fn get_spawn_name_from_id(id: u8) -> Option<&'static str> {
     spawn_egg_names().get(&id).copied()
 }
+
+pub(crate) fn get_spawn_egg_ids() -> impl Iterator<Item = (u8, &'static str)> {
+    spawn_egg_names().iter().map(|(id, name)| (*id, *name))
+}
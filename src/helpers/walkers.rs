@@ -1,24 +1,23 @@
-use std::sync::RwLock;
 use world_transmuter_engine::{
-    convert_object_in_map, DataVersion, JCompound, JValue, MapDataWalker, ObjectDataType,
+    convert_object_in_map, AbstractValueDataType, DataVersion, JCompound, JValue, MapDataWalker,
 };
 
-pub(crate) struct GameEventListenerWalker<'a> {
-    game_event_type: &'a RwLock<ObjectDataType<'a>>,
+pub(crate) struct GameEventListenerWalker<T> {
+    game_event_type: T,
 }
 
-impl<'a> GameEventListenerWalker<'a> {
-    pub(crate) fn new(game_event_type: &'a RwLock<ObjectDataType<'a>>) -> Self {
+impl<T> GameEventListenerWalker<T> {
+    pub(crate) fn new(game_event_type: T) -> Self {
         Self { game_event_type }
     }
 }
 
-impl MapDataWalker for GameEventListenerWalker<'_> {
+impl<T: AbstractValueDataType> MapDataWalker for GameEventListenerWalker<T> {
     fn walk(&self, data: &mut JCompound, from_version: DataVersion, to_version: DataVersion) {
         if let Some(JValue::Compound(listener)) = data.get_mut("listener") {
             if let Some(JValue::Compound(event)) = listener.get_mut("event") {
                 convert_object_in_map(
-                    self.game_event_type,
+                    &self.game_event_type,
                     event,
                     "game_event",
                     from_version,
@@ -0,0 +1,27 @@
+use std::cell::Cell;
+
+thread_local! {
+    /// The number of visitors, diagnostic collections and traces active on this thread.
+    static ACTIVE: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Whether anything is watching the conversions on this thread. If not, converting through the types skips the
+/// bookkeeping of the [visitors](crate::visitor), [diagnostics](crate::diagnostics) and [traces](crate::trace).
+pub(crate) fn is_active() -> bool {
+    ACTIVE.get() != 0
+}
+
+/// Runs `f`, with conversions through the types doing their bookkeeping while it runs.
+pub(crate) fn activate<R>(f: impl FnOnce() -> R) -> R {
+    struct DecrementGuard;
+
+    impl Drop for DecrementGuard {
+        fn drop(&mut self) {
+            ACTIVE.set(ACTIVE.get() - 1);
+        }
+    }
+
+    ACTIVE.set(ACTIVE.get() + 1);
+    let _guard = DecrementGuard;
+    f()
+}
@@ -0,0 +1,265 @@
+use crate::iterate_breakpoints;
+use crate::types::TypeLock;
+use java_string::JavaString;
+use std::collections::BTreeSet;
+use world_transmuter_engine::{
+    DataVersion, DynamicDataType, IdDataType, MapDataType, ObjectDataType,
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum RegistrationKind {
+    Converter,
+    Walker,
+    Hook,
+}
+
+/// A converter, walker or hook registered with a data type.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Registration {
+    pub version: DataVersion,
+    pub kind: RegistrationKind,
+    /// The id the converter or walker is specific to, if any.
+    pub id: Option<JavaString>,
+}
+
+/// How one of the data types of the engine runs its converters, hooks and walkers, which differs slightly between them.
+pub trait ConversionOrder {
+    /// Whether the post hooks after each converter run in reverse order.
+    const REVERSE_CONVERTER_POST_HOOKS: bool = true;
+    /// Whether the pre hooks before the walkers run in reverse order.
+    const REVERSE_WALKER_PRE_HOOKS: bool = false;
+    /// Whether hooks and walkers run after the converters.
+    const HAS_WALKERS: bool = true;
+}
+
+impl ConversionOrder for MapDataType<'_> {}
+
+impl ConversionOrder for IdDataType<'_> {
+    const REVERSE_CONVERTER_POST_HOOKS: bool = false;
+    const REVERSE_WALKER_PRE_HOOKS: bool = true;
+}
+
+impl ConversionOrder for ObjectDataType<'_> {
+    const HAS_WALKERS: bool = false;
+}
+
+impl ConversionOrder for DynamicDataType<'_> {}
+
+/// A converter, walker or hook running as part of a conversion pass.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConversionStep {
+    PreHook(Registration),
+    Converter(Registration),
+    PostHook(Registration),
+    /// Walkers with an id only run for objects with that id.
+    Walker(Registration),
+}
+
+/// One pass of a conversion, between two breakpoints or the versions converted from and to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConversionPass {
+    pub from_version: DataVersion,
+    pub to_version: DataVersion,
+    /// The converters, hooks and walkers that run, in order.
+    pub steps: Vec<ConversionStep>,
+}
+
+impl ConversionPass {
+    pub fn converters(&self) -> impl Iterator<Item = &Registration> {
+        self.steps.iter().filter_map(|step| match step {
+            ConversionStep::Converter(registration) => Some(registration),
+            _ => None,
+        })
+    }
+
+    pub fn walkers(&self) -> impl Iterator<Item = &Registration> {
+        self.steps.iter().filter_map(|step| match step {
+            ConversionStep::Walker(registration) => Some(registration),
+            _ => None,
+        })
+    }
+}
+
+/// Lists the converters, walkers and hooks of `typ` that run when converting from `from_version` to `to_version`, split
+/// into the same passes as [`convert_map`](crate::convert_map) and friends split the conversion at breakpoints.
+///
+/// Only the registrations of `typ` itself are listed. Nested types converted by the walkers can be queried separately.
+pub fn conversion_passes<T: ConversionOrder>(
+    typ: &TypeLock<T>,
+    from_version: impl Into<DataVersion>,
+    to_version: impl Into<DataVersion>,
+) -> Vec<ConversionPass> {
+    let registrations = typ.registrations();
+
+    // the engine inserts each converter at the index a binary search by version finds, which for converters of the same
+    // version isn't necessarily after the others
+    let mut converters = Vec::<&Registration>::new();
+    for registration in &registrations {
+        if registration.kind == RegistrationKind::Converter {
+            let index = converters
+                .binary_search_by_key(&registration.version, |converter| converter.version)
+                .unwrap_or_else(|index| index);
+            converters.insert(index, registration);
+        }
+    }
+
+    // walkers and hooks are only ever added, but only those registered at the latest version up to the version being
+    // converted to are used, per id for walkers of specific ids
+    let latest =
+        |kind: RegistrationKind, id: Option<&JavaString>, version: DataVersion| -> Vec<_> {
+            let latest_version = registrations
+                .iter()
+                .filter(|registration| {
+                    registration.kind == kind
+                        && registration.id.as_ref() == id
+                        && registration.version <= version
+                })
+                .map(|registration| registration.version)
+                .max();
+            registrations
+                .iter()
+                .filter(|registration| {
+                    registration.kind == kind
+                        && registration.id.as_ref() == id
+                        && Some(registration.version) == latest_version
+                })
+                .cloned()
+                .collect()
+        };
+    let walker_ids: BTreeSet<_> = registrations
+        .iter()
+        .filter(|registration| registration.kind == RegistrationKind::Walker)
+        .filter_map(|registration| registration.id.as_ref())
+        .collect();
+
+    let mut passes = Vec::new();
+    iterate_breakpoints(from_version, to_version, |from_version, to_version| {
+        let mut steps = Vec::new();
+        let hooks = latest(RegistrationKind::Hook, None, to_version);
+        for converter in &converters {
+            if converter.version <= from_version || converter.version > to_version {
+                continue;
+            }
+            steps.extend(
+                latest(RegistrationKind::Hook, None, converter.version)
+                    .into_iter()
+                    .map(ConversionStep::PreHook),
+            );
+            steps.push(ConversionStep::Converter((*converter).clone()));
+            if T::REVERSE_CONVERTER_POST_HOOKS {
+                steps.extend(hooks.iter().rev().cloned().map(ConversionStep::PostHook));
+            } else {
+                steps.extend(hooks.iter().cloned().map(ConversionStep::PostHook));
+            }
+        }
+
+        if T::HAS_WALKERS {
+            if T::REVERSE_WALKER_PRE_HOOKS {
+                steps.extend(hooks.iter().rev().cloned().map(ConversionStep::PreHook));
+            } else {
+                steps.extend(hooks.iter().cloned().map(ConversionStep::PreHook));
+            }
+            steps.extend(
+                latest(RegistrationKind::Walker, None, to_version)
+                    .into_iter()
+                    .map(ConversionStep::Walker),
+            );
+            for id in &walker_ids {
+                steps.extend(
+                    latest(RegistrationKind::Walker, Some(id), to_version)
+                        .into_iter()
+                        .map(ConversionStep::Walker),
+                );
+            }
+            steps.extend(hooks.iter().rev().cloned().map(ConversionStep::PostHook));
+        }
+
+        passes.push(ConversionPass {
+            from_version,
+            to_version,
+            steps,
+        });
+    });
+    passes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types;
+    use crate::types::MinecraftTypes;
+    use world_transmuter_engine::{map_data_converter_func, JCompound, MapDataHook};
+
+    #[test]
+    fn test_conversion_passes() {
+        let passes = conversion_passes(types::default_types().item_stack_ref(), 3817, 3819);
+        assert_eq!(2, passes.len());
+        assert_eq!(DataVersion::new(3818, 5), passes[0].to_version);
+        assert!(passes[0]
+            .converters()
+            .all(|converter| converter.version <= DataVersion::new(3818, 5)));
+        assert!(passes[0].converters().next().is_some());
+
+        let passes = conversion_passes(types::default_types().tile_entity_ref(), 703, 704);
+        assert_eq!(1, passes.len());
+        assert!(passes[0].walkers().any(|walker| walker.id.as_deref()
+            == Some(JavaString::from("minecraft:chest").as_ref())
+            && walker.version == DataVersion::from(704)));
+    }
+
+    #[test]
+    fn test_conversion_order() {
+        struct NoopHook;
+
+        impl MapDataHook for NoopHook {
+            fn pre_hook(&self, _data: &mut JCompound, _from: DataVersion, _to: DataVersion) {}
+
+            fn post_hook(&self, _data: &mut JCompound, _from: DataVersion, _to: DataVersion) {}
+        }
+
+        let types = MinecraftTypes::builder()
+            .extension(|| {
+                let mut entity = types::entity_mut();
+                let noop = || map_data_converter_func(|_data, _from_version, _to_version| {});
+                entity.add_converter_for_id("mymod:a", 4960, noop());
+                entity.add_structure_hook(4950, NoopHook);
+                entity.add_converter_for_id("mymod:b", 4980, noop());
+                entity.add_converter_for_id("mymod:c", 4960, noop());
+                entity.add_structure_hook(4970, NoopHook);
+            })
+            .build();
+        let passes = conversion_passes(types.entity_ref(), 4900, 5000);
+        assert_eq!(1, passes.len());
+
+        let registration = |kind, version: u32, id: Option<&str>| Registration {
+            version: version.into(),
+            kind,
+            id: id.map(JavaString::from),
+        };
+        let converter = |version, id| {
+            ConversionStep::Converter(registration(RegistrationKind::Converter, version, Some(id)))
+        };
+        let hook = |version| registration(RegistrationKind::Hook, version, None);
+        // the binary search puts c before a, and pre hooks run at the version of each converter
+        assert_eq!(
+            vec![
+                ConversionStep::PreHook(hook(4950)),
+                converter(4960, "mymod:c"),
+                ConversionStep::PostHook(hook(4970)),
+                ConversionStep::PreHook(hook(4950)),
+                converter(4960, "mymod:a"),
+                ConversionStep::PostHook(hook(4970)),
+                ConversionStep::PreHook(hook(4970)),
+                converter(4980, "mymod:b"),
+                ConversionStep::PostHook(hook(4970)),
+                ConversionStep::PreHook(hook(4970)),
+            ],
+            passes[0].steps[..10]
+        );
+        assert!(matches!(passes[0].steps[10], ConversionStep::Walker(_)));
+        assert_eq!(
+            Some(&ConversionStep::PostHook(hook(4970))),
+            passes[0].steps.last()
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use crate::helpers::block_state::{BlockState, BlockStateOwned};
+use crate::helpers::json_parser::{parse_compound, ParseError};
+use crate::types;
+use ahash::AHashMap;
+use java_string::{JavaStr, JavaString};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::RwLock;
+use world_transmuter_engine::{JCompound, JList, JValue};
+
+/// How a modded block from before the flattening in 1.13 is converted, for blocks that aren't in the vanilla tables.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockFlattening {
+    /// The legacy block name, such as `mymod:machine`.
+    pub name: JavaString,
+    /// The metadata the mapping applies to in chunks.
+    pub data: Option<u8>,
+    /// The legacy block state properties the mapping applies to in block state compounds, such as those of falling
+    /// blocks. A mapping with neither metadata nor properties applies to everything of the block that has no mapping of
+    /// its own, and otherwise the mapping for metadata 0 is used.
+    pub legacy_properties: Option<BTreeMap<JavaString, JavaString>>,
+    /// The flattened block state, a compound with a `Name` and optionally `Properties`.
+    pub state: JCompound,
+}
+
+/// The block flattening mappings registered with a [`MinecraftTypes`](crate::types::MinecraftTypes) registry.
+#[derive(Default)]
+pub(crate) struct BlockFlattenings(RwLock<AHashMap<JavaString, Vec<Entry>>>);
+
+struct Entry {
+    data: Option<u8>,
+    legacy_properties: Option<BTreeMap<JavaString, JavaString>>,
+    state: BlockStateOwned,
+}
+
+/// Registers how a modded legacy block is flattened with the current [registry](crate::types), that is the registry
+/// being built inside an extension or made current with [`MinecraftTypes::enter`](crate::types::MinecraftTypes::enter),
+/// or the default registry otherwise. Returns `false` if the state isn't a valid block state.
+///
+/// The mappings are used by the flattening of chunks, where the numeric ids are resolved to names with a
+/// [`LegacyIdMap`](super::id_map::LegacyIdMap), and of block state compounds. A mapping replaces an earlier mapping for
+/// the same name, metadata and properties. The mappings are dropped along with the registry.
+pub fn register_block_flattening(flattening: BlockFlattening) -> bool {
+    let Some(state) = BlockState::from_nbt(&flattening.state).map(|state| state.to_owned()) else {
+        return false;
+    };
+    types::with_types(|types| {
+        let mut registered = types
+            .legacy()
+            .block_flattenings
+            .0
+            .write()
+            .unwrap_or_else(|err| err.into_inner());
+        let entries = registered.entry(flattening.name).or_default();
+        entries.retain(|entry| {
+            entry.data != flattening.data || entry.legacy_properties != flattening.legacy_properties
+        });
+        entries.push(Entry {
+            data: flattening.data,
+            legacy_properties: flattening.legacy_properties,
+            state,
+        });
+    });
+    true
+}
+
+/// Loads block flattening mappings from a JSON file and registers them with the current registry like
+/// [`register_block_flattening`], returning how many were registered. The file
+/// has the form:
+/// ```json
+/// {
+///   "blocks": [
+///     {
+///       "name": "mymod:machine",
+///       "data": 5,
+///       "properties": {"facing": "east", "active": "true"},
+///       "state": {"Name": "mymod:machine", "Properties": {"facing": "east", "lit": "true"}}
+///     }
+///   ]
+/// }
+/// ```
+/// where `data` and `properties` are optional.
+pub fn load_block_flattening(path: impl AsRef<Path>) -> Result<usize, FlatteningLoadError> {
+    let json = std::fs::read(path)?;
+    let json = JavaStr::from_semi_utf8(&json).map_err(|_| FlatteningLoadError::InvalidUtf8)?;
+    let json = parse_compound(json, false)?;
+    let Some(JValue::List(JList::Compound(blocks))) = json.get("blocks") else {
+        return Err(FlatteningLoadError::MissingField("blocks"));
+    };
+
+    let mut flattenings = Vec::with_capacity(blocks.len());
+    for block in blocks {
+        let Some(JValue::String(name)) = block.get("name") else {
+            return Err(FlatteningLoadError::MissingField("name"));
+        };
+        let data = match block.get("data") {
+            Some(data) => Some(
+                data.as_i32()
+                    .and_then(|data| u8::try_from(data).ok())
+                    .filter(|data| *data < 16)
+                    .ok_or(FlatteningLoadError::InvalidField("data"))?,
+            ),
+            None => None,
+        };
+        let legacy_properties = match block.get("properties") {
+            Some(JValue::Compound(properties)) => Some(
+                properties
+                    .iter()
+                    .map(|(key, value)| match value {
+                        JValue::String(value) => Ok((key.clone(), value.clone())),
+                        _ => Err(FlatteningLoadError::InvalidField("properties")),
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+            Some(_) => return Err(FlatteningLoadError::InvalidField("properties")),
+            None => None,
+        };
+        let Some(JValue::Compound(state)) = block.get("state") else {
+            return Err(FlatteningLoadError::MissingField("state"));
+        };
+        if BlockState::from_nbt(state).is_none() {
+            return Err(FlatteningLoadError::InvalidField("state"));
+        }
+        flattenings.push(BlockFlattening {
+            name: name.clone(),
+            data,
+            legacy_properties,
+            state: state.clone(),
+        });
+    }
+
+    let count = flattenings.len();
+    for flattening in flattenings {
+        register_block_flattening(flattening);
+    }
+    Ok(count)
+}
+
+fn lookup(name: &JavaStr, matches: impl Fn(&Entry) -> bool) -> Option<BlockStateOwned> {
+    types::with_types(|types| {
+        let registered = types
+            .legacy()
+            .block_flattenings
+            .0
+            .read()
+            .unwrap_or_else(|err| err.into_inner());
+        let entries = registered.get(name)?;
+        entries
+            .iter()
+            .find(|entry| matches(entry))
+            .or_else(|| {
+                entries
+                    .iter()
+                    .find(|entry| entry.data.is_none() && entry.legacy_properties.is_none())
+            })
+            // like vanilla, fall back to metadata 0
+            .or_else(|| entries.iter().find(|entry| entry.data == Some(0)))
+            .map(|entry| entry.state.clone())
+    })
+}
+
+/// Returns the flattened state of a modded block in a chunk.
+pub(crate) fn flatten_block(name: &JavaStr, data: u8) -> Option<BlockStateOwned> {
+    lookup(name, |entry| entry.data == Some(data))
+}
+
+/// Returns the flattened state of a modded legacy block state compound.
+pub(crate) fn flatten_nbt(nbt: &JCompound) -> Option<JCompound> {
+    let state = BlockState::from_nbt(nbt)?;
+    lookup(state.name, |entry| {
+        entry.legacy_properties.as_ref().is_some_and(|properties| {
+            properties.len() == state.properties.len()
+                && properties
+                    .iter()
+                    .all(|(key, value)| state.get_property(key) == Some(&value[..]))
+        })
+    })
+    .map(|state| state.to_nbt())
+}
+
+#[derive(Debug)]
+pub enum FlatteningLoadError {
+    Io(std::io::Error),
+    Json(ParseError),
+    InvalidUtf8,
+    MissingField(&'static str),
+    InvalidField(&'static str),
+}
+
+impl Display for FlatteningLoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlatteningLoadError::Io(err) => Display::fmt(err, f),
+            FlatteningLoadError::Json(err) => write!(f, "Invalid JSON: {err}"),
+            FlatteningLoadError::InvalidUtf8 => write!(f, "Invalid UTF-8"),
+            FlatteningLoadError::MissingField(field) => write!(f, "Missing field {field}"),
+            FlatteningLoadError::InvalidField(field) => write!(f, "Invalid field {field}"),
+        }
+    }
+}
+
+impl std::error::Error for FlatteningLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FlatteningLoadError::Io(err) => Some(err),
+            FlatteningLoadError::Json(err) => Some(err),
+            FlatteningLoadError::InvalidUtf8
+            | FlatteningLoadError::MissingField(_)
+            | FlatteningLoadError::InvalidField(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for FlatteningLoadError {
+    fn from(value: std::io::Error) -> Self {
+        FlatteningLoadError::Io(value)
+    }
+}
+
+impl From<ParseError> for FlatteningLoadError {
+    fn from(value: ParseError) -> Self {
+        FlatteningLoadError::Json(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::types;
+    use crate::types::MinecraftTypes;
+    use valence_nbt::{compound, jcompound};
+
+    #[test]
+    fn test_load_block_flattening() {
+        let dir = TempDir::new("block-flattening");
+        let path = dir.path().join("flattening.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "blocks": [
+                    {
+                        "name": "testmod:machine",
+                        "state": {"Name": "testmod:machine", "Properties": {"facing": "north"}}
+                    },
+                    {
+                        "name": "testmod:machine",
+                        "data": 5,
+                        "properties": {"facing": "east"},
+                        "state": {"Name": "testmod:machine", "Properties": {"facing": "east"}}
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let modded = MinecraftTypes::new();
+        assert_eq!(2, modded.enter(|| load_block_flattening(&path)).unwrap());
+
+        let east = jcompound! {
+            "Name" => "testmod:machine",
+            "Properties" => jcompound! {
+                "facing" => "east",
+            },
+        };
+        let north = jcompound! {
+            "Name" => "testmod:machine",
+            "Properties" => jcompound! {
+                "facing" => "north",
+            },
+        };
+        modded.enter(|| {
+            assert_eq!(
+                Some(east.clone()),
+                flatten_block("testmod:machine".into(), 5).map(|state| state.to_nbt())
+            );
+            assert_eq!(
+                Some(north.clone()),
+                flatten_block("testmod:machine".into(), 3).map(|state| state.to_nbt())
+            );
+        });
+        // the mappings only belong to the registry they were loaded into
+        assert_eq!(None, flatten_block("testmod:machine".into(), 5));
+
+        let mut legacy_state = jcompound! {
+            "Name" => "testmod:machine",
+            "Properties" => jcompound! {
+                "facing" => "east",
+            },
+        };
+        crate::convert_map(modded.block_state_ref(), &mut legacy_state, 1343, 1450);
+        assert_eq!(east, legacy_state);
+
+        let legacy_state = jcompound! {
+            "Name" => "testmod:machine",
+        };
+        let mut modded_state = legacy_state.clone();
+        crate::convert_map(modded.block_state_ref(), &mut modded_state, 1343, 1450);
+        assert_eq!(north, modded_state);
+        let mut vanilla_state = legacy_state.clone();
+        crate::convert_map(types::block_state_ref(), &mut vanilla_state, 1343, 1450);
+        assert_eq!(legacy_state, vanilla_state);
+    }
+}
@@ -0,0 +1,305 @@
+use crate::helpers::block_flattening_v1450;
+use crate::helpers::block_state::{BlockState, BlockStateOwned};
+use crate::legacy::flattening;
+use crate::remap::{RemapTable, Remapped};
+use java_string::{JavaStr, JavaString};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use world_transmuter_engine::{JCompound, JList, JValue};
+
+thread_local! {
+    static ID_MAP: RefCell<Option<Arc<LegacyIdMap>>> = const { RefCell::new(None) };
+}
+
+/// The numeric block and item ids of a world from before the flattening, as assigned by Forge.
+///
+/// Vanilla ids always take priority, so the map is only used for ids that vanilla doesn't know.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LegacyIdMap {
+    blocks: BTreeMap<u16, JavaString>,
+    items: BTreeMap<i32, JavaString>,
+}
+
+impl LegacyIdMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the id maps Forge stores in the root compound of `level.dat`, either in `FML.Registries` or, before 1.8,
+    /// in `FML.ItemData`. Returns `None` if the world has no id maps.
+    pub fn from_level_dat(root: &JCompound) -> Option<Self> {
+        let Some(JValue::Compound(fml)) = root.get("FML") else {
+            return None;
+        };
+        let mut map = Self::new();
+
+        if let Some(JValue::Compound(registries)) = fml.get("Registries") {
+            for (registry, is_block) in [("minecraft:blocks", true), ("minecraft:items", false)] {
+                let Some(JValue::Compound(registry)) = registries.get(registry) else {
+                    continue;
+                };
+                let Some(JValue::List(JList::Compound(ids))) = registry.get("ids") else {
+                    continue;
+                };
+                for (name, id) in ids.iter().filter_map(read_entry) {
+                    map.insert(is_block, id, name);
+                }
+            }
+        } else if let Some(JValue::List(JList::Compound(item_data))) = fml.get("ItemData") {
+            // the first character of the name is \u0001 for blocks and \u0002 for items
+            for (name, id) in item_data.iter().filter_map(read_entry) {
+                if let Some(name) = name.strip_prefix('\u{1}') {
+                    map.insert(true, id, name);
+                } else if let Some(name) = name.strip_prefix('\u{2}') {
+                    map.insert(false, id, name);
+                }
+            }
+        }
+
+        Some(map)
+    }
+
+    fn insert(&mut self, is_block: bool, id: i32, name: &JavaStr) {
+        let name = if name.contains(':') {
+            name.to_owned()
+        } else {
+            JavaString::from("minecraft:") + name
+        };
+        if is_block {
+            if let Ok(id) = u16::try_from(id) {
+                self.insert_block(id, name);
+            }
+        } else {
+            self.insert_item(id, name);
+        }
+    }
+
+    /// Sets the name of a block id, which is at most 4095.
+    pub fn insert_block(&mut self, id: u16, name: impl Into<JavaString>) {
+        self.blocks.insert(id, name.into());
+    }
+
+    pub fn insert_item(&mut self, id: i32, name: impl Into<JavaString>) {
+        self.items.insert(id, name.into());
+    }
+
+    pub fn block_name(&self, id: u16) -> Option<&JavaStr> {
+        self.blocks.get(&id).map(|name| &name[..])
+    }
+
+    /// Returns the name of an item id. Forge gives the item of a block the block's id, so this falls back to the block
+    /// names.
+    pub fn item_name(&self, id: i32) -> Option<&JavaStr> {
+        self.items
+            .get(&id)
+            .or_else(|| u16::try_from(id).ok().and_then(|id| self.blocks.get(&id)))
+            .map(|name| &name[..])
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty() && self.items.is_empty()
+    }
+
+    /// Renames the blocks and items in the map using the rules of `table`, so that modded ids are converted straight to
+    /// their new names. Blocks and items that the table deletes are removed from the map, so that they become air.
+    pub fn remap(&mut self, table: &RemapTable) {
+        fn remap_names<K: Ord>(
+            names: &mut BTreeMap<K, JavaString>,
+            lookup: impl Fn(&JavaStr) -> Option<Remapped>,
+        ) {
+            names.retain(|_, name| match lookup(name) {
+                Some(Remapped::Rename(new_name)) => {
+                    *name = new_name;
+                    true
+                }
+                Some(Remapped::Delete) => false,
+                None => true,
+            });
+        }
+
+        remap_names(&mut self.blocks, |name| table.lookup_block(name));
+        remap_names(&mut self.items, |name| table.lookup_item(name));
+    }
+}
+
+fn read_entry(entry: &JCompound) -> Option<(&JavaStr, i32)> {
+    let Some(JValue::String(name)) = entry.get("K") else {
+        return None;
+    };
+    Some((name, entry.get("V")?.as_i32()?))
+}
+
+/// Runs `f`, resolving numeric ids that vanilla doesn't know with `map` while converting data on this thread from
+/// before data versions 102 (for items) and 1451 (for blocks in chunks). The map is shared, so that it can be reused
+/// for every file of a world without copying it.
+pub fn with_id_map<R>(map: Arc<LegacyIdMap>, f: impl FnOnce() -> R) -> R {
+    struct RestoreGuard(Option<Arc<LegacyIdMap>>);
+
+    impl Drop for RestoreGuard {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            ID_MAP.with_borrow_mut(|id_map| *id_map = previous);
+        }
+    }
+
+    let _guard = RestoreGuard(ID_MAP.with_borrow_mut(|id_map| id_map.replace(map)));
+    f()
+}
+
+fn current() -> Option<Arc<LegacyIdMap>> {
+    ID_MAP.with_borrow(|id_map| id_map.clone())
+}
+
+pub(crate) fn item_name(id: i32) -> Option<JavaString> {
+    current()?.item_name(id).map(JavaStr::to_owned)
+}
+
+/// Returns the state of a block id and metadata packed as `id << 4 | data`, if vanilla doesn't know the block. Vanilla
+/// blocks with metadata vanilla doesn't know are left to become air. Blocks without a registered
+/// [flattening](super::flattening) keep their name and lose their metadata.
+pub(crate) fn block_state(state_id: u16) -> Option<BlockStateOwned> {
+    let block_id = state_id >> 4;
+    if block_id <= 255 && block_flattening_v1450::get_state_for_id_raw(block_id << 4).is_some() {
+        return None;
+    }
+    let id_map = current()?;
+    let name = id_map.block_name(state_id >> 4)?;
+    Some(
+        flattening::flatten_block(name, (state_id & 15) as u8).unwrap_or_else(|| BlockStateOwned {
+            name: name.to_owned(),
+            properties: BTreeMap::new(),
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types;
+    use valence_nbt::{compound, jcompound};
+    use world_transmuter_engine::DataVersion;
+
+    #[test]
+    fn test_modded_ids() {
+        let level_dat = jcompound! {
+            "Data" => JCompound::new(),
+            "FML" => jcompound! {
+                "Registries" => jcompound! {
+                    "minecraft:blocks" => jcompound! {
+                        "ids" => JList::Compound(vec![
+                            jcompound! {
+                                "K" => "minecraft:stone",
+                                "V" => 1,
+                            },
+                            jcompound! {
+                                "K" => "mymod:copper_ore",
+                                "V" => 2043,
+                            },
+                        ]),
+                    },
+                    "minecraft:items" => jcompound! {
+                        "ids" => JList::Compound(vec![
+                            jcompound! {
+                                "K" => "mymod:copper",
+                                "V" => 4096,
+                            },
+                        ]),
+                    },
+                },
+            },
+        };
+        let mut id_map = LegacyIdMap::from_level_dat(&level_dat).unwrap();
+        id_map.remap(&RemapTable::new().rename_item("mymod:copper", "minecraft:copper_ingot"));
+        let id_map = Arc::new(id_map);
+
+        let mut item = jcompound! {
+            "id" => 4096i16,
+            "Count" => 1i8,
+        };
+        let mut ore_item = jcompound! {
+            "id" => 2043i16,
+            "Count" => 1i8,
+        };
+        with_id_map(id_map.clone(), || {
+            crate::convert_map(types::item_stack_ref(), &mut item, 101, 102);
+            crate::convert_map(types::item_stack_ref(), &mut ore_item, 101, 102);
+        });
+        assert_eq!(
+            Some(&JValue::from("minecraft:copper_ingot")),
+            item.get("id")
+        );
+        assert_eq!(Some(&JValue::from("mymod:copper_ore")), ore_item.get("id"));
+
+        // block 2043 needs the Add array
+        let mut blocks = vec![0i8; 4096];
+        let mut add = vec![0i8; 2048];
+        blocks[0] = (2043 & 255) as u8 as i8;
+        add[0] = (2043 >> 8) as i8;
+        let mut chunk = jcompound! {
+            "Level" => jcompound! {
+                "xPos" => 0,
+                "zPos" => 0,
+                "Sections" => JList::Compound(vec![jcompound! {
+                    "Y" => 0i8,
+                    "Blocks" => blocks,
+                    "Add" => add,
+                    "Data" => vec![0i8; 2048],
+                }]),
+            },
+        };
+        with_id_map(id_map.clone(), || {
+            crate::convert_map(
+                types::chunk_ref(),
+                &mut chunk,
+                1343,
+                DataVersion::new(1451, 1),
+            );
+        });
+        let Some(JValue::Compound(level)) = chunk.get("Level") else {
+            panic!("chunk lost its Level tag");
+        };
+        let Some(JValue::List(JList::Compound(sections))) = level.get("Sections") else {
+            panic!("chunk lost its sections");
+        };
+        let Some(JValue::List(JList::Compound(palette))) = sections[0].get("Palette") else {
+            panic!("section has no palette");
+        };
+        assert!(palette
+            .iter()
+            .any(|state| state.get("Name") == Some(&JValue::from("mymod:copper_ore"))));
+    }
+
+    #[test]
+    fn test_vanilla_ids_ignore_id_map() {
+        // metadata vanilla doesn't know falls back to the vanilla block, not to the id map
+        let mut id_map = LegacyIdMap::new();
+        id_map.insert_block(1, "mymod:marble");
+        id_map.insert_block(175, "mymod:tall_fern");
+
+        let mut blocks = vec![0i8; 4096];
+        let mut data = vec![0i8; 2048];
+        blocks[0] = 1;
+        blocks[1] = 175u8 as i8;
+        data[0] = 15 | (6 << 4);
+        let chunk = jcompound! {
+            "Level" => jcompound! {
+                "xPos" => 0,
+                "zPos" => 0,
+                "Sections" => JList::Compound(vec![jcompound! {
+                    "Y" => 0i8,
+                    "Blocks" => blocks,
+                    "Data" => data,
+                }]),
+            },
+        };
+        let convert = |chunk: &mut JCompound| {
+            crate::convert_map(types::chunk_ref(), chunk, 1343, DataVersion::new(1451, 1))
+        };
+        let mut vanilla_chunk = chunk.clone();
+        convert(&mut vanilla_chunk);
+        let mut modded_chunk = chunk;
+        with_id_map(Arc::new(id_map), || convert(&mut modded_chunk));
+        assert_eq!(vanilla_chunk, modded_chunk);
+    }
+}
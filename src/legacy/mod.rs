@@ -0,0 +1,372 @@
+use crate::helpers::block_state::{BlockState, BlockStateOwned};
+use crate::helpers::{
+    block_flattening_v1450, flatten_item_stack_v1451, item_name_v102, spawn_egg_name_v105,
+};
+use crate::types::MinecraftTypes;
+use crate::{convert_map, types};
+use ahash::AHashMap;
+use java_string::{JavaStr, JavaString};
+use std::sync::OnceLock;
+use world_transmuter_engine::{DataVersion, JCompound};
+
+pub mod flattening;
+pub mod id_map;
+
+/// The version the block flattening table converts to.
+const FLATTENED_VERSION: u32 = 1450;
+
+/// A block as stored before the flattening in 1.13, in the `Blocks` and `Data` arrays of a chunk section.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct LegacyBlock {
+    pub id: u8,
+    /// The 4 bit metadata value.
+    pub data: u8,
+}
+
+impl LegacyBlock {
+    /// The id and metadata packed as `id << 4 | data`, as used by the flattening table.
+    pub fn packed(self) -> u16 {
+        (self.id as u16) << 4 | self.data as u16
+    }
+
+    pub fn from_packed(packed: u16) -> Self {
+        Self {
+            id: (packed >> 4) as u8,
+            data: (packed & 15) as u8,
+        }
+    }
+}
+
+/// How well a [`LegacyBlock`] returned by [`unflatten_block_state`] represents the block state.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum LegacyMatch {
+    /// The block has no legacy counterpart, and air was returned instead.
+    Missing,
+    /// The block existed, but some of its properties didn't match any legacy block, so the legacy block of the same
+    /// block that matches the most properties was returned.
+    Partial,
+    /// The legacy block flattens to this block state. Properties that weren't stored in the metadata, such as
+    /// `waterlogged`, are ignored.
+    Exact,
+}
+
+/// The legacy tables of a [`MinecraftTypes`] registry, which depend on its converters.
+#[derive(Default)]
+pub(crate) struct LegacyTables {
+    unflatten_index: OnceLock<UnflattenIndex>,
+    block_flattenings: flattening::BlockFlattenings,
+}
+
+impl LegacyTables {
+    /// Returns the index used by [`unflatten_block_state`], building it with `types` the first time. The index isn't
+    /// built under a lock, so threads that need it at the same time may each build it, and all but one are dropped.
+    fn unflatten_index(&self, types: &MinecraftTypes) -> &UnflattenIndex {
+        if let Some(index) = self.unflatten_index.get() {
+            return index;
+        }
+        let _ = self.unflatten_index.set(UnflattenIndex::new(types));
+        self.unflatten_index.get().unwrap()
+    }
+}
+
+/// The flattened block states of the legacy blocks, converted to the newest version of a registry.
+struct UnflattenIndex {
+    version: DataVersion,
+    by_name: AHashMap<JavaString, Vec<(u16, BlockStateOwned)>>,
+}
+
+impl UnflattenIndex {
+    fn new(types: &MinecraftTypes) -> Self {
+        let version = types
+            .block_state_ref()
+            .registrations()
+            .iter()
+            .map(|registration| registration.version)
+            .fold(DataVersion::from(FLATTENED_VERSION), DataVersion::max);
+        let mut by_name: AHashMap<JavaString, Vec<(u16, BlockStateOwned)>> = AHashMap::new();
+        for &id in block_flattening_v1450::get_registered_ids() {
+            let mut state = block_flattening_v1450::get_nbt_for_id(id);
+            convert_map(
+                types.block_state_ref(),
+                &mut state,
+                FLATTENED_VERSION,
+                version,
+            );
+            let Some(state) = BlockState::from_nbt(&state).map(|state| state.to_owned()) else {
+                continue;
+            };
+            let candidates = by_name.entry(state.name.clone()).or_default();
+            // several legacy blocks can flatten to the same state, prefer the one with the smallest id
+            if !candidates.iter().any(|(_, candidate)| *candidate == state) {
+                candidates.push((id, state));
+            }
+        }
+        Self { version, by_name }
+    }
+}
+
+/// Returns the legacy id and metadata of a block state from data version `version`, for exporting to versions before the
+/// flattening in 1.13 (data version 1451).
+///
+/// Blocks that were renamed or changed after the flattening are recognized under their name and properties at
+/// `version`, by converting the state to the newest version of the current [registry](crate::types) first. Tile entity
+/// data, which some legacy blocks such as flower pots and beds needed, isn't recreated. Blocks that didn't exist before
+/// the flattening are replaced with air, and [`LegacyMatch::Missing`] is returned.
+pub fn unflatten_block_state(
+    state: &JCompound,
+    version: impl Into<DataVersion>,
+) -> (LegacyBlock, LegacyMatch) {
+    const AIR: LegacyBlock = LegacyBlock { id: 0, data: 0 };
+
+    let version = version.into().max(DataVersion::from(FLATTENED_VERSION));
+    types::with_types(|types| {
+        let index = types.legacy().unflatten_index(types);
+        let mut converted;
+        let state = if version < index.version {
+            converted = state.clone();
+            convert_map(
+                types.block_state_ref(),
+                &mut converted,
+                version,
+                index.version,
+            );
+            &converted
+        } else {
+            state
+        };
+        let Some(state) = BlockState::from_nbt(state) else {
+            return (AIR, LegacyMatch::Missing);
+        };
+
+        let Some(candidates) = index.by_name.get(state.name) else {
+            return (AIR, LegacyMatch::Missing);
+        };
+        let mut best = None;
+        for (id, candidate) in candidates {
+            let matching = candidate
+                .properties
+                .iter()
+                .filter(|(key, value)| state.get_property(*key) == Some(&value[..]))
+                .count();
+            let exact = matching == candidate.properties.len();
+            if best.is_none_or(|(_, best_exact, best_matching)| {
+                (exact, matching) > (best_exact, best_matching)
+            }) {
+                best = Some((*id, exact, matching));
+            }
+        }
+        match best {
+            Some((id, exact, _)) => (
+                LegacyBlock::from_packed(id),
+                if exact {
+                    LegacyMatch::Exact
+                } else {
+                    LegacyMatch::Partial
+                },
+            ),
+            None => (AIR, LegacyMatch::Missing),
+        }
+    })
+}
+
+struct ReverseTables {
+    item_ids: AHashMap<&'static JavaStr, i32>,
+    potion_ids: AHashMap<&'static JavaStr, i32>,
+    spawn_egg_ids: AHashMap<&'static str, u8>,
+    unflattened_items: AHashMap<&'static JavaStr, (&'static JavaStr, u8)>,
+}
+
+// Where several legacy ids map to the same name, the smallest one is used
+fn reverse_tables() -> &'static ReverseTables {
+    static REVERSE_TABLES: OnceLock<ReverseTables> = OnceLock::new();
+    REVERSE_TABLES.get_or_init(|| {
+        let mut item_ids = AHashMap::new();
+        for (id, name) in item_name_v102::get_item_ids() {
+            let entry = item_ids.entry(name).or_insert(id);
+            *entry = (*entry).min(id);
+        }
+        let mut potion_ids = AHashMap::new();
+        for (id, name) in item_name_v102::get_potion_ids() {
+            let entry = potion_ids.entry(name).or_insert(id);
+            *entry = (*entry).min(id);
+        }
+        let mut spawn_egg_ids = AHashMap::new();
+        for (id, name) in spawn_egg_name_v105::get_spawn_egg_ids() {
+            let entry = spawn_egg_ids.entry(name).or_insert(id);
+            *entry = (*entry).min(id);
+        }
+        let mut unflattened_items = AHashMap::new();
+        for (old_name, damage, new_name) in flatten_item_stack_v1451::get_flattened_items() {
+            let entry = unflattened_items
+                .entry(new_name)
+                .or_insert((old_name, damage));
+            *entry = (*entry).min((old_name, damage));
+        }
+        ReverseTables {
+            item_ids,
+            potion_ids,
+            spawn_egg_ids,
+            unflattened_items,
+        }
+    })
+}
+
+/// Returns the name of a numeric item or block id, as stored in item stacks before data version 102.
+pub fn item_name_for_id(id: i32) -> Option<&'static JavaStr> {
+    item_name_v102::get_name_from_id(id)
+}
+
+/// Returns the numeric id of an item or block name from before data version 102. The name must be namespaced.
+pub fn item_id_for_name(name: &(impl AsRef<JavaStr> + ?Sized)) -> Option<i32> {
+    reverse_tables().item_ids.get(name.as_ref()).copied()
+}
+
+/// Returns the potion type of the damage value of a potion item from before data version 102. Only the lower 7 bits of
+/// the damage value select the potion type, the other bits select whether the potion is a splash potion.
+pub fn potion_name_for_damage(damage: i32) -> Option<&'static JavaStr> {
+    item_name_v102::get_potion_name_from_id(damage)
+}
+
+/// Returns the smallest damage value of a potion item with the potion type from before data version 102.
+pub fn potion_damage_for_name(name: &(impl AsRef<JavaStr> + ?Sized)) -> Option<i32> {
+    reverse_tables().potion_ids.get(name.as_ref()).copied()
+}
+
+/// Returns the entity name, such as `Villager`, of the damage value of a spawn egg from before data version 105.
+pub fn spawn_egg_entity_for_damage(damage: u8) -> Option<&'static str> {
+    spawn_egg_name_v105::get_spawn_name_from_id(damage)
+}
+
+/// Returns the damage value of a spawn egg from before data version 105 that spawns the entity with the given legacy
+/// name, such as `Villager`.
+pub fn spawn_egg_damage_for_entity(entity: &str) -> Option<u8> {
+    reverse_tables().spawn_egg_ids.get(entity).copied()
+}
+
+/// Returns the item name that an item with the given name and damage value was renamed to by the flattening in 1.13,
+/// for items whose variants were distinguished by their damage value. Unknown damage values fall back to damage value
+/// 0 like in the game. Returns `None` for items that kept their name and damage value.
+pub fn flatten_item(name: &(impl AsRef<JavaStr> + ?Sized), damage: u8) -> Option<&'static JavaStr> {
+    flatten_item_stack_v1451::flatten_item(name, damage)
+}
+
+/// The reverse of [`flatten_item`], returning the name and damage value an item had before the flattening in 1.13.
+/// Returns `None` for items that didn't need flattening.
+pub fn unflatten_item(name: &(impl AsRef<JavaStr> + ?Sized)) -> Option<(&'static JavaStr, u8)> {
+    reverse_tables()
+        .unflattened_items
+        .get(name.as_ref())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use valence_nbt::{compound, jcompound};
+
+    #[test]
+    fn test_unflatten_block_state() {
+        let granite = jcompound! {
+            "Name" => "minecraft:polished_granite",
+        };
+        assert_eq!(
+            (LegacyBlock { id: 1, data: 2 }, LegacyMatch::Exact),
+            unflatten_block_state(&granite, 1451)
+        );
+
+        let stairs = jcompound! {
+            "Name" => "minecraft:oak_stairs",
+            "Properties" => jcompound! {
+                "facing" => "west",
+                "half" => "bottom",
+                "shape" => "straight",
+                "waterlogged" => "false",
+            },
+        };
+        assert_eq!(
+            (LegacyBlock { id: 53, data: 1 }, LegacyMatch::Exact),
+            unflatten_block_state(&stairs, 3700)
+        );
+
+        // renamed from grass in 23w46a
+        let short_grass = jcompound! {
+            "Name" => "minecraft:short_grass",
+        };
+        assert_eq!(
+            (LegacyBlock { id: 31, data: 1 }, LegacyMatch::Exact),
+            unflatten_block_state(&short_grass, 3700)
+        );
+
+        let deepslate = jcompound! {
+            "Name" => "minecraft:deepslate",
+            "Properties" => jcompound! {
+                "axis" => "y",
+            },
+        };
+        assert_eq!(
+            (LegacyBlock { id: 0, data: 0 }, LegacyMatch::Missing),
+            unflatten_block_state(&deepslate, 3700)
+        );
+    }
+
+    #[test]
+    fn test_unflatten_block_state_registry() {
+        let types = MinecraftTypes::builder()
+            .extension(|| {
+                crate::rename::rename_block(
+                    4000,
+                    crate::rename::simple_rename("minecraft:polished_granite", "mymod:granite"),
+                )
+            })
+            .build();
+        let granite = jcompound! {
+            "Name" => "mymod:granite",
+        };
+        assert_eq!(
+            (LegacyBlock { id: 1, data: 2 }, LegacyMatch::Exact),
+            types.enter(|| unflatten_block_state(&granite, 4000))
+        );
+        assert_eq!(
+            (LegacyBlock { id: 0, data: 0 }, LegacyMatch::Missing),
+            unflatten_block_state(&granite, 4000)
+        );
+
+        // the old name is converted to the new one first
+        let old_granite = jcompound! {
+            "Name" => "minecraft:polished_granite",
+        };
+        assert_eq!(
+            (LegacyBlock { id: 1, data: 2 }, LegacyMatch::Exact),
+            types.enter(|| unflatten_block_state(&old_granite, 3700))
+        );
+    }
+
+    #[test]
+    fn test_item_tables() {
+        assert_eq!(
+            Some(JavaStr::from_str("minecraft:stone")),
+            item_name_for_id(1)
+        );
+        assert_eq!(Some(1), item_id_for_name("minecraft:stone"));
+        assert_eq!(Some(452), item_id_for_name("minecraft:iron_nugget"));
+
+        assert_eq!(
+            Some(JavaStr::from_str("minecraft:strong_healing")),
+            potion_name_for_damage(16421)
+        );
+        assert_eq!(Some(37), potion_damage_for_name("minecraft:strong_healing"));
+
+        assert_eq!(Some("Villager"), spawn_egg_entity_for_damage(120));
+        assert_eq!(Some(120), spawn_egg_damage_for_entity("Villager"));
+
+        assert_eq!(
+            Some(JavaStr::from_str("minecraft:red_wool")),
+            flatten_item("minecraft:wool", 14)
+        );
+        assert_eq!(
+            Some((JavaStr::from_str("minecraft:wool"), 14)),
+            unflatten_item("minecraft:red_wool")
+        );
+        assert_eq!(None, unflatten_item("minecraft:diamond"));
+    }
+}
@@ -1,21 +1,45 @@
 #![allow(clippy::collapsible_else_if)]
 #![allow(unused)]
 
+use crate::diagnostics::{Diagnostic, LossyConversionError};
+use crate::trace::TraceEntry;
 use crate::version_names::get_breakpoints;
 use world_transmuter_engine::{
     AbstractDynamicDataType, AbstractMapDataType, AbstractValueDataType, DataVersion, JCompound,
     JValue, JValueMut,
 };
 
+pub mod alpha;
+pub mod detect;
+pub mod diagnostics;
 mod helpers;
+mod instrument;
+pub mod introspect;
+pub mod legacy;
+pub mod mcregion;
+pub mod nbt_io;
+pub mod players;
+pub mod region;
+pub mod remap;
+pub mod schematic;
+pub mod trace;
 pub mod types;
 pub mod version_names;
 mod versions;
+pub mod visitor;
+pub mod world;
+
+#[cfg(test)]
+mod test_util;
 
 pub mod json {
     pub use crate::helpers::json_parser::*;
 }
 
+pub mod rename {
+    pub use crate::helpers::rename::*;
+}
+
 pub fn convert_map(
     typ: impl AbstractMapDataType,
     data: &mut JCompound,
@@ -49,6 +73,79 @@ pub fn convert_dyn(
     });
 }
 
+pub fn convert_map_with_diagnostics(
+    typ: impl AbstractMapDataType,
+    data: &mut JCompound,
+    from_version: impl Into<DataVersion>,
+    to_version: impl Into<DataVersion>,
+) -> Vec<Diagnostic> {
+    diagnostics::collect(|| convert_map(typ, data, from_version, to_version)).1
+}
+
+pub fn convert_value_with_diagnostics(
+    typ: impl AbstractValueDataType,
+    data: &mut JValueMut,
+    from_version: impl Into<DataVersion>,
+    to_version: impl Into<DataVersion>,
+) -> Vec<Diagnostic> {
+    diagnostics::collect(|| convert_value(typ, data, from_version, to_version)).1
+}
+
+pub fn convert_dyn_with_diagnostics(
+    typ: impl AbstractDynamicDataType,
+    data: &mut JValue,
+    from_version: impl Into<DataVersion>,
+    to_version: impl Into<DataVersion>,
+) -> Vec<Diagnostic> {
+    diagnostics::collect(|| convert_dyn(typ, data, from_version, to_version)).1
+}
+
+/// Converts the data, but fails without modifying it if any converter had to throw away data or guess how to convert
+/// it.
+pub fn convert_map_strict(
+    typ: impl AbstractMapDataType,
+    data: &mut JCompound,
+    from_version: impl Into<DataVersion>,
+    to_version: impl Into<DataVersion>,
+) -> Result<(), LossyConversionError> {
+    diagnostics::run_strict(data, |data| {
+        convert_map(typ, data, from_version, to_version)
+    })
+}
+
+/// Converts the data, but fails without modifying it if any converter had to throw away data or guess how to convert
+/// it.
+pub fn convert_dyn_strict(
+    typ: impl AbstractDynamicDataType,
+    data: &mut JValue,
+    from_version: impl Into<DataVersion>,
+    to_version: impl Into<DataVersion>,
+) -> Result<(), LossyConversionError> {
+    diagnostics::run_strict(data, |data| {
+        convert_dyn(typ, data, from_version, to_version)
+    })
+}
+
+/// Converts the data, returning the changes made by each converter or hook that changed something.
+pub fn convert_map_traced(
+    typ: impl AbstractMapDataType,
+    data: &mut JCompound,
+    from_version: impl Into<DataVersion>,
+    to_version: impl Into<DataVersion>,
+) -> Vec<TraceEntry> {
+    trace::collect(|| convert_map(typ, data, from_version, to_version)).1
+}
+
+/// Converts the data, returning the changes made by each converter or hook that changed something.
+pub fn convert_dyn_traced(
+    typ: impl AbstractDynamicDataType,
+    data: &mut JValue,
+    from_version: impl Into<DataVersion>,
+    to_version: impl Into<DataVersion>,
+) -> Vec<TraceEntry> {
+    trace::collect(|| convert_dyn(typ, data, from_version, to_version)).1
+}
+
 fn iterate_breakpoints(
     from_version: impl Into<DataVersion>,
     to_version: impl Into<DataVersion>,
@@ -0,0 +1,550 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use tracing::level_filters::LevelFilter;
+use tracing::Level;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+use world_transmuter::detect::{detect_version, Confidence};
+use world_transmuter::legacy::flattening::load_block_flattening;
+use world_transmuter::nbt_io::{read_nbt_file, read_snbt, write_nbt_file, write_snbt};
+use world_transmuter::players::PlayerUuids;
+use world_transmuter::schematic::litematica::upgrade_litematica_schematic;
+use world_transmuter::schematic::mcedit::{
+    detect_mcedit_version, mcedit_to_sponge, mcedit_to_structure,
+};
+use world_transmuter::schematic::sponge::upgrade_sponge_schematic;
+use world_transmuter::types::TypeKind;
+use world_transmuter::version_names::{
+    find_version, get_versions, latest, load_versions, VersionType,
+};
+use world_transmuter::world::{convert_level, WorldUpgrader};
+use world_transmuter::{convert_map, diagnostics, trace, types};
+use world_transmuter_engine::{JCompound, JValue};
+
+#[derive(Parser)]
+#[command(
+    name = "world-transmuter",
+    version,
+    about = "Upgrades Minecraft data to newer versions"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// Load additional version names from a version manifest, a version.json or a jar file
+    #[arg(long, value_name = "FILE", global = true)]
+    load_versions: Vec<PathBuf>,
+    /// Load how modded blocks from before 1.13 are flattened from a JSON file
+    #[arg(long, value_name = "FILE", global = true)]
+    legacy_blocks: Vec<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Upgrade every file of a world directory in place
+    World {
+        world_dir: PathBuf,
+        /// The version to upgrade to, as a data version or a version name. Defaults to the latest version
+        #[arg(long)]
+        to: Option<String>,
+        /// Fail instead of throwing away data or guessing how to convert it
+        #[arg(long)]
+        strict: bool,
+        /// Resolve the names of player files from before 1.7.6 with a usercache.json, whitelist.json or ops.json file.
+        /// Defaults to the ones of the server the world is in
+        #[arg(long, value_name = "FILE")]
+        player_uuids: Vec<PathBuf>,
+        /// Give players that aren't in the player lists their offline UUID, for servers in offline mode. Otherwise
+        /// their files are left in place
+        #[arg(long)]
+        offline_players: bool,
+    },
+    /// Upgrade a single NBT file, such as a .nbt structure or a .dat file
+    File {
+        file: PathBuf,
+        #[arg(long = "type", value_name = "TYPE", value_enum)]
+        typ: DataType,
+        /// The version the file is from. Defaults to the DataVersion stored in the file
+        #[arg(long)]
+        from: Option<String>,
+        #[arg(long)]
+        to: Option<String>,
+        /// Fail instead of throwing away data or guessing how to convert it
+        #[arg(long)]
+        strict: bool,
+        /// Print the changes made by each converter to stderr
+        #[arg(long)]
+        trace: bool,
+        /// Where to write the upgraded file. Defaults to overwriting the input file
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Upgrade a WorldEdit .schem or Litematica .litematic schematic, or convert an MCEdit .schematic
+    Schematic {
+        file: PathBuf,
+        /// The version an MCEdit schematic is from. Defaults to a version guessed from its block entities and entities.
+        /// Other schematics store their version
+        #[arg(long)]
+        from: Option<String>,
+        #[arg(long)]
+        to: Option<String>,
+        /// Where to write the upgraded schematic. Defaults to overwriting the input file, or for a .schematic to a .schem
+        /// file next to it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Upgrade SNBT read from stdin and write it to stdout
+    Snbt {
+        #[arg(long = "type", value_name = "TYPE", value_enum)]
+        typ: DataType,
+        #[arg(long)]
+        from: Option<String>,
+        #[arg(long)]
+        to: Option<String>,
+        /// Fail instead of throwing away data or guessing how to convert it
+        #[arg(long)]
+        strict: bool,
+        /// Print the changes made by each converter to stderr
+        #[arg(long)]
+        trace: bool,
+    },
+    /// List the known versions and their data versions
+    Versions {
+        /// Only list releases
+        #[arg(long)]
+        releases: bool,
+    },
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum DataType {
+    Level,
+    Player,
+    Chunk,
+    EntityChunk,
+    PoiChunk,
+    Structure,
+    Entity,
+    ItemStack,
+    TileEntity,
+    Hotbar,
+    Options,
+    Stats,
+    Advancements,
+    SavedDataRaids,
+    SavedDataScoreboard,
+    SavedDataMapData,
+    SavedDataMapIndex,
+    SavedDataCommandStorage,
+    SavedDataForcedChunks,
+    SavedDataRandomSequences,
+    SavedDataStructureFeatureIndices,
+}
+
+impl DataType {
+    fn kind(self) -> Option<TypeKind> {
+        match self {
+            DataType::Chunk => Some(TypeKind::Chunk),
+            DataType::Entity => Some(TypeKind::Entity),
+            DataType::ItemStack => Some(TypeKind::ItemStack),
+            DataType::TileEntity => Some(TypeKind::TileEntity),
+            _ => None,
+        }
+    }
+
+    /// Whether files of this type store their `DataVersion`. Other data, such as a single item, only has one written
+    /// back if it already had one.
+    fn stores_data_version(self) -> bool {
+        !matches!(
+            self,
+            DataType::Entity | DataType::ItemStack | DataType::TileEntity | DataType::Options
+        )
+    }
+
+    fn convert(self, data: &mut JCompound, from_version: u32, to_version: u32) {
+        match self {
+            DataType::Level => {
+                // level.dat wraps the data in a Data compound
+                if let Some(JValue::Compound(data)) = data.get_mut("Data") {
+                    convert_level(data, from_version, to_version);
+                } else {
+                    convert_level(data, from_version, to_version);
+                }
+            }
+            DataType::Player => convert_map(types::player_ref(), data, from_version, to_version),
+            DataType::Chunk => convert_map(types::chunk_ref(), data, from_version, to_version),
+            DataType::EntityChunk => {
+                convert_map(types::entity_chunk_ref(), data, from_version, to_version)
+            }
+            DataType::PoiChunk => {
+                convert_map(types::poi_chunk_ref(), data, from_version, to_version)
+            }
+            DataType::Structure => {
+                convert_map(types::structure_ref(), data, from_version, to_version)
+            }
+            DataType::Entity => convert_map(types::entity_ref(), data, from_version, to_version),
+            DataType::ItemStack => {
+                convert_map(types::item_stack_ref(), data, from_version, to_version)
+            }
+            DataType::TileEntity => {
+                convert_map(types::tile_entity_ref(), data, from_version, to_version)
+            }
+            DataType::Hotbar => convert_map(types::hotbar_ref(), data, from_version, to_version),
+            DataType::Options => convert_map(types::options_ref(), data, from_version, to_version),
+            DataType::Stats => convert_map(types::stats_ref(), data, from_version, to_version),
+            DataType::Advancements => {
+                convert_map(types::advancements_ref(), data, from_version, to_version)
+            }
+            DataType::SavedDataRaids => convert_map(
+                types::saved_data_raids_ref(),
+                data,
+                from_version,
+                to_version,
+            ),
+            DataType::SavedDataScoreboard => convert_map(
+                types::saved_data_scoreboard_ref(),
+                data,
+                from_version,
+                to_version,
+            ),
+            DataType::SavedDataMapData => convert_map(
+                types::saved_data_map_data_ref(),
+                data,
+                from_version,
+                to_version,
+            ),
+            DataType::SavedDataMapIndex => convert_map(
+                types::saved_data_map_index_ref(),
+                data,
+                from_version,
+                to_version,
+            ),
+            DataType::SavedDataCommandStorage => convert_map(
+                types::saved_data_command_storage_ref(),
+                data,
+                from_version,
+                to_version,
+            ),
+            DataType::SavedDataForcedChunks => convert_map(
+                types::saved_data_forced_chunks_ref(),
+                data,
+                from_version,
+                to_version,
+            ),
+            DataType::SavedDataRandomSequences => convert_map(
+                types::saved_data_random_sequences_ref(),
+                data,
+                from_version,
+                to_version,
+            ),
+            DataType::SavedDataStructureFeatureIndices => convert_map(
+                types::saved_data_structure_feature_indices_ref(),
+                data,
+                from_version,
+                to_version,
+            ),
+        }
+    }
+}
+
+// Versions are parsed after the command line, so that they can use the versions loaded with --load-versions
+fn parse_version(version: Option<String>) -> Result<Option<u32>, String> {
+    version
+        .map(|version| {
+            if let Ok(data_version) = version.parse() {
+                return Ok(data_version);
+            }
+            find_version(&version)
+                .map(|version| version.data_version)
+                .map_err(|err| err.to_string())
+        })
+        .transpose()
+}
+
+fn latest_version() -> u32 {
+    latest().map_or(0, |version| version.data_version)
+}
+
+// The DataVersion of level.dat is stored inside its Data compound.
+fn data_version_holder(typ: DataType, data: &mut JCompound) -> &mut JCompound {
+    if matches!(typ, DataType::Level) && matches!(data.get("Data"), Some(JValue::Compound(_))) {
+        let Some(JValue::Compound(data)) = data.get_mut("Data") else {
+            unreachable!()
+        };
+        data
+    } else {
+        data
+    }
+}
+
+fn upgrade(
+    typ: DataType,
+    data: &mut JCompound,
+    from: Option<u32>,
+    to: Option<u32>,
+    strict: bool,
+    trace: bool,
+) -> Result<(), String> {
+    let holder = data_version_holder(typ, data);
+    let has_data_version = holder.contains_key("DataVersion");
+    // a negative DataVersion is corrupt, so guess the version like when there is none
+    let stored_version = holder
+        .get("DataVersion")
+        .and_then(|version| version.as_i32())
+        .and_then(|version| u32::try_from(version).ok());
+    let from_version = match from.or(stored_version) {
+        Some(from_version) => from_version,
+        None => match typ.kind().and_then(|kind| detect_version(kind, data)) {
+            Some(detected) if detected.confidence >= Confidence::Medium => {
+                eprintln!(
+                    "The data has no DataVersion, assuming {}",
+                    detected.data_version
+                );
+                detected.data_version
+            }
+            _ => return Err("the data has no DataVersion, specify one with --from".to_owned()),
+        },
+    };
+    let to_version = to.unwrap_or_else(latest_version);
+
+    let mut convert = || {
+        diagnostics::collect(|| {
+            if strict {
+                diagnostics::run_strict(data, |data| typ.convert(data, from_version, to_version))
+            } else {
+                typ.convert(data, from_version, to_version);
+                Ok(())
+            }
+        })
+    };
+    // Tracing copies the data before every converter, so only do it when asked to
+    let (result, diagnostics) = if trace {
+        let (result, entries) = trace::collect(convert);
+        for entry in entries {
+            eprintln!("{entry}");
+        }
+        result
+    } else {
+        convert()
+    };
+    for diagnostic in diagnostics {
+        eprintln!("{diagnostic}");
+    }
+    result.map_err(|err| err.to_string())?;
+
+    if has_data_version || typ.stores_data_version() {
+        data_version_holder(typ, data).insert("DataVersion", to_version as i32);
+    }
+    Ok(())
+}
+
+fn run(command: Command) -> Result<(), String> {
+    match command {
+        Command::World {
+            world_dir,
+            to,
+            strict,
+            player_uuids,
+            offline_players,
+        } => {
+            let to_version = parse_version(to)?.unwrap_or_else(latest_version);
+            let mut upgrader = WorldUpgrader::new(&world_dir, to_version)
+                .strict(strict)
+                .offline_players(offline_players);
+            if !player_uuids.is_empty() {
+                let mut uuids = PlayerUuids::new();
+                for path in player_uuids {
+                    uuids
+                        .load(&path)
+                        .map_err(|err| format!("Failed to load {}: {err}", path.display()))?;
+                }
+                upgrader = upgrader.player_uuids(uuids);
+            }
+            let report = upgrader.upgrade().map_err(|err| err.to_string())?;
+            for failure in &report.failures {
+                match failure.chunk {
+                    Some((x, z)) => eprintln!(
+                        "Failed to upgrade chunk {x}, {z} in {}: {}",
+                        failure.path.display(),
+                        failure.error
+                    ),
+                    None => eprintln!(
+                        "Failed to upgrade {}: {}",
+                        failure.path.display(),
+                        failure.error
+                    ),
+                }
+            }
+            for diagnostic in &report.diagnostics {
+                match diagnostic.chunk {
+                    Some((x, z)) => eprintln!(
+                        "In chunk {x}, {z} in {}: {}",
+                        diagnostic.path.display(),
+                        diagnostic.diagnostic
+                    ),
+                    None => eprintln!(
+                        "In {}: {}",
+                        diagnostic.path.display(),
+                        diagnostic.diagnostic
+                    ),
+                }
+            }
+            println!(
+                "Upgraded {} files and {} chunks, skipped {} files and {} chunks that were already up to date",
+                report.files_upgraded,
+                report.chunks_upgraded,
+                report.files_skipped,
+                report.chunks_skipped
+            );
+            if !report.failures.is_empty() {
+                return Err(format!("{} failures", report.failures.len()));
+            }
+        }
+        Command::File {
+            file,
+            typ,
+            from,
+            to,
+            strict,
+            trace,
+            output,
+        } => {
+            let (mut data, compression) = read_nbt_file(&file)
+                .map_err(|err| format!("Failed to read {}: {err}", file.display()))?;
+            upgrade(
+                typ,
+                &mut data,
+                parse_version(from)?,
+                parse_version(to)?,
+                strict,
+                trace,
+            )?;
+            let output = output.unwrap_or(file);
+            write_nbt_file(&output, &data, compression)
+                .map_err(|err| format!("Failed to write {}: {err}", output.display()))?;
+        }
+        Command::Schematic {
+            file,
+            from,
+            to,
+            output,
+        } => {
+            let (mut data, compression) = read_nbt_file(&file)
+                .map_err(|err| format!("Failed to read {}: {err}", file.display()))?;
+            let from_version = parse_version(from)?;
+            let to_version = parse_version(to)?.unwrap_or_else(latest_version);
+            let extension = file.extension().and_then(|extension| extension.to_str());
+            if from_version.is_some() && extension != Some("schematic") {
+                return Err("--from only applies to MCEdit schematics".to_owned());
+            }
+            let output = match extension {
+                Some("litematic") => {
+                    upgrade_litematica_schematic(&mut data, to_version)
+                        .map_err(|err| err.to_string())?;
+                    output.unwrap_or(file)
+                }
+                // MCEdit schematics are converted to Sponge schematics, or to structures if written to a .nbt file
+                Some("schematic") => {
+                    let output = output.unwrap_or_else(|| file.with_extension("schem"));
+                    let from_version = from_version.unwrap_or_else(|| detect_mcedit_version(&data));
+                    let result = if output
+                        .extension()
+                        .is_some_and(|extension| extension == "nbt")
+                    {
+                        mcedit_to_structure(&data, from_version, to_version)
+                    } else {
+                        mcedit_to_sponge(&data, from_version, to_version)
+                    };
+                    data = result.map_err(|err| err.to_string())?;
+                    output
+                }
+                _ => {
+                    upgrade_sponge_schematic(&mut data, to_version)
+                        .map_err(|err| err.to_string())?;
+                    output.unwrap_or(file)
+                }
+            };
+            write_nbt_file(&output, &data, compression)
+                .map_err(|err| format!("Failed to write {}: {err}", output.display()))?;
+        }
+        Command::Snbt {
+            typ,
+            from,
+            to,
+            strict,
+            trace,
+        } => {
+            let mut snbt = String::new();
+            std::io::stdin()
+                .read_to_string(&mut snbt)
+                .map_err(|err| format!("Failed to read stdin: {err}"))?;
+            let JValue::Compound(mut data) =
+                read_snbt(&snbt).map_err(|err| format!("Invalid SNBT: {err}"))?
+            else {
+                return Err("the SNBT must be a compound".to_owned());
+            };
+            upgrade(
+                typ,
+                &mut data,
+                parse_version(from)?,
+                parse_version(to)?,
+                strict,
+                trace,
+            )?;
+            println!("{}", write_snbt(&JValue::Compound(data)));
+        }
+        Command::Versions { releases } => {
+            for version in get_versions() {
+                if releases && version.typ != VersionType::Release {
+                    continue;
+                }
+                println!("{}\t{}", version.data_version, version.name);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    // Diagnostics are printed with their location instead
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_filter(
+                    Targets::new()
+                        .with_default(Level::WARN)
+                        .with_target("world_transmuter::diagnostics", LevelFilter::OFF),
+                ),
+        )
+        .init();
+
+    let cli = Cli::parse();
+    for path in &cli.load_versions {
+        if let Err(err) = load_versions(path) {
+            eprintln!("Failed to load versions from {}: {err}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+
+    for path in &cli.legacy_blocks {
+        if let Err(err) = load_block_flattening(path) {
+            eprintln!(
+                "Failed to load block flattening from {}: {err}",
+                path.display()
+            );
+            return ExitCode::FAILURE;
+        }
+    }
+
+    match run(cli.command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
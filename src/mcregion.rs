@@ -0,0 +1,171 @@
+use world_transmuter_engine::{JCompound, JList, JValue};
+
+/// Chunks before Anvil were 128 blocks high.
+const HEIGHT: usize = 128;
+/// Biomes weren't stored before Anvil, vanilla generated them from the seed. Like later versions do for unknown biomes,
+/// fall back to plains.
+const DEFAULT_BIOME: i8 = 1;
+
+/// Converts a chunk from the MCRegion format used from Beta 1.3 to 1.1, where the blocks are stored in one 32768 byte
+/// array in XZY order, to the Anvil format of 1.2, with 16 block high sections. The chunk can then be upgraded from
+/// data version 99 like any other chunk without a `DataVersion`.
+///
+/// Returns `false` if the chunk isn't in the MCRegion format.
+pub fn convert_mcregion_chunk(chunk: &mut JCompound) -> bool {
+    let Some(JValue::Compound(level)) = chunk.get_mut("Level") else {
+        return false;
+    };
+    if !matches!(level.get("Blocks"), Some(JValue::ByteArray(blocks)) if blocks.len() == HEIGHT * 256)
+    {
+        return false;
+    }
+    let Some(JValue::ByteArray(blocks)) = level.remove("Blocks") else {
+        unreachable!()
+    };
+    let mut take_nibbles = |key| match level.remove(key) {
+        Some(JValue::ByteArray(nibbles)) if nibbles.len() == HEIGHT * 128 => nibbles,
+        _ => vec![0; HEIGHT * 128],
+    };
+    let data = take_nibbles("Data");
+    let sky_light = take_nibbles("SkyLight");
+    let block_light = take_nibbles("BlockLight");
+
+    let mut sections = Vec::new();
+    for section_y in 0..HEIGHT / 16 {
+        let old_index = |index: usize| {
+            let (x, y, z) = (index & 15, index >> 8, (index >> 4) & 15);
+            (x << 11) | (z << 7) | (section_y * 16 + y)
+        };
+        // like vanilla, sections without blocks are dropped along with their light
+        if (0..4096).all(|index| blocks[old_index(index)] == 0) {
+            continue;
+        }
+
+        let mut section = JCompound::new();
+        section.insert("Y", section_y as i8);
+        section.insert(
+            "Blocks",
+            (0..4096)
+                .map(|index| blocks[old_index(index)])
+                .collect::<Vec<_>>(),
+        );
+        for (key, old_nibbles) in [
+            ("Data", &data),
+            ("SkyLight", &sky_light),
+            ("BlockLight", &block_light),
+        ] {
+            let mut nibbles = vec![0i8; 2048];
+            for index in 0..4096 {
+                set_nibble(
+                    &mut nibbles,
+                    index,
+                    get_nibble(old_nibbles, old_index(index)),
+                );
+            }
+            section.insert(key, nibbles);
+        }
+        sections.push(section);
+    }
+    level.insert("Sections", JList::Compound(sections));
+
+    let height_map = match level.remove("HeightMap") {
+        Some(JValue::ByteArray(height_map)) if height_map.len() == 256 => height_map
+            .into_iter()
+            .map(|height| height as u8 as i32)
+            .collect(),
+        _ => vec![0i32; 256],
+    };
+    level.insert("HeightMap", height_map);
+    if !level.contains_key("Biomes") {
+        level.insert("Biomes", vec![DEFAULT_BIOME; 256]);
+    }
+    true
+}
+
+fn get_nibble(nibbles: &[i8], index: usize) -> u8 {
+    let value = nibbles[index >> 1] as u8;
+    (value >> ((index & 1) << 2)) & 15
+}
+
+fn set_nibble(nibbles: &mut [i8], index: usize, value: u8) {
+    nibbles[index >> 1] |= ((value & 15) << ((index & 1) << 2)) as i8;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types;
+    use valence_nbt::{compound, jcompound};
+    use world_transmuter_engine::DataVersion;
+
+    #[test]
+    fn test_convert_mcregion_chunk() {
+        // stone at x=1, y=70, z=2 with a torch above it, lit to 14
+        let mut blocks = vec![0i8; 32768];
+        let mut data = vec![0i8; 16384];
+        let mut block_light = vec![0i8; 16384];
+        let index = (1 << 11) | (2 << 7) | 70;
+        blocks[index] = 1;
+        blocks[index + 1] = 50;
+        set_nibble(&mut data, index + 1, 5);
+        set_nibble(&mut block_light, index + 1, 14);
+        let mut chunk = jcompound! {
+            "Level" => jcompound! {
+                "xPos" => 0,
+                "zPos" => 0,
+                "Blocks" => blocks,
+                "Data" => data,
+                "BlockLight" => block_light,
+                "SkyLight" => vec![0i8; 16384],
+                "HeightMap" => vec![72i8; 256],
+                "Entities" => JList::Compound(Vec::new()),
+                "TileEntities" => JList::Compound(Vec::new()),
+            },
+        };
+        assert!(convert_mcregion_chunk(&mut chunk));
+        assert!(!convert_mcregion_chunk(&mut chunk));
+
+        let Some(JValue::Compound(level)) = chunk.get("Level") else {
+            panic!("chunk lost its Level tag");
+        };
+        let Some(JValue::List(JList::Compound(sections))) = level.get("Sections") else {
+            panic!("chunk has no sections");
+        };
+        assert_eq!(1, sections.len());
+        assert_eq!(Some(&JValue::Byte(4)), sections[0].get("Y"));
+        let new_index = (6 << 8) | (2 << 4) | 1;
+        let Some(JValue::ByteArray(section_blocks)) = sections[0].get("Blocks") else {
+            panic!("section has no blocks");
+        };
+        assert_eq!(1, section_blocks[new_index]);
+        assert_eq!(50, section_blocks[new_index + 256]);
+        let Some(JValue::ByteArray(section_light)) = sections[0].get("BlockLight") else {
+            panic!("section has no block light");
+        };
+        assert_eq!(14, get_nibble(section_light, new_index + 256));
+        assert_eq!(
+            Some(&JValue::IntArray(vec![72; 256])),
+            level.get("HeightMap")
+        );
+
+        // the rest of the pipeline can take it from there
+        crate::convert_map(
+            types::chunk_ref(),
+            &mut chunk,
+            99,
+            DataVersion::new(1451, 1),
+        );
+        let Some(JValue::Compound(level)) = chunk.get("Level") else {
+            panic!("chunk lost its Level tag");
+        };
+        let Some(JValue::List(JList::Compound(sections))) = level.get("Sections") else {
+            panic!("chunk lost its sections");
+        };
+        let Some(JValue::List(JList::Compound(palette))) = sections[0].get("Palette") else {
+            panic!("section has no palette");
+        };
+        assert!(palette
+            .iter()
+            .any(|state| state.get("Name") == Some(&JValue::from("minecraft:torch"))));
+    }
+}
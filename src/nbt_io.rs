@@ -0,0 +1,349 @@
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use java_string::{JavaStr, JavaString};
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use valence_nbt::snbt::SnbtError;
+use valence_nbt::{Compound, List, Value};
+use world_transmuter_engine::{JCompound, JValue};
+
+// Minecraft uses lz4-java's LZ4BlockOutputStream, which is a different format to the LZ4 frame format.
+const LZ4_MAGIC: &[u8; 8] = b"LZ4Block";
+const LZ4_HEADER_LENGTH: usize = LZ4_MAGIC.len() + 1 + 4 + 4 + 4;
+const LZ4_COMPRESSION_METHOD_RAW: u8 = 0x10;
+const LZ4_COMPRESSION_METHOD_LZ4: u8 = 0x20;
+const LZ4_BLOCK_SIZE: usize = 1 << 16;
+const LZ4_COMPRESSION_LEVEL_BASE: u32 = 10;
+const LZ4_COMPRESSION_LEVEL: u8 = (LZ4_BLOCK_SIZE.ilog2() - LZ4_COMPRESSION_LEVEL_BASE) as u8;
+const LZ4_CHECKSUM_SEED: u32 = 0x9747b28c;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum CompressionType {
+    Gzip,
+    Zlib,
+    None,
+    Lz4,
+}
+
+impl CompressionType {
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(CompressionType::Gzip),
+            2 => Some(CompressionType::Zlib),
+            3 => Some(CompressionType::None),
+            4 => Some(CompressionType::Lz4),
+            _ => None,
+        }
+    }
+
+    pub fn id(self) -> u8 {
+        match self {
+            CompressionType::Gzip => 1,
+            CompressionType::Zlib => 2,
+            CompressionType::None => 3,
+            CompressionType::Lz4 => 4,
+        }
+    }
+
+    pub fn decompress(self, data: &[u8]) -> Result<Vec<u8>, NbtIoError> {
+        let mut result = Vec::new();
+        match self {
+            CompressionType::Gzip => {
+                GzDecoder::new(data).read_to_end(&mut result)?;
+            }
+            CompressionType::Zlib => {
+                ZlibDecoder::new(data).read_to_end(&mut result)?;
+            }
+            CompressionType::None => result.extend_from_slice(data),
+            CompressionType::Lz4 => lz4_block_decompress(data, &mut result)?,
+        }
+        Ok(result)
+    }
+
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>, NbtIoError> {
+        match self {
+            CompressionType::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                Ok(encoder.finish()?)
+            }
+            CompressionType::Zlib => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                Ok(encoder.finish()?)
+            }
+            CompressionType::None => Ok(data.to_vec()),
+            CompressionType::Lz4 => Ok(lz4_block_compress(data)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum NbtIoError {
+    Io(std::io::Error),
+    Nbt(valence_nbt::binary::Error),
+    Lz4(String),
+}
+
+impl Display for NbtIoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NbtIoError::Io(err) => Display::fmt(err, f),
+            NbtIoError::Nbt(err) => write!(f, "Invalid NBT: {err}"),
+            NbtIoError::Lz4(message) => write!(f, "Invalid LZ4 block stream: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for NbtIoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NbtIoError::Io(err) => Some(err),
+            NbtIoError::Nbt(err) => Some(err),
+            NbtIoError::Lz4(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for NbtIoError {
+    fn from(value: std::io::Error) -> Self {
+        NbtIoError::Io(value)
+    }
+}
+
+impl From<valence_nbt::binary::Error> for NbtIoError {
+    fn from(value: valence_nbt::binary::Error) -> Self {
+        NbtIoError::Nbt(value)
+    }
+}
+
+pub fn read_nbt(mut data: &[u8]) -> Result<JCompound, NbtIoError> {
+    let (compound, _root_name) = valence_nbt::from_binary::<JavaString>(&mut data)?;
+    Ok(compound)
+}
+
+pub fn write_nbt(compound: &JCompound) -> Result<Vec<u8>, NbtIoError> {
+    let mut result = Vec::new();
+    valence_nbt::to_binary(compound, &mut result, "")?;
+    Ok(result)
+}
+
+pub fn read_compressed_nbt(
+    data: &[u8],
+    compression: CompressionType,
+) -> Result<JCompound, NbtIoError> {
+    read_nbt(&compression.decompress(data)?)
+}
+
+pub fn write_compressed_nbt(
+    compound: &JCompound,
+    compression: CompressionType,
+) -> Result<Vec<u8>, NbtIoError> {
+    compression.compress(&write_nbt(compound)?)
+}
+
+/// Reads an NBT file such as `level.dat`, detecting whether it is gzip compressed or not.
+pub fn read_nbt_file(path: impl AsRef<Path>) -> Result<(JCompound, CompressionType), NbtIoError> {
+    let mut data = Vec::new();
+    BufReader::new(File::open(path)?).read_to_end(&mut data)?;
+    let compression = if data.starts_with(&[0x1f, 0x8b]) {
+        CompressionType::Gzip
+    } else {
+        CompressionType::None
+    };
+    Ok((read_compressed_nbt(&data, compression)?, compression))
+}
+
+pub fn write_nbt_file(
+    path: impl AsRef<Path>,
+    compound: &JCompound,
+    compression: CompressionType,
+) -> Result<(), NbtIoError> {
+    let data = write_compressed_nbt(compound, compression)?;
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(&data)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn read_snbt(snbt: &str) -> Result<JValue, SnbtError> {
+    Ok(map_strings(
+        valence_nbt::snbt::from_snbt_str(snbt)?,
+        &|str| JavaString::from(str),
+    ))
+}
+
+/// Writes a value as SNBT. Strings which aren't valid UTF-8 are written lossily.
+pub fn write_snbt(value: &JValue) -> String {
+    let value = map_strings(value.clone(), &|str: JavaString| {
+        JavaStr::as_str_lossy(&str).into_owned()
+    });
+    valence_nbt::snbt::to_snbt_string(&value)
+}
+
+fn map_strings<S, T: Ord + std::hash::Hash>(value: Value<S>, f: &impl Fn(S) -> T) -> Value<T> {
+    fn map_compound<S, T: Ord + std::hash::Hash>(
+        compound: Compound<S>,
+        f: &impl Fn(S) -> T,
+    ) -> Compound<T> {
+        compound
+            .into_iter()
+            .map(|(key, value)| (f(key), map_strings(value, f)))
+            .collect()
+    }
+
+    fn map_list<S, T: Ord + std::hash::Hash>(list: List<S>, f: &impl Fn(S) -> T) -> List<T> {
+        match list {
+            List::End => List::End,
+            List::Byte(list) => List::Byte(list),
+            List::Short(list) => List::Short(list),
+            List::Int(list) => List::Int(list),
+            List::Long(list) => List::Long(list),
+            List::Float(list) => List::Float(list),
+            List::Double(list) => List::Double(list),
+            List::ByteArray(list) => List::ByteArray(list),
+            List::String(list) => List::String(list.into_iter().map(f).collect()),
+            List::List(list) => {
+                List::List(list.into_iter().map(|list| map_list(list, f)).collect())
+            }
+            List::Compound(list) => List::Compound(
+                list.into_iter()
+                    .map(|compound| map_compound(compound, f))
+                    .collect(),
+            ),
+            List::IntArray(list) => List::IntArray(list),
+            List::LongArray(list) => List::LongArray(list),
+        }
+    }
+
+    match value {
+        Value::Byte(value) => Value::Byte(value),
+        Value::Short(value) => Value::Short(value),
+        Value::Int(value) => Value::Int(value),
+        Value::Long(value) => Value::Long(value),
+        Value::Float(value) => Value::Float(value),
+        Value::Double(value) => Value::Double(value),
+        Value::ByteArray(value) => Value::ByteArray(value),
+        Value::String(value) => Value::String(f(value)),
+        Value::List(list) => Value::List(map_list(list, f)),
+        Value::Compound(compound) => Value::Compound(map_compound(compound, f)),
+        Value::IntArray(value) => Value::IntArray(value),
+        Value::LongArray(value) => Value::LongArray(value),
+    }
+}
+
+fn lz4_checksum(data: &[u8]) -> u32 {
+    twox_hash::XxHash32::oneshot(LZ4_CHECKSUM_SEED, data) & 0x0FFFFFFF
+}
+
+fn lz4_block_decompress(mut data: &[u8], result: &mut Vec<u8>) -> Result<(), NbtIoError> {
+    loop {
+        if data.len() < LZ4_HEADER_LENGTH || !data.starts_with(LZ4_MAGIC) {
+            return Err(NbtIoError::Lz4("invalid block header".to_owned()));
+        }
+        let token = data[LZ4_MAGIC.len()];
+        let read_i32 = |offset: usize| {
+            let start = LZ4_MAGIC.len() + 1 + offset;
+            u32::from_le_bytes(data[start..start + 4].try_into().unwrap())
+        };
+        let compressed_length = read_i32(0) as usize;
+        let decompressed_length = read_i32(4) as usize;
+        let checksum = read_i32(8);
+        data = &data[LZ4_HEADER_LENGTH..];
+
+        if decompressed_length == 0 {
+            // end of stream marker
+            return Ok(());
+        }
+        // like lz4-java, reject blocks larger than the block size in the header, which is at most 32 MiB, before
+        // allocating anything
+        let max_block_size = 1usize << (LZ4_COMPRESSION_LEVEL_BASE + (token & 0x0f) as u32);
+        if decompressed_length > max_block_size || compressed_length > max_block_size {
+            return Err(NbtIoError::Lz4(format!(
+                "block length {} exceeds block size {}",
+                decompressed_length.max(compressed_length),
+                max_block_size
+            )));
+        }
+        if compressed_length > data.len() {
+            return Err(NbtIoError::Lz4(format!(
+                "block length {} exceeds remaining data {}",
+                compressed_length,
+                data.len()
+            )));
+        }
+
+        let block = &data[..compressed_length];
+        data = &data[compressed_length..];
+        let start = result.len();
+        match token & 0xf0 {
+            LZ4_COMPRESSION_METHOD_RAW => {
+                if compressed_length != decompressed_length {
+                    return Err(NbtIoError::Lz4("raw block length mismatch".to_owned()));
+                }
+                result.extend_from_slice(block);
+            }
+            LZ4_COMPRESSION_METHOD_LZ4 => {
+                result.resize(start + decompressed_length, 0);
+                let written = lz4_flex::block::decompress_into(block, &mut result[start..])
+                    .map_err(|err| NbtIoError::Lz4(err.to_string()))?;
+                if written != decompressed_length {
+                    return Err(NbtIoError::Lz4("decompressed length mismatch".to_owned()));
+                }
+            }
+            _ => {
+                return Err(NbtIoError::Lz4(format!(
+                    "unknown compression method {token:#x}"
+                )))
+            }
+        }
+        if lz4_checksum(&result[start..]) != checksum {
+            return Err(NbtIoError::Lz4("checksum mismatch".to_owned()));
+        }
+    }
+}
+
+fn lz4_block_compress(data: &[u8]) -> Vec<u8> {
+    fn write_header(
+        result: &mut Vec<u8>,
+        method: u8,
+        compressed_length: usize,
+        decompressed_length: usize,
+        checksum: u32,
+    ) {
+        result.extend_from_slice(LZ4_MAGIC);
+        result.push(method | LZ4_COMPRESSION_LEVEL);
+        result.extend_from_slice(&(compressed_length as u32).to_le_bytes());
+        result.extend_from_slice(&(decompressed_length as u32).to_le_bytes());
+        result.extend_from_slice(&checksum.to_le_bytes());
+    }
+
+    let mut result = Vec::new();
+    for block in data.chunks(LZ4_BLOCK_SIZE) {
+        let checksum = lz4_checksum(block);
+        let compressed = lz4_flex::block::compress(block);
+        if compressed.len() < block.len() {
+            write_header(
+                &mut result,
+                LZ4_COMPRESSION_METHOD_LZ4,
+                compressed.len(),
+                block.len(),
+                checksum,
+            );
+            result.extend_from_slice(&compressed);
+        } else {
+            write_header(
+                &mut result,
+                LZ4_COMPRESSION_METHOD_RAW,
+                block.len(),
+                block.len(),
+                checksum,
+            );
+            result.extend_from_slice(block);
+        }
+    }
+    write_header(&mut result, LZ4_COMPRESSION_METHOD_RAW, 0, 0, 0);
+    result
+}
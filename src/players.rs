@@ -0,0 +1,201 @@
+use crate::helpers::json_parser::{parse_value, ParseError};
+use ahash::AHashMap;
+use java_string::JavaStr;
+use md5::{Digest, Md5};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use uuid::{Builder, Uuid};
+use world_transmuter_engine::{JList, JValue};
+
+/// The files of a server that map player names to UUIDs, in the order they are loaded by
+/// [`PlayerUuids::from_server_dir`].
+const PLAYER_LIST_FILES: [&str; 3] = ["usercache.json", "whitelist.json", "ops.json"];
+
+/// Maps the names of players to their UUIDs, to move the player files of worlds from before 1.7.6, which are named
+/// after the player, to files named after their UUID. Names are case insensitive.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PlayerUuids {
+    uuids: AHashMap<String, Uuid>,
+}
+
+impl PlayerUuids {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the `usercache.json`, `whitelist.json` and `ops.json` files that exist in a server directory. Later files
+    /// take priority.
+    pub fn from_server_dir(dir: impl AsRef<Path>) -> Result<Self, PlayerUuidsError> {
+        let mut uuids = Self::new();
+        for file in PLAYER_LIST_FILES {
+            let path = dir.as_ref().join(file);
+            if path.is_file() {
+                uuids.load(path)?;
+            }
+        }
+        Ok(uuids)
+    }
+
+    /// Loads a JSON array of objects with a `name` and a `uuid`, such as a `usercache.json`, `whitelist.json` or
+    /// `ops.json` file, returning how many players were loaded.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<usize, PlayerUuidsError> {
+        let json = std::fs::read(path)?;
+        let json = JavaStr::from_semi_utf8(&json).map_err(|_| PlayerUuidsError::InvalidUtf8)?;
+        let players = match parse_value(json, false)? {
+            JValue::List(JList::Compound(players)) => players,
+            JValue::List(players) if players.is_empty() => return Ok(0),
+            _ => return Err(PlayerUuidsError::InvalidField("players")),
+        };
+
+        let mut entries = Vec::with_capacity(players.len());
+        for player in &players {
+            let Some(JValue::String(name)) = player.get("name") else {
+                return Err(PlayerUuidsError::InvalidField("name"));
+            };
+            let Some(JValue::String(uuid)) = player.get("uuid") else {
+                return Err(PlayerUuidsError::InvalidField("uuid"));
+            };
+            let uuid = uuid
+                .as_str()
+                .ok()
+                .and_then(|uuid| Uuid::parse_str(uuid).ok())
+                .ok_or(PlayerUuidsError::InvalidField("uuid"))?;
+            entries.push((name.to_string(), uuid));
+        }
+
+        let count = entries.len();
+        for (name, uuid) in entries {
+            self.insert(&name, uuid);
+        }
+        Ok(count)
+    }
+
+    pub fn insert(&mut self, name: &str, uuid: Uuid) {
+        self.uuids.insert(name.to_lowercase(), uuid);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Uuid> {
+        self.uuids.get(&name.to_lowercase()).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.uuids.is_empty()
+    }
+}
+
+/// Returns the UUID the game gives a player on a server in offline mode, which is the type 3 UUID of
+/// `OfflinePlayer:<name>`.
+pub fn offline_uuid(name: &str) -> Uuid {
+    let hash = Md5::digest(format!("OfflinePlayer:{name}"));
+    Builder::from_md5_bytes(hash.into()).into_uuid()
+}
+
+#[derive(Debug)]
+pub enum PlayerUuidsError {
+    Io(std::io::Error),
+    Json(ParseError),
+    InvalidUtf8,
+    InvalidField(&'static str),
+}
+
+impl Display for PlayerUuidsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlayerUuidsError::Io(err) => Display::fmt(err, f),
+            PlayerUuidsError::Json(err) => write!(f, "Invalid JSON: {err}"),
+            PlayerUuidsError::InvalidUtf8 => write!(f, "Invalid UTF-8"),
+            PlayerUuidsError::InvalidField(field) => write!(f, "Invalid field {field}"),
+        }
+    }
+}
+
+impl std::error::Error for PlayerUuidsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PlayerUuidsError::Io(err) => Some(err),
+            PlayerUuidsError::Json(err) => Some(err),
+            PlayerUuidsError::InvalidUtf8 | PlayerUuidsError::InvalidField(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for PlayerUuidsError {
+    fn from(value: std::io::Error) -> Self {
+        PlayerUuidsError::Io(value)
+    }
+}
+
+impl From<ParseError> for PlayerUuidsError {
+    fn from(value: ParseError) -> Self {
+        PlayerUuidsError::Json(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::DiagnosticKind;
+    use crate::nbt_io::{read_nbt_file, write_nbt_file, CompressionType};
+    use crate::test_util::TempDir;
+    use crate::world::WorldUpgrader;
+    use std::fs;
+    use world_transmuter_engine::JCompound;
+
+    #[test]
+    fn test_migrate_player_files() {
+        assert_eq!(
+            Uuid::parse_str("b50ad385-829d-3141-a216-7e7d7539ba7f").unwrap(),
+            offline_uuid("Notch")
+        );
+
+        let dir = TempDir::new("player-files");
+        let world_dir = dir.path().join("world");
+        fs::create_dir_all(world_dir.join("players")).unwrap();
+        fs::write(
+            dir.path().join("usercache.json"),
+            r#"[{"name":"Steve","uuid":"8667ba71-b85a-4004-af54-457a9734eed7","expiresOn":"2014-06-01 12:00:00 +0000"}]"#,
+        )
+        .unwrap();
+
+        let mut player = JCompound::new();
+        player.insert("Health", 20i16);
+        for name in ["steve", "Notch"] {
+            write_nbt_file(
+                world_dir.join(format!("players/{name}.dat")),
+                &player,
+                CompressionType::Gzip,
+            )
+            .unwrap();
+        }
+
+        let report = WorldUpgrader::new(&world_dir, 3953).upgrade().unwrap();
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        assert_eq!(1, report.files_upgraded);
+        assert!(!world_dir.join("players/steve.dat").exists());
+        assert!(world_dir.join("players/Notch.dat").is_file());
+        assert_eq!(1, report.diagnostics.len());
+        assert_eq!(
+            DiagnosticKind::UnknownPlayer,
+            report.diagnostics[0].diagnostic.kind
+        );
+        assert!(!world_dir
+            .join("playerdata/b50ad385-829d-3141-a216-7e7d7539ba7f.dat")
+            .exists());
+
+        let (player, _) =
+            read_nbt_file(world_dir.join("playerdata/8667ba71-b85a-4004-af54-457a9734eed7.dat"))
+                .unwrap();
+        assert_eq!(Some(&JValue::Int(3953)), player.get("DataVersion"));
+
+        let report = WorldUpgrader::new(&world_dir, 3953)
+            .offline_players(true)
+            .upgrade()
+            .unwrap();
+        assert!(report.diagnostics.is_empty(), "{:?}", report.diagnostics);
+        assert_eq!(1, report.files_upgraded);
+        assert!(!world_dir.join("players/Notch.dat").exists());
+        assert!(world_dir
+            .join("playerdata/b50ad385-829d-3141-a216-7e7d7539ba7f.dat")
+            .is_file());
+    }
+}
@@ -0,0 +1,547 @@
+use crate::nbt_io::{read_compressed_nbt, write_compressed_nbt, CompressionType, NbtIoError};
+use std::fmt::{Display, Formatter};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use world_transmuter_engine::JCompound;
+
+const SECTOR_SIZE: usize = 4096;
+const CHUNK_COUNT: usize = 1024;
+const HEADER_SECTORS: usize = 2;
+const CHUNK_HEADER_SIZE: usize = 5;
+const EXTERNAL_STREAM_FLAG: u8 = 128;
+const MAX_SECTOR_COUNT: usize = 255;
+
+#[derive(Debug)]
+pub enum RegionError {
+    Io(std::io::Error),
+    Nbt(NbtIoError),
+    UnknownCompression(u8),
+    Corrupt { x: i32, z: i32, reason: String },
+}
+
+impl Display for RegionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegionError::Io(err) => Display::fmt(err, f),
+            RegionError::Nbt(err) => Display::fmt(err, f),
+            RegionError::UnknownCompression(id) => write!(f, "Unknown compression type {id}"),
+            RegionError::Corrupt { x, z, reason } => {
+                write!(f, "Chunk [{x}, {z}] is corrupt: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RegionError::Io(err) => Some(err),
+            RegionError::Nbt(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for RegionError {
+    fn from(value: std::io::Error) -> Self {
+        RegionError::Io(value)
+    }
+}
+
+impl From<NbtIoError> for RegionError {
+    fn from(value: NbtIoError) -> Self {
+        RegionError::Nbt(value)
+    }
+}
+
+/// Parses a region file name such as `r.1.-2.mca` into its region coordinates.
+pub fn parse_region_file_name(name: &str) -> Option<(i32, i32)> {
+    let name = name
+        .strip_suffix(".mca")
+        .or_else(|| name.strip_suffix(".mcr"))?;
+    let mut parts = name.strip_prefix("r.")?.split('.');
+    let x = parts.next()?.parse().ok()?;
+    let z = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((x, z))
+}
+
+/// An Anvil region file, holding up to 32x32 chunks.
+///
+/// Chunk coordinates passed to this type may be either absolute or relative to the region, only the lower 5 bits are
+/// used.
+pub struct RegionFile {
+    path: PathBuf,
+    file: File,
+    writable: bool,
+    region_x: i32,
+    region_z: i32,
+    offsets: [u32; CHUNK_COUNT],
+    timestamps: [u32; CHUNK_COUNT],
+    used_sectors: Vec<bool>,
+}
+
+impl RegionFile {
+    /// Opens a region file for reading and writing, creating it if it doesn't exist.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, RegionError> {
+        let path = path.into();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        Self::from_file(path, file, true)
+    }
+
+    pub fn open_read_only(path: impl Into<PathBuf>) -> Result<Self, RegionError> {
+        let path = path.into();
+        let file = File::open(&path)?;
+        Self::from_file(path, file, false)
+    }
+
+    fn from_file(path: PathBuf, file: File, writable: bool) -> Result<Self, RegionError> {
+        let (region_x, region_z) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(parse_region_file_name)
+            .unwrap_or((0, 0));
+
+        let mut region = RegionFile {
+            path,
+            file,
+            writable,
+            region_x,
+            region_z,
+            offsets: [0; CHUNK_COUNT],
+            timestamps: [0; CHUNK_COUNT],
+            used_sectors: vec![true; HEADER_SECTORS],
+        };
+
+        let file_len = region.file.metadata()?.len() as usize;
+        if file_len < HEADER_SECTORS * SECTOR_SIZE {
+            if writable {
+                region.file.seek(SeekFrom::Start(0))?;
+                region.file.write_all(&[0; HEADER_SECTORS * SECTOR_SIZE])?;
+            }
+            return Ok(region);
+        }
+
+        let mut header = vec![0; HEADER_SECTORS * SECTOR_SIZE];
+        region.file.seek(SeekFrom::Start(0))?;
+        region.file.read_exact(&mut header)?;
+        for index in 0..CHUNK_COUNT {
+            region.offsets[index] = read_u32(&header, index * 4);
+            region.timestamps[index] = read_u32(&header, SECTOR_SIZE + index * 4);
+        }
+
+        let total_sectors = file_len.div_ceil(SECTOR_SIZE);
+        for offset in region.offsets {
+            let (sector, count) = unpack_offset(offset);
+            if offset != 0 && sector >= HEADER_SECTORS && sector + count <= total_sectors {
+                region.set_used(sector, count, true);
+            }
+        }
+
+        Ok(region)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn region_x(&self) -> i32 {
+        self.region_x
+    }
+
+    pub fn region_z(&self) -> i32 {
+        self.region_z
+    }
+
+    /// Returns the absolute coordinates of all the chunks stored in this region.
+    pub fn chunks(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        (0..CHUNK_COUNT)
+            .filter(|&index| self.offsets[index] != 0)
+            .map(|index| self.absolute_pos(index))
+    }
+
+    pub fn has_chunk(&self, x: i32, z: i32) -> bool {
+        self.offsets[chunk_index(x, z)] != 0
+    }
+
+    /// Returns the last modification time of the chunk in seconds since the unix epoch, or 0 if absent.
+    pub fn timestamp(&self, x: i32, z: i32) -> u32 {
+        self.timestamps[chunk_index(x, z)]
+    }
+
+    pub fn read_chunk(&mut self, x: i32, z: i32) -> Result<Option<JCompound>, RegionError> {
+        Ok(self
+            .read_chunk_with_compression(x, z)?
+            .map(|(chunk, _compression)| chunk))
+    }
+
+    /// Reads a chunk along with the compression it was stored with, so that it can be written back the same way.
+    pub fn read_chunk_with_compression(
+        &mut self,
+        x: i32,
+        z: i32,
+    ) -> Result<Option<(JCompound, CompressionType)>, RegionError> {
+        let index = chunk_index(x, z);
+        let offset = self.offsets[index];
+        if offset == 0 {
+            return Ok(None);
+        }
+        let (x, z) = self.absolute_pos(index);
+        let corrupt = |reason: String| RegionError::Corrupt { x, z, reason };
+
+        let (sector, count) = unpack_offset(offset);
+        if sector < HEADER_SECTORS || count == 0 {
+            return Err(corrupt(format!("invalid sector offset {sector}")));
+        }
+        let mut data = vec![0; count * SECTOR_SIZE];
+        self.file
+            .seek(SeekFrom::Start((sector * SECTOR_SIZE) as u64))?;
+        let read = read_fully(&mut self.file, &mut data)?;
+        data.truncate(read);
+        if data.len() < CHUNK_HEADER_SIZE {
+            return Err(corrupt("truncated chunk header".to_owned()));
+        }
+
+        let length = read_u32(&data, 0) as usize;
+        if length == 0 {
+            return Err(corrupt("chunk has zero length".to_owned()));
+        }
+        if length + 4 > data.len() {
+            return Err(corrupt(format!(
+                "chunk length {} exceeds allocated space {}",
+                length,
+                data.len() - 4
+            )));
+        }
+        let typ = data[4];
+        let compression = CompressionType::from_id(typ & !EXTERNAL_STREAM_FLAG)
+            .ok_or(RegionError::UnknownCompression(typ & !EXTERNAL_STREAM_FLAG))?;
+
+        let chunk = if typ & EXTERNAL_STREAM_FLAG != 0 {
+            let external_path = self.external_path(index);
+            let mut external_data = Vec::new();
+            File::open(&external_path)
+                .and_then(|mut file| file.read_to_end(&mut external_data))
+                .map_err(|err| {
+                    corrupt(format!(
+                        "could not read external chunk {}: {err}",
+                        external_path.display()
+                    ))
+                })?;
+            read_compressed_nbt(&external_data, compression)?
+        } else {
+            read_compressed_nbt(&data[CHUNK_HEADER_SIZE..length + 4], compression)?
+        };
+        Ok(Some((chunk, compression)))
+    }
+
+    /// Writes a chunk with the current time as its timestamp.
+    pub fn write_chunk(
+        &mut self,
+        x: i32,
+        z: i32,
+        chunk: &JCompound,
+        compression: CompressionType,
+    ) -> Result<(), RegionError> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs() as u32);
+        self.write_chunk_with_timestamp(x, z, chunk, compression, timestamp)
+    }
+
+    pub fn write_chunk_with_timestamp(
+        &mut self,
+        x: i32,
+        z: i32,
+        chunk: &JCompound,
+        compression: CompressionType,
+        timestamp: u32,
+    ) -> Result<(), RegionError> {
+        self.check_writable()?;
+        let data = write_compressed_nbt(chunk, compression)?;
+        let index = chunk_index(x, z);
+
+        let sector_count = (data.len() + CHUNK_HEADER_SIZE).div_ceil(SECTOR_SIZE);
+        let external = sector_count > MAX_SECTOR_COUNT;
+        let mut payload = Vec::new();
+        if external {
+            let external_path = self.external_path(index);
+            File::create(&external_path)?.write_all(&data)?;
+            payload.extend_from_slice(&1u32.to_be_bytes());
+            payload.push(compression.id() | EXTERNAL_STREAM_FLAG);
+        } else {
+            payload.extend_from_slice(&(data.len() as u32 + 1).to_be_bytes());
+            payload.push(compression.id());
+            payload.extend_from_slice(&data);
+        }
+        payload.resize(payload.len().div_ceil(SECTOR_SIZE) * SECTOR_SIZE, 0);
+
+        // Allocate before freeing the old sectors, so that a crash part way through never loses the old chunk.
+        let new_count = payload.len() / SECTOR_SIZE;
+        let new_sector = self.allocate(new_count);
+        self.file
+            .seek(SeekFrom::Start((new_sector * SECTOR_SIZE) as u64))?;
+        self.file.write_all(&payload)?;
+
+        let old_offset = self.offsets[index];
+        self.write_header_entry(index, pack_offset(new_sector, new_count), timestamp)?;
+        if old_offset != 0 {
+            let (old_sector, old_count) = unpack_offset(old_offset);
+            self.set_used(old_sector, old_count, false);
+        }
+        self.set_used(new_sector, new_count, true);
+
+        if !external {
+            self.remove_external_file(index)?;
+        }
+        Ok(())
+    }
+
+    pub fn delete_chunk(&mut self, x: i32, z: i32) -> Result<(), RegionError> {
+        self.check_writable()?;
+        let index = chunk_index(x, z);
+        let old_offset = self.offsets[index];
+        if old_offset == 0 {
+            return Ok(());
+        }
+        self.write_header_entry(index, 0, 0)?;
+        let (old_sector, old_count) = unpack_offset(old_offset);
+        self.set_used(old_sector, old_count, false);
+        self.remove_external_file(index)?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), RegionError> {
+        self.file.flush()?;
+        Ok(())
+    }
+
+    fn absolute_pos(&self, index: usize) -> (i32, i32) {
+        (
+            self.region_x * 32 + (index % 32) as i32,
+            self.region_z * 32 + (index / 32) as i32,
+        )
+    }
+
+    fn external_path(&self, index: usize) -> PathBuf {
+        let (x, z) = self.absolute_pos(index);
+        self.path.with_file_name(format!("c.{x}.{z}.mcc"))
+    }
+
+    fn remove_external_file(&self, index: usize) -> std::io::Result<()> {
+        match std::fs::remove_file(self.external_path(index)) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_writable(&self) -> Result<(), RegionError> {
+        if self.writable {
+            Ok(())
+        } else {
+            Err(RegionError::Io(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "region file was opened read only",
+            )))
+        }
+    }
+
+    fn write_header_entry(
+        &mut self,
+        index: usize,
+        offset: u32,
+        timestamp: u32,
+    ) -> Result<(), RegionError> {
+        self.file.seek(SeekFrom::Start((index * 4) as u64))?;
+        self.file.write_all(&offset.to_be_bytes())?;
+        self.file
+            .seek(SeekFrom::Start((SECTOR_SIZE + index * 4) as u64))?;
+        self.file.write_all(&timestamp.to_be_bytes())?;
+        self.offsets[index] = offset;
+        self.timestamps[index] = timestamp;
+        Ok(())
+    }
+
+    fn allocate(&self, count: usize) -> usize {
+        let mut run_start = HEADER_SECTORS;
+        let mut run_length = 0;
+        for (sector, &used) in self.used_sectors.iter().enumerate().skip(HEADER_SECTORS) {
+            if used {
+                run_start = sector + 1;
+                run_length = 0;
+            } else {
+                run_length += 1;
+                if run_length == count {
+                    return run_start;
+                }
+            }
+        }
+        run_start
+    }
+
+    fn set_used(&mut self, sector: usize, count: usize, used: bool) {
+        if self.used_sectors.len() < sector + count {
+            self.used_sectors.resize(sector + count, false);
+        }
+        self.used_sectors[sector..sector + count].fill(used);
+    }
+}
+
+fn chunk_index(x: i32, z: i32) -> usize {
+    (x & 31) as usize + (z & 31) as usize * 32
+}
+
+fn pack_offset(sector: usize, count: usize) -> u32 {
+    ((sector as u32) << 8) | count as u32
+}
+
+fn unpack_offset(offset: u32) -> (usize, usize) {
+    ((offset >> 8) as usize, (offset & 0xff) as usize)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_fully(file: &mut File, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match file.read(&mut buf[total..])? {
+            0 => break,
+            n => total += n,
+        }
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use world_transmuter_engine::JValue;
+
+    fn make_chunk(x: i32, z: i32, payload_len: usize) -> JCompound {
+        let mut chunk = JCompound::new();
+        chunk.insert("xPos", x);
+        chunk.insert("zPos", z);
+        chunk.insert(
+            "payload",
+            JValue::ByteArray((0..payload_len).map(|i| (i * 31 % 251) as i8).collect()),
+        );
+        chunk
+    }
+
+    #[test]
+    fn test_parse_region_file_name() {
+        assert_eq!(Some((1, -2)), parse_region_file_name("r.1.-2.mca"));
+        assert_eq!(Some((0, 0)), parse_region_file_name("r.0.0.mcr"));
+        assert_eq!(None, parse_region_file_name("r.0.mca"));
+        assert_eq!(None, parse_region_file_name("c.0.0.mcc"));
+    }
+
+    #[test]
+    fn test_round_trip_all_compressions() {
+        let dir = TempDir::new("region-round-trip");
+        let path = dir.path().join("r.-1.2.mca");
+        let compressions = [
+            CompressionType::Gzip,
+            CompressionType::Zlib,
+            CompressionType::None,
+            CompressionType::Lz4,
+        ];
+        {
+            let mut region = RegionFile::open(&path).unwrap();
+            for (i, compression) in compressions.into_iter().enumerate() {
+                let (x, z) = (-32 + i as i32, 64 + i as i32);
+                region
+                    .write_chunk_with_timestamp(x, z, &make_chunk(x, z, 100_000), compression, 42)
+                    .unwrap();
+            }
+        }
+
+        let mut region = RegionFile::open_read_only(&path).unwrap();
+        let chunks: Vec<_> = region.chunks().collect();
+        assert_eq!(vec![(-32, 64), (-31, 65), (-30, 66), (-29, 67)], chunks);
+        for (x, z) in chunks {
+            assert_eq!(42, region.timestamp(x, z));
+            assert_eq!(
+                Some(make_chunk(x, z, 100_000)),
+                region.read_chunk(x, z).unwrap()
+            );
+        }
+        assert_eq!(None, region.read_chunk(-27, 64).unwrap());
+    }
+
+    #[test]
+    fn test_oversized_lz4_block() {
+        // a compressed block claiming to decompress to 4 GiB
+        let mut data = b"LZ4Block".to_vec();
+        data.push(0x20 | 6);
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.push(0);
+        assert!(matches!(
+            CompressionType::Lz4.decompress(&data),
+            Err(NbtIoError::Lz4(_))
+        ));
+    }
+
+    #[test]
+    fn test_external_chunk() {
+        let dir = TempDir::new("region-external");
+        let path = dir.path().join("r.0.0.mca");
+        let big_chunk = make_chunk(3, 4, MAX_SECTOR_COUNT * SECTOR_SIZE);
+        let mut region = RegionFile::open(&path).unwrap();
+        region
+            .write_chunk(3, 4, &big_chunk, CompressionType::None)
+            .unwrap();
+        assert!(dir.path().join("c.3.4.mcc").exists());
+        assert_eq!(Some(big_chunk), region.read_chunk(3, 4).unwrap());
+
+        let small_chunk = make_chunk(3, 4, 10);
+        region
+            .write_chunk(3, 4, &small_chunk, CompressionType::Zlib)
+            .unwrap();
+        assert!(!dir.path().join("c.3.4.mcc").exists());
+        assert_eq!(Some(small_chunk), region.read_chunk(3, 4).unwrap());
+    }
+
+    #[test]
+    fn test_rewrite_reuses_freed_sectors() {
+        let dir = TempDir::new("region-rewrite");
+        let path = dir.path().join("r.0.0.mca");
+        let mut region = RegionFile::open(&path).unwrap();
+        region
+            .write_chunk(0, 0, &make_chunk(0, 0, 20_000), CompressionType::None)
+            .unwrap();
+        region
+            .write_chunk(1, 0, &make_chunk(1, 0, 10), CompressionType::None)
+            .unwrap();
+        region
+            .write_chunk(0, 0, &make_chunk(0, 0, 10), CompressionType::None)
+            .unwrap();
+        region
+            .write_chunk(2, 0, &make_chunk(2, 0, 10), CompressionType::None)
+            .unwrap();
+
+        // chunk (2, 0) should go into the space freed by the original (0, 0)
+        assert_eq!(HEADER_SECTORS, unpack_offset(region.offsets[2]).0);
+        region.delete_chunk(1, 0).unwrap();
+        drop(region);
+
+        let mut region = RegionFile::open(&path).unwrap();
+        assert_eq!(vec![(0, 0), (2, 0)], region.chunks().collect::<Vec<_>>());
+        assert_eq!(Some(make_chunk(0, 0, 10)), region.read_chunk(0, 0).unwrap());
+        assert_eq!(Some(make_chunk(2, 0, 10)), region.read_chunk(2, 0).unwrap());
+    }
+}
@@ -0,0 +1,456 @@
+use crate::helpers::resource_location::ResourceLocation;
+use crate::types::TypeKind;
+use crate::visitor;
+use crate::visitor::VisitedMut;
+use java_string::{format_java, JavaStr, JavaString};
+use std::collections::BTreeMap;
+use world_transmuter_engine::{
+    AbstractDynamicDataType, AbstractMapDataType, DataVersion, JCompound, JList, JValue, JValueMut,
+};
+
+/// Marks objects that were deleted, so that they can be removed from their parent after the walk.
+const TOMBSTONE: &str = "world_transmuter:deleted";
+/// Lists of item stacks where the index of a stack is its slot. Deleted stacks in them are replaced with empty ones.
+const POSITIONAL_LISTS: [&str; 2] = ["HandItems", "ArmorItems"];
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Target {
+    Id(JavaString),
+    Namespace(JavaString),
+    Delete,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Rules {
+    exact: BTreeMap<JavaString, Target>,
+    namespaces: BTreeMap<JavaString, Target>,
+}
+
+impl Rules {
+    fn add(&mut self, pattern: &str, target: Target) {
+        match pattern.strip_suffix(":*") {
+            Some(namespace) => self.namespaces.insert(JavaString::from(namespace), target),
+            None => self.exact.insert(JavaString::from(pattern), target),
+        };
+    }
+
+    fn lookup(&self, id: &JavaStr) -> Option<Remapped> {
+        let id = ResourceLocation::parse(id).ok()?;
+        let target = self
+            .exact
+            .get(&id.to_java_string())
+            .or_else(|| self.namespaces.get(&id.namespace))?;
+        Some(match target {
+            Target::Id(new_id) => Remapped::Rename(new_id.clone()),
+            Target::Namespace(namespace) => {
+                Remapped::Rename(format_java!("{}:{}", namespace, id.path))
+            }
+            Target::Delete => Remapped::Delete,
+        })
+    }
+}
+
+pub(crate) enum Remapped {
+    Rename(JavaString),
+    Delete,
+}
+
+fn rename_target(to: &str) -> Target {
+    match to.strip_suffix(":*") {
+        Some(namespace) => Target::Namespace(JavaString::from(namespace)),
+        None => Target::Id(JavaString::from(to)),
+    }
+}
+
+/// A table of block, item and entity ids to rename or delete, which can be applied to any data with [`remap_map`] or
+/// [`remap_dyn`].
+///
+/// Patterns are either a full id, such as `mymod:copper_ore`, or all ids in a namespace, such as `mymod:*`. Renaming a
+/// namespace pattern to another namespace pattern, such as `mymod:*` to `othermod:*`, keeps the paths of the ids.
+/// Full ids take priority over namespace patterns.
+#[derive(Clone, Debug, Default)]
+pub struct RemapTable {
+    blocks: Rules,
+    items: Rules,
+    entities: Rules,
+}
+
+impl RemapTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Renames blocks in block states, flattened block states and block names.
+    pub fn rename_block(mut self, from: &str, to: &str) -> Self {
+        self.blocks.add(from, rename_target(to));
+        self
+    }
+
+    /// Replaces blocks with air, removing their block state properties, and removes the block entities with the same id.
+    pub fn delete_block(mut self, pattern: &str) -> Self {
+        self.blocks.add(pattern, Target::Delete);
+        self
+    }
+
+    /// Renames items in item stacks and item names.
+    pub fn rename_item(mut self, from: &str, to: &str) -> Self {
+        self.items.add(from, rename_target(to));
+        self
+    }
+
+    /// Removes item stacks of the item. Item names that can't be removed are replaced with air.
+    pub fn delete_item(mut self, pattern: &str) -> Self {
+        self.items.add(pattern, Target::Delete);
+        self
+    }
+
+    /// Renames entities and entity names.
+    pub fn rename_entity(mut self, from: &str, to: &str) -> Self {
+        self.entities.add(from, rename_target(to));
+        self
+    }
+
+    /// Removes entities, together with their passengers. Entity names are left as they are.
+    pub fn delete_entity(mut self, pattern: &str) -> Self {
+        self.entities.add(pattern, Target::Delete);
+        self
+    }
+
+    pub(crate) fn lookup_block(&self, id: &JavaStr) -> Option<Remapped> {
+        self.blocks.lookup(id)
+    }
+
+    pub(crate) fn lookup_item(&self, id: &JavaStr) -> Option<Remapped> {
+        self.items.lookup(id)
+    }
+
+    fn apply(&self, kind: TypeKind, data: VisitedMut, stats: &mut RemapStats) {
+        match (kind, data) {
+            (TypeKind::BlockState, VisitedMut::Map(data)) => {
+                let Some(JValue::String(name)) = data.get_mut("Name") else {
+                    return;
+                };
+                match self.blocks.lookup(name) {
+                    Some(Remapped::Rename(new_name)) => {
+                        *name = new_name;
+                        stats.renamed += 1;
+                    }
+                    Some(Remapped::Delete) => {
+                        *name = JavaString::from("minecraft:air");
+                        data.remove("Properties");
+                        stats.deleted += 1;
+                    }
+                    None => {}
+                }
+            }
+            (TypeKind::BlockName, VisitedMut::Value(JValueMut::String(name))) => {
+                Self::apply_name(&self.blocks, name, stats)
+            }
+            (TypeKind::FlatBlockState, VisitedMut::Value(JValueMut::String(state))) => {
+                let name_end = state.find(['[', '{'].as_slice()).unwrap_or(state.len());
+                let new_name = match self.blocks.lookup(&state[..name_end]) {
+                    Some(Remapped::Rename(new_name)) => {
+                        stats.renamed += 1;
+                        new_name
+                    }
+                    Some(Remapped::Delete) => {
+                        stats.deleted += 1;
+                        **state = JavaString::from("minecraft:air");
+                        return;
+                    }
+                    None => return,
+                };
+                state.replace_range_java(..name_end, &new_name);
+            }
+            (TypeKind::ItemName, VisitedMut::Value(JValueMut::String(name))) => {
+                Self::apply_name(&self.items, name, stats)
+            }
+            (TypeKind::ItemStack, VisitedMut::Map(data)) => {
+                Self::apply_id(&self.items, data, stats)
+            }
+            (TypeKind::EntityName, VisitedMut::Value(JValueMut::String(name))) => {
+                if let Some(Remapped::Rename(new_name)) = self.entities.lookup(name) {
+                    **name = new_name;
+                    stats.renamed += 1;
+                }
+            }
+            (TypeKind::Entity, VisitedMut::Map(data)) => {
+                Self::apply_id(&self.entities, data, stats)
+            }
+            (TypeKind::TileEntity, VisitedMut::Map(data)) => {
+                // block entities are only deleted with their block, renaming a block doesn't rename its block entity
+                let Some(JValue::String(id)) = data.get("id") else {
+                    return;
+                };
+                if let Some(Remapped::Delete) = self.blocks.lookup(id) {
+                    Self::delete(data, stats);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn apply_name(rules: &Rules, name: &mut JavaString, stats: &mut RemapStats) {
+        match rules.lookup(name) {
+            Some(Remapped::Rename(new_name)) => {
+                *name = new_name;
+                stats.renamed += 1;
+            }
+            Some(Remapped::Delete) => {
+                *name = JavaString::from("minecraft:air");
+                stats.deleted += 1;
+            }
+            None => {}
+        }
+    }
+
+    fn apply_id(rules: &Rules, data: &mut JCompound, stats: &mut RemapStats) {
+        let Some(JValue::String(id)) = data.get_mut("id") else {
+            return;
+        };
+        match rules.lookup(id) {
+            Some(Remapped::Rename(new_id)) => {
+                *id = new_id;
+                stats.renamed += 1;
+            }
+            Some(Remapped::Delete) => Self::delete(data, stats),
+            None => {}
+        }
+    }
+
+    fn delete(data: &mut JCompound, stats: &mut RemapStats) {
+        // clearing the data also stops the walkers from visiting anything inside it
+        data.clear();
+        data.insert(TOMBSTONE, true);
+        stats.deleted += 1;
+    }
+}
+
+/// The number of ids that were changed by a remap.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RemapStats {
+    pub renamed: usize,
+    /// The number of objects that were removed or replaced with air.
+    pub deleted: usize,
+}
+
+/// Applies the remap table to `data`, which is at data version `version`, everywhere the walkers of that version find
+/// a block, item or entity. If `typ` is one of the types of a registry, such as
+/// [`types::entity_ref`](crate::types::entity_ref), `data` itself is also remapped, and is left empty if it is deleted.
+///
+/// Deleted item stacks, entities and block entities are removed from the list or compound they are in, except for
+/// item stacks in lists where their index is their slot, such as `HandItems`, which are replaced with empty compounds.
+/// Ids in the data may also be namespaced as a side effect.
+pub fn remap_map(
+    typ: impl AbstractMapDataType,
+    data: &mut JCompound,
+    version: impl Into<DataVersion>,
+    table: &RemapTable,
+) -> RemapStats {
+    let version = version.into();
+    let mut stats = RemapStats::default();
+    visitor::with_visitor(
+        &mut |kind, data| table.apply(kind, data, &mut stats),
+        || typ.convert(data, version, version),
+    );
+    if stats.deleted != 0 {
+        if data.contains_key(TOMBSTONE) {
+            data.clear();
+        } else {
+            remove_tombstones_in_map(data);
+        }
+    }
+    stats
+}
+
+/// Like [`remap_map`], but for dynamic data types.
+pub fn remap_dyn(
+    typ: impl AbstractDynamicDataType,
+    data: &mut JValue,
+    version: impl Into<DataVersion>,
+    table: &RemapTable,
+) -> RemapStats {
+    let version = version.into();
+    let mut stats = RemapStats::default();
+    visitor::with_visitor(
+        &mut |kind, data| table.apply(kind, data, &mut stats),
+        || typ.convert(data, version, version),
+    );
+    if stats.deleted != 0 {
+        match data {
+            JValue::Compound(data) if data.contains_key(TOMBSTONE) => data.clear(),
+            JValue::Compound(data) => remove_tombstones_in_map(data),
+            JValue::List(list) => remove_tombstones_in_list(list),
+            _ => {}
+        }
+    }
+    stats
+}
+
+fn is_tombstone(value: &JValue) -> bool {
+    matches!(value, JValue::Compound(compound) if compound.contains_key(TOMBSTONE))
+}
+
+fn remove_tombstones_in_map(data: &mut JCompound) {
+    data.retain(|_, value| !is_tombstone(value));
+    for (key, value) in data.iter_mut() {
+        match value {
+            JValue::List(JList::Compound(compounds))
+                if POSITIONAL_LISTS.iter().any(|name| key == name) =>
+            {
+                for compound in compounds {
+                    if compound.contains_key(TOMBSTONE) {
+                        compound.clear();
+                    } else {
+                        remove_tombstones_in_map(compound);
+                    }
+                }
+            }
+            JValue::Compound(compound) => remove_tombstones_in_map(compound),
+            JValue::List(list) => remove_tombstones_in_list(list),
+            _ => {}
+        }
+    }
+}
+
+fn remove_tombstones_in_list(list: &mut JList) {
+    match list {
+        JList::Compound(compounds) => {
+            compounds.retain(|compound| !compound.contains_key(TOMBSTONE));
+            for compound in compounds {
+                remove_tombstones_in_map(compound);
+            }
+        }
+        JList::List(lists) => {
+            for list in lists {
+                remove_tombstones_in_list(list);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types;
+    use valence_nbt::{compound, jcompound};
+
+    #[test]
+    fn test_remap() {
+        let mut entity_chunk = jcompound! {
+            "Entities" => JList::Compound(vec![
+                jcompound! {
+                    "id" => "mymod:mob",
+                },
+                jcompound! {
+                    "id" => "minecraft:zombie",
+                    "HandItems" => JList::Compound(vec![
+                        jcompound! {
+                            "id" => "mymod:copper",
+                            "Count" => 1i8,
+                        },
+                        jcompound! {
+                            "id" => "mymod:gem",
+                            "Count" => 1i8,
+                        },
+                    ]),
+                    "Passengers" => JList::Compound(vec![
+                        jcompound! {
+                            "id" => "minecraft:falling_block",
+                            "BlockState" => jcompound! {
+                                "Name" => "mymod:copper_ore",
+                            },
+                        },
+                    ]),
+                },
+            ]),
+        };
+        let table = RemapTable::new()
+            .delete_entity("mymod:*")
+            .rename_item("mymod:copper", "minecraft:copper_ingot")
+            .delete_item("mymod:gem")
+            .rename_block("mymod:*", "othermod:*");
+
+        let stats = remap_map(types::entity_chunk_ref(), &mut entity_chunk, 3700, &table);
+
+        assert_eq!(
+            RemapStats {
+                renamed: 2,
+                deleted: 2,
+            },
+            stats
+        );
+        let expected = jcompound! {
+            "Entities" => JList::Compound(vec![
+                jcompound! {
+                    "id" => "minecraft:zombie",
+                    "HandItems" => JList::Compound(vec![
+                        jcompound! {
+                            "id" => "minecraft:copper_ingot",
+                            "Count" => 1i8,
+                        },
+                        JCompound::new(),
+                    ]),
+                    "Passengers" => JList::Compound(vec![
+                        jcompound! {
+                            "id" => "minecraft:falling_block",
+                            "BlockState" => jcompound! {
+                                "Name" => "othermod:copper_ore",
+                            },
+                        },
+                    ]),
+                },
+            ]),
+        };
+        assert_eq!(expected, entity_chunk);
+    }
+
+    #[test]
+    fn test_remap_deletes_block_entities() {
+        let mut chunk = jcompound! {
+            "sections" => JList::Compound(vec![jcompound! {
+                "Y" => 0i8,
+                "block_states" => jcompound! {
+                    "palette" => JList::Compound(vec![
+                        jcompound! {
+                            "Name" => "minecraft:chest",
+                        },
+                        jcompound! {
+                            "Name" => "mymod:machine",
+                        },
+                    ]),
+                    "data" => vec![0i64; 256],
+                },
+            }]),
+            "block_entities" => JList::Compound(vec![
+                jcompound! {
+                    "id" => "minecraft:chest",
+                    "x" => 0,
+                    "y" => 0,
+                    "z" => 0,
+                },
+                jcompound! {
+                    "id" => "mymod:machine",
+                    "x" => 1,
+                    "y" => 0,
+                    "z" => 0,
+                },
+            ]),
+        };
+        let table = RemapTable::new().delete_block("mymod:*");
+
+        let stats = remap_map(types::chunk_ref(), &mut chunk, 3700, &table);
+
+        assert_eq!(2, stats.deleted);
+        let Some(JValue::List(JList::Compound(block_entities))) = chunk.get("block_entities")
+        else {
+            panic!("chunk lost its block entities");
+        };
+        assert_eq!(1, block_entities.len());
+        assert_eq!(
+            Some(&JValue::from("minecraft:chest")),
+            block_entities[0].get("id")
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use crate::helpers::bit_storage::{
+    ceil_log2, BitStorage, BitStorageMut, BitStorageOwned, PackedBitStorage,
+};
+use crate::schematic::SchematicError;
+use crate::{convert_map, convert_value, types};
+use world_transmuter_engine::{
+    AbstractMapDataType, DataVersion, JCompound, JList, JValue, JValueMut,
+};
+
+/// Upgrades a Litematica schematic (`.litematic`) to `to_version`. `root` is the root compound of the file.
+///
+/// The palette, tile entities, entities and pending block ticks of each region are converted. If palette entries are
+/// merged, the block states of the region are repacked.
+pub fn upgrade_litematica_schematic(
+    root: &mut JCompound,
+    to_version: impl Into<DataVersion>,
+) -> Result<(), SchematicError> {
+    let to_version = to_version.into();
+    let from_version = root
+        .get("MinecraftDataVersion")
+        .and_then(|version| version.as_i32())
+        .ok_or(SchematicError::MissingField("MinecraftDataVersion"))?;
+    let from_version = u32::try_from(from_version)
+        .map_err(|_| SchematicError::InvalidData("MinecraftDataVersion"))?;
+    let from_version = DataVersion::from(from_version);
+    if from_version >= to_version {
+        return Ok(());
+    }
+
+    if let Some(JValue::Compound(regions)) = root.get_mut("Regions") {
+        // check every region before changing any, so that nothing is changed if one is invalid
+        let volumes = regions
+            .values()
+            .map(|region| match region {
+                JValue::Compound(region) => region_volume(region),
+                _ => Err(SchematicError::InvalidData("Regions")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for (region, volume) in regions.values_mut().zip(volumes) {
+            let JValue::Compound(region) = region else {
+                unreachable!()
+            };
+            upgrade_region(region, volume, from_version, to_version);
+        }
+    }
+
+    root.insert("MinecraftDataVersion", to_version.get_version() as i32);
+    Ok(())
+}
+
+/// Returns the number of blocks in a region, checking that its block states fit its palette.
+fn region_volume(region: &JCompound) -> Result<usize, SchematicError> {
+    let Some(JValue::Compound(size)) = region.get("Size") else {
+        return Err(SchematicError::MissingField("Size"));
+    };
+    // the size is negative along the axes the region extends backwards in
+    let mut volume = 1usize;
+    for axis in ["x", "y", "z"] {
+        let length = size
+            .get(axis)
+            .and_then(|length| length.as_i32())
+            .ok_or(SchematicError::MissingField("Size"))?;
+        volume = volume
+            .checked_mul(length.unsigned_abs() as usize)
+            .ok_or(SchematicError::InvalidData("Size"))?;
+    }
+
+    let palette_len = match region.get("BlockStatePalette") {
+        Some(JValue::List(JList::Compound(palette))) => palette.len(),
+        Some(JValue::List(palette)) if palette.is_empty() => 0,
+        _ => return Err(SchematicError::MissingField("BlockStatePalette")),
+    };
+    let Some(JValue::LongArray(block_states)) = region.get("BlockStates") else {
+        return Err(SchematicError::MissingField("BlockStates"));
+    };
+    let storage = PackedBitStorage::try_wrap(palette_bits(palette_len), volume, &block_states[..])
+        .map_err(|_| SchematicError::InvalidData("BlockStates"))?;
+    if (0..volume).any(|index| storage.get(index) as usize >= palette_len.max(1)) {
+        return Err(SchematicError::InvalidData("BlockStates"));
+    }
+    Ok(volume)
+}
+
+fn palette_bits(palette_len: usize) -> u8 {
+    ceil_log2(palette_len.max(1) as u32).max(2)
+}
+
+fn upgrade_region(
+    region: &mut JCompound,
+    volume: usize,
+    from_version: DataVersion,
+    to_version: DataVersion,
+) {
+    let Some(JValue::List(JList::Compound(palette))) = region.get_mut("BlockStatePalette") else {
+        return;
+    };
+    let old_bits = palette_bits(palette.len());
+    let mut new_palette = Vec::<JCompound>::with_capacity(palette.len());
+    let mut remap = Vec::with_capacity(palette.len());
+    for mut state in std::mem::take(palette) {
+        convert_map(
+            types::block_state_ref(),
+            &mut state,
+            from_version,
+            to_version,
+        );
+        let index = match new_palette.iter().position(|existing| *existing == state) {
+            Some(index) => index,
+            None => {
+                new_palette.push(state);
+                new_palette.len() - 1
+            }
+        };
+        remap.push(index as u32);
+    }
+    let merged = new_palette.len() != remap.len();
+    let new_bits = palette_bits(new_palette.len());
+    *palette = new_palette;
+
+    if merged {
+        if let Some(JValue::LongArray(block_states)) = region.get_mut("BlockStates") {
+            let old_storage = PackedBitStorage::wrap(old_bits, volume, &block_states[..]);
+            let mut new_storage = PackedBitStorage::new(new_bits, volume);
+            for index in 0..volume {
+                new_storage.set(index, remap[old_storage.get(index) as usize]);
+            }
+            *block_states = new_storage.into_raw();
+        }
+    }
+
+    convert_list(
+        region,
+        "TileEntities",
+        types::tile_entity_ref(),
+        from_version,
+        to_version,
+    );
+    convert_list(
+        region,
+        "Entities",
+        types::entity_ref(),
+        from_version,
+        to_version,
+    );
+    if let Some(JValue::List(JList::Compound(ticks))) = region.get_mut("PendingBlockTicks") {
+        for tick in ticks {
+            if let Some(JValue::String(block)) = tick.get_mut("Block") {
+                convert_value(
+                    types::block_name_ref(),
+                    &mut JValueMut::String(block),
+                    from_version,
+                    to_version,
+                );
+            }
+        }
+    }
+}
+
+fn convert_list(
+    region: &mut JCompound,
+    key: &str,
+    typ: impl AbstractMapDataType + Copy,
+    from_version: DataVersion,
+    to_version: DataVersion,
+) {
+    if let Some(JValue::List(JList::Compound(objects))) = region.get_mut(key) {
+        for object in objects {
+            convert_map(typ, object, from_version, to_version);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use valence_nbt::{compound, jcompound};
+
+    fn block(name: &str) -> JCompound {
+        jcompound! {
+            "Name" => name,
+        }
+    }
+
+    #[test]
+    fn test_upgrade_litematica() {
+        // grass was renamed to short_grass, leaving 4 palette entries which need 2 bits instead of 3
+        let palette = ["air", "grass", "short_grass", "stone", "dirt"];
+        let values = [0, 1, 2, 3, 4, 1, 2, 0, 4, 3];
+        let mut storage = PackedBitStorage::new(3, values.len());
+        for (index, value) in values.iter().enumerate() {
+            storage.set(index, *value);
+        }
+        let mut root = jcompound! {
+            "MinecraftDataVersion" => 3337,
+            "Version" => 6,
+            "Regions" => jcompound! {
+                "house" => jcompound! {
+                    "Position" => jcompound! {
+                        "x" => 0,
+                        "y" => 0,
+                        "z" => 0,
+                    },
+                    "Size" => jcompound! {
+                        "x" => -5,
+                        "y" => 1,
+                        "z" => 2,
+                    },
+                    "BlockStatePalette" => JList::Compound(
+                        palette.iter().map(|name| block(&format!("minecraft:{name}"))).collect(),
+                    ),
+                    "BlockStates" => storage.into_raw(),
+                    "TileEntities" => JList::Compound(vec![jcompound! {
+                        "id" => "minecraft:sign",
+                        "x" => 0,
+                        "y" => 0,
+                        "z" => 0,
+                        "Text1" => r#"{"text":"hello"}"#,
+                    }]),
+                    "Entities" => JList::Compound(Vec::new()),
+                    "PendingBlockTicks" => JList::Compound(vec![jcompound! {
+                        "Block" => "minecraft:grass",
+                        "Time" => 1,
+                    }]),
+                },
+            },
+        };
+        upgrade_litematica_schematic(&mut root, 3700).unwrap();
+
+        assert_eq!(Some(&JValue::Int(3700)), root.get("MinecraftDataVersion"));
+        let Some(JValue::Compound(regions)) = root.get("Regions") else {
+            panic!("schematic lost its regions");
+        };
+        let Some(JValue::Compound(region)) = regions.get("house") else {
+            panic!("schematic lost its region");
+        };
+        let expected_palette = JList::Compound(vec![
+            block("minecraft:air"),
+            block("minecraft:short_grass"),
+            block("minecraft:stone"),
+            block("minecraft:dirt"),
+        ]);
+        assert_eq!(
+            Some(&JValue::List(expected_palette)),
+            region.get("BlockStatePalette")
+        );
+        let Some(JValue::LongArray(block_states)) = region.get("BlockStates") else {
+            panic!("region lost its block states");
+        };
+        let storage = PackedBitStorage::wrap(2, values.len(), &block_states[..]);
+        let new_values: Vec<_> = (0..values.len()).map(|index| storage.get(index)).collect();
+        assert_eq!(vec![0, 1, 1, 2, 3, 1, 1, 0, 3, 2], new_values);
+
+        let Some(JValue::List(JList::Compound(tile_entities))) = region.get("TileEntities") else {
+            panic!("region lost its tile entities");
+        };
+        assert!(tile_entities[0].contains_key("front_text"));
+        let Some(JValue::List(JList::Compound(ticks))) = region.get("PendingBlockTicks") else {
+            panic!("region lost its pending ticks");
+        };
+        assert_eq!(
+            Some(&JValue::from("minecraft:short_grass")),
+            ticks[0].get("Block")
+        );
+    }
+
+    #[test]
+    fn test_negative_version() {
+        let mut root = jcompound! {
+            "MinecraftDataVersion" => -1,
+            "Version" => 6,
+            "Regions" => JCompound::new(),
+        };
+        let original = root.clone();
+        assert_eq!(
+            Err(SchematicError::InvalidData("MinecraftDataVersion")),
+            upgrade_litematica_schematic(&mut root, 3700)
+        );
+        assert_eq!(original, root);
+    }
+}
@@ -0,0 +1,628 @@
+use crate::detect::{detect_version, Confidence};
+use crate::helpers::bit_storage::{ceil_log2, BitStorage, PackedBitStorage};
+use crate::helpers::block_state::{BlockState, BlockStateOwned};
+use crate::schematic::{write_varints, SchematicError};
+use crate::types::TypeKind;
+use crate::{convert_map, types};
+use java_string::JavaString;
+use std::collections::BTreeMap;
+use world_transmuter_engine::{DataVersion, JCompound, JList, JValue};
+
+/// MCEdit schematics have no data version, and the format was replaced by Sponge schematics in 1.13, so they are from
+/// 1.12.2 at the latest.
+const LATEST_LEGACY_DATA_VERSION: u32 = 1343;
+
+struct LegacySchematic<'a> {
+    width: usize,
+    height: usize,
+    length: usize,
+    blocks: &'a [i8],
+    add_blocks: &'a [i8],
+    data: &'a [i8],
+}
+
+impl<'a> LegacySchematic<'a> {
+    fn from_nbt(root: &'a JCompound) -> Result<Self, SchematicError> {
+        if let Some(materials) = root.get("Materials") {
+            if *materials != JValue::from("Alpha") {
+                return Err(SchematicError::InvalidData("Materials"));
+            }
+        }
+        let dimension = |key: &'static str| {
+            root.get(key)
+                .and_then(|value| value.as_i32())
+                .ok_or(SchematicError::MissingField(key))
+                .and_then(|value| {
+                    usize::try_from(value).map_err(|_| SchematicError::InvalidData(key))
+                })
+        };
+        let width = dimension("Width")?;
+        let height = dimension("Height")?;
+        let length = dimension("Length")?;
+        // the blocks are flattened as chunks, which are at most 256 blocks high
+        if height > 256 {
+            return Err(SchematicError::InvalidData("Height"));
+        }
+
+        let volume = width
+            .checked_mul(height)
+            .and_then(|area| area.checked_mul(length))
+            .ok_or(SchematicError::InvalidData("Width"))?;
+        let byte_array = |key: &'static str| match root.get(key) {
+            Some(JValue::ByteArray(array)) if array.len() == volume => Ok(&array[..]),
+            Some(_) => Err(SchematicError::InvalidData(key)),
+            None => Err(SchematicError::MissingField(key)),
+        };
+        let blocks = byte_array("Blocks")?;
+        let data = byte_array("Data")?;
+        let add_blocks = match root.get("AddBlocks") {
+            Some(JValue::ByteArray(add_blocks)) => &add_blocks[..],
+            _ => &[],
+        };
+
+        Ok(Self {
+            width,
+            height,
+            length,
+            blocks,
+            add_blocks,
+            data,
+        })
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (y * self.length + z) * self.width + x
+    }
+
+    /// Returns the block id and metadata at `index`, packed as `id << 4 | data`.
+    fn state_id(&self, index: usize) -> u16 {
+        let mut id = self.blocks[index] as u8 as u16;
+        if let Some(&add) = self.add_blocks.get(index >> 1) {
+            let add = if index & 1 == 0 {
+                add as u8 & 15
+            } else {
+                add as u8 >> 4
+            };
+            id |= (add as u16) << 8;
+        }
+        (id << 4) | (self.data[index] as u8 & 15) as u16
+    }
+
+    /// Builds the pre-1.13 chunk containing the blocks from `chunk_x * 16` and `chunk_z * 16`, so that they can be
+    /// flattened like a world, including the states that depend on block entities.
+    fn to_chunk(&self, chunk_x: usize, chunk_z: usize, tile_entities: &[JCompound]) -> JCompound {
+        let mut sections = Vec::new();
+        for section_y in 0..self.height.div_ceil(16) {
+            let mut blocks = vec![0i8; 4096];
+            let mut add = vec![0i8; 2048];
+            let mut data = vec![0i8; 2048];
+            for y in section_y * 16..(section_y * 16 + 16).min(self.height) {
+                for z in chunk_z * 16..(chunk_z * 16 + 16).min(self.length) {
+                    for x in chunk_x * 16..(chunk_x * 16 + 16).min(self.width) {
+                        let state_id = self.state_id(self.index(x, y, z));
+                        let index = ((y & 15) << 8) | ((z & 15) << 4) | (x & 15);
+                        let shift = (index & 1) << 2;
+                        blocks[index] = (state_id >> 4) as u8 as i8;
+                        add[index >> 1] |= (((state_id >> 12) as u8 & 15) << shift) as i8;
+                        data[index >> 1] |= (((state_id & 15) as u8) << shift) as i8;
+                    }
+                }
+            }
+            let mut section = JCompound::new();
+            section.insert("Y", section_y as i8);
+            section.insert("Blocks", blocks);
+            section.insert("Add", add);
+            section.insert("Data", data);
+            sections.push(section);
+        }
+
+        let in_chunk = |tile_entity: &&JCompound| {
+            let coord = |key| {
+                tile_entity
+                    .get(key)
+                    .and_then(|value| value.as_i32())
+                    .and_then(|value| usize::try_from(value).ok())
+            };
+            matches!(
+                (coord("x"), coord("y"), coord("z")),
+                (Some(x), Some(y), Some(z))
+                    if x / 16 == chunk_x && z / 16 == chunk_z
+                        && x < self.width && y < self.height && z < self.length
+            )
+        };
+
+        let mut level = JCompound::new();
+        level.insert("xPos", chunk_x as i32);
+        level.insert("zPos", chunk_z as i32);
+        level.insert("Sections", JList::Compound(sections));
+        level.insert(
+            "TileEntities",
+            JList::Compound(tile_entities.iter().filter(in_chunk).cloned().collect()),
+        );
+        let mut chunk = JCompound::new();
+        chunk.insert("Level", level);
+        chunk
+    }
+}
+
+/// Guesses the data version of an MCEdit schematic, which doesn't store one, from the ids of its block entities,
+/// entities and the items inside them. Schematics that give no clue, such as those with only blocks, are assumed to be
+/// from 1.12.2.
+pub fn detect_mcedit_version(root: &JCompound) -> u32 {
+    let mut detected = Vec::new();
+    for (key, kind) in [
+        ("TileEntities", TypeKind::TileEntity),
+        ("Entities", TypeKind::Entity),
+    ] {
+        let Some(JValue::List(JList::Compound(objects))) = root.get(key) else {
+            continue;
+        };
+        for object in objects {
+            detected.push(detect_version(kind, object));
+            if let Some(JValue::List(JList::Compound(items))) = object.get("Items") {
+                for item in items {
+                    detected.push(detect_version(TypeKind::ItemStack, item));
+                }
+            }
+        }
+    }
+    // each object gives the earliest version it can be from, so the schematic is from the latest of those
+    detected
+        .into_iter()
+        .flatten()
+        .filter(|detected| detected.confidence >= Confidence::Medium)
+        .map(|detected| detected.data_version)
+        .max()
+        .map_or(LATEST_LEGACY_DATA_VERSION, |version| {
+            version.min(LATEST_LEGACY_DATA_VERSION)
+        })
+}
+
+/// Converts an MCEdit schematic (`.schematic`), as written by MCEdit and by WorldEdit before 1.13, to a vanilla
+/// structure at `to_version`. `root` is the root compound of the file, whose data is from `from_version`, which has to
+/// be before the flattening. If it isn't known, it can be guessed with [`detect_mcedit_version`].
+///
+/// The blocks are flattened as they would be in a world, including the states that are stored in block entities, such
+/// as the contents of flower pots. Entity positions are relative to the schematic. Like in a world that is upgraded,
+/// the connections between blocks, such as of fences, are left to be updated when the structure is placed.
+pub fn mcedit_to_structure(
+    root: &JCompound,
+    from_version: impl Into<DataVersion>,
+    to_version: impl Into<DataVersion>,
+) -> Result<JCompound, SchematicError> {
+    let from_version = from_version.into();
+    let to_version = to_version.into();
+    let flattened_version = DataVersion::new(1451, 1);
+    if from_version.get_version() >= flattened_version.get_version() {
+        return Err(SchematicError::UnsupportedDataVersion(
+            from_version.get_version(),
+        ));
+    }
+    let schematic = LegacySchematic::from_nbt(root)?;
+    let tile_entities = match root.get("TileEntities") {
+        Some(JValue::List(JList::Compound(tile_entities))) => &tile_entities[..],
+        _ => &[],
+    };
+
+    let mut palette = Vec::<BlockStateOwned>::new();
+    let mut palette_indexes = BTreeMap::new();
+    let mut states = vec![0u32; schematic.width * schematic.height * schematic.length];
+    let mut blocks_nbt = BTreeMap::new();
+    for chunk_x in 0..schematic.width.div_ceil(16) {
+        for chunk_z in 0..schematic.length.div_ceil(16) {
+            let mut chunk = schematic.to_chunk(chunk_x, chunk_z, tile_entities);
+            convert_map(
+                types::chunk_ref(),
+                &mut chunk,
+                from_version,
+                flattened_version,
+            );
+            let Some(JValue::Compound(level)) = chunk.remove("Level") else {
+                continue;
+            };
+
+            if let Some(JValue::List(JList::Compound(sections))) = level.get("Sections") {
+                for section in sections {
+                    let Some(section_y) = section
+                        .get("Y")
+                        .and_then(|y| y.as_i32())
+                        .and_then(|y| usize::try_from(y).ok())
+                    else {
+                        continue;
+                    };
+                    let (
+                        Some(JValue::List(JList::Compound(section_palette))),
+                        Some(JValue::LongArray(block_states)),
+                    ) = (section.get("Palette"), section.get("BlockStates"))
+                    else {
+                        continue;
+                    };
+                    let section_palette: Vec<u32> = section_palette
+                        .iter()
+                        .map(|state| {
+                            let state = BlockState::from_nbt(state)
+                                .map(|state| state.to_owned())
+                                .unwrap_or_else(|| BlockStateOwned {
+                                    name: JavaString::from("minecraft:air"),
+                                    properties: BTreeMap::new(),
+                                });
+                            *palette_indexes.entry(state.clone()).or_insert_with(|| {
+                                palette.push(state);
+                                palette.len() as u32 - 1
+                            })
+                        })
+                        .collect();
+                    let bits = ceil_log2(section_palette.len() as u32).max(4);
+                    let Ok(storage) = PackedBitStorage::try_wrap(bits, 4096, &block_states[..])
+                    else {
+                        continue;
+                    };
+                    for y in section_y * 16..(section_y * 16 + 16).min(schematic.height) {
+                        for z in chunk_z * 16..(chunk_z * 16 + 16).min(schematic.length) {
+                            for x in chunk_x * 16..(chunk_x * 16 + 16).min(schematic.width) {
+                                let index = ((y & 15) << 8) | ((z & 15) << 4) | (x & 15);
+                                if let Some(&state) =
+                                    section_palette.get(storage.get(index) as usize)
+                                {
+                                    states[schematic.index(x, y, z)] = state;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            if let Some(JValue::List(JList::Compound(tile_entities))) = level.get("TileEntities") {
+                for tile_entity in tile_entities {
+                    let mut tile_entity = tile_entity.clone();
+                    let [x, y, z] = ["x", "y", "z"].map(|key| {
+                        tile_entity
+                            .remove(key)
+                            .and_then(|value| value.as_i32())
+                            .unwrap_or(0)
+                    });
+                    blocks_nbt.insert((x, y, z), tile_entity);
+                }
+            }
+        }
+    }
+
+    // the empty palette of an empty schematic is fine, its blocks are never looked up
+    let mut blocks = Vec::with_capacity(states.len());
+    for y in 0..schematic.height {
+        for z in 0..schematic.length {
+            for x in 0..schematic.width {
+                let mut block = JCompound::new();
+                block.insert("pos", JList::Int(vec![x as i32, y as i32, z as i32]));
+                block.insert("state", states[schematic.index(x, y, z)] as i32);
+                if let Some(nbt) = blocks_nbt.remove(&(x as i32, y as i32, z as i32)) {
+                    block.insert("nbt", nbt);
+                }
+                blocks.push(block);
+            }
+        }
+    }
+
+    let mut entities = Vec::new();
+    if let Some(JValue::List(JList::Compound(legacy_entities))) = root.get("Entities") {
+        for entity in legacy_entities {
+            let mut entity = entity.clone();
+            convert_map(
+                types::entity_ref(),
+                &mut entity,
+                from_version,
+                flattened_version,
+            );
+            let pos = match entity.get("Pos") {
+                Some(JValue::List(JList::Double(pos))) if pos.len() == 3 => pos.clone(),
+                _ => vec![0.0; 3],
+            };
+            let mut structure_entity = JCompound::new();
+            structure_entity.insert(
+                "blockPos",
+                JList::Int(pos.iter().map(|coord| coord.floor() as i32).collect()),
+            );
+            structure_entity.insert("pos", JList::Double(pos));
+            structure_entity.insert("nbt", entity);
+            entities.push(structure_entity);
+        }
+    }
+
+    let mut structure = JCompound::new();
+    structure.insert("DataVersion", flattened_version.get_version() as i32);
+    structure.insert(
+        "size",
+        JList::Int(vec![
+            schematic.width as i32,
+            schematic.height as i32,
+            schematic.length as i32,
+        ]),
+    );
+    structure.insert(
+        "palette",
+        JList::Compound(palette.iter().map(BlockStateOwned::to_nbt).collect()),
+    );
+    structure.insert("blocks", JList::Compound(blocks));
+    structure.insert("entities", JList::Compound(entities));
+
+    convert_map(
+        types::structure_ref(),
+        &mut structure,
+        flattened_version,
+        to_version,
+    );
+    structure.insert("DataVersion", to_version.get_version() as i32);
+    Ok(structure)
+}
+
+/// Converts an MCEdit schematic to a version 2 Sponge schematic at `to_version`, like [`mcedit_to_structure`].
+pub fn mcedit_to_sponge(
+    root: &JCompound,
+    from_version: impl Into<DataVersion>,
+    to_version: impl Into<DataVersion>,
+) -> Result<JCompound, SchematicError> {
+    let to_version = to_version.into();
+    let mut structure = mcedit_to_structure(root, from_version, to_version)?;
+    let schematic = LegacySchematic::from_nbt(root)?;
+
+    let mut palette = JCompound::new();
+    if let Some(JValue::List(JList::Compound(states))) = structure.get("palette") {
+        for (index, state) in states.iter().enumerate() {
+            let state =
+                BlockState::from_nbt(state).ok_or(SchematicError::InvalidData("palette"))?;
+            let mut name = state.name.to_owned();
+            if !state.properties.is_empty() {
+                let properties: Vec<_> = state
+                    .properties
+                    .iter()
+                    .map(|(key, value)| format!("{key}={value}"))
+                    .collect();
+                name.push_str(&format!("[{}]", properties.join(",")));
+            }
+            palette.insert(name, index as i32);
+        }
+    }
+
+    let mut block_data = Vec::with_capacity(schematic.width * schematic.height * schematic.length);
+    let mut block_entities = Vec::new();
+    if let Some(JValue::List(JList::Compound(blocks))) = structure.get_mut("blocks") {
+        // the blocks were written in the order of the Sponge block data
+        for block in blocks {
+            block_data.push(
+                block
+                    .get("state")
+                    .and_then(|state| state.as_i32())
+                    .unwrap_or(0) as u32,
+            );
+            if let Some(JValue::Compound(mut nbt)) = block.remove("nbt") {
+                let Some(JValue::List(JList::Int(pos))) = block.remove("pos") else {
+                    continue;
+                };
+                if let Some(id) = nbt.remove("id") {
+                    nbt.insert("Id", id);
+                }
+                nbt.insert("Pos", JValue::IntArray(pos));
+                block_entities.push(nbt);
+            }
+        }
+    }
+
+    let mut entities = Vec::new();
+    if let Some(JValue::List(JList::Compound(structure_entities))) = structure.get_mut("entities") {
+        for entity in structure_entities {
+            let (Some(JValue::Compound(mut nbt)), Some(pos)) =
+                (entity.remove("nbt"), entity.remove("pos"))
+            else {
+                continue;
+            };
+            if let Some(id) = nbt.remove("id") {
+                nbt.insert("Id", id);
+            }
+            nbt.insert("Pos", pos);
+            entities.push(nbt);
+        }
+    }
+
+    let mut sponge = JCompound::new();
+    sponge.insert("Version", 2);
+    sponge.insert("DataVersion", to_version.get_version() as i32);
+    sponge.insert("Width", schematic.width as i16);
+    sponge.insert("Height", schematic.height as i16);
+    sponge.insert("Length", schematic.length as i16);
+    sponge.insert("Offset", vec![0, 0, 0]);
+    sponge.insert("PaletteMax", palette.len() as i32);
+    sponge.insert("Palette", palette);
+    sponge.insert("BlockData", write_varints(block_data));
+    sponge.insert("BlockEntities", JList::Compound(block_entities));
+    sponge.insert("Entities", JList::Compound(entities));
+    Ok(sponge)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schematic::read_varints;
+    use valence_nbt::{compound, jcompound};
+
+    fn legacy_schematic() -> JCompound {
+        // a 2x1x1 schematic holding a flower pot with a poppy and oak planks
+        jcompound! {
+            "Width" => 2i16,
+            "Height" => 1i16,
+            "Length" => 1i16,
+            "Materials" => "Alpha",
+            "Blocks" => vec![140u8 as i8, 5],
+            "Data" => vec![0i8, 0],
+            "TileEntities" => JList::Compound(vec![jcompound! {
+                "id" => "minecraft:flower_pot",
+                "x" => 0,
+                "y" => 0,
+                "z" => 0,
+                "Item" => "minecraft:red_flower",
+                "Data" => 0,
+            }]),
+            "Entities" => JList::Compound(vec![jcompound! {
+                "id" => "minecraft:zombie_pigman",
+                "Pos" => JList::Double(vec![1.5, 0.0, 0.5]),
+            }]),
+        }
+    }
+
+    #[test]
+    fn test_mcedit_to_structure() {
+        let schematic = legacy_schematic();
+        // namespaced entity ids appeared in 1.11
+        assert_eq!(705, detect_mcedit_version(&schematic));
+        let structure = mcedit_to_structure(&schematic, 1343, 3700).unwrap();
+        assert_eq!(Some(&JValue::Int(3700)), structure.get("DataVersion"));
+
+        let Some(JValue::List(JList::Compound(palette))) = structure.get("palette") else {
+            panic!("structure has no palette");
+        };
+        let Some(JValue::List(JList::Compound(blocks))) = structure.get("blocks") else {
+            panic!("structure has no blocks");
+        };
+        let names: Vec<_> = blocks
+            .iter()
+            .map(|block| {
+                let state = block.get("state").and_then(|state| state.as_i32()).unwrap();
+                palette[state as usize].get("Name").cloned()
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                Some(JValue::from("minecraft:potted_poppy")),
+                Some(JValue::from("minecraft:oak_planks")),
+            ],
+            names
+        );
+        assert!(!blocks[0].contains_key("nbt"));
+
+        let Some(JValue::List(JList::Compound(entities))) = structure.get("entities") else {
+            panic!("structure has no entities");
+        };
+        let Some(JValue::Compound(entity)) = entities[0].get("nbt") else {
+            panic!("entity has no nbt");
+        };
+        assert_eq!(
+            Some(&JValue::from("minecraft:zombified_piglin")),
+            entity.get("id")
+        );
+        assert_eq!(
+            Some(&JValue::List(JList::Int(vec![1, 0, 0]))),
+            entities[0].get("blockPos")
+        );
+    }
+
+    #[test]
+    fn test_mcedit_to_sponge() {
+        // put a chest in place of the oak planks, which keeps its block entity
+        let mut schematic = legacy_schematic();
+        schematic.insert("Blocks", vec![140u8 as i8, 54]);
+        if let Some(JValue::List(JList::Compound(tile_entities))) =
+            schematic.get_mut("TileEntities")
+        {
+            tile_entities.push(jcompound! {
+                "id" => "minecraft:chest",
+                "x" => 1,
+                "y" => 0,
+                "z" => 0,
+                "Items" => JList::Compound(Vec::new()),
+            });
+        }
+        let sponge = mcedit_to_sponge(&schematic, 1343, 3700).unwrap();
+        let Some(JValue::Compound(palette)) = sponge.get("Palette") else {
+            panic!("schematic has no palette");
+        };
+        let Some(JValue::ByteArray(block_data)) = sponge.get("BlockData") else {
+            panic!("schematic has no block data");
+        };
+        let block_data = read_varints(block_data).unwrap();
+        assert_eq!(
+            Some(&JValue::Int(block_data[0] as i32)),
+            palette.get("minecraft:potted_poppy")
+        );
+        assert_eq!(
+            Some(&JValue::Int(palette.len() as i32)),
+            sponge.get("PaletteMax")
+        );
+        let Some(JValue::List(JList::Compound(block_entities))) = sponge.get("BlockEntities")
+        else {
+            panic!("schematic has no block entities");
+        };
+        assert_eq!(1, block_entities.len());
+        assert_eq!(
+            Some(&JValue::from("minecraft:chest")),
+            block_entities[0].get("Id")
+        );
+        assert_eq!(
+            Some(&JValue::IntArray(vec![1, 0, 0])),
+            block_entities[0].get("Pos")
+        );
+    }
+
+    #[test]
+    fn test_pre_1_11_mcedit_schematic() {
+        let schematic = jcompound! {
+            "Width" => 1i16,
+            "Height" => 1i16,
+            "Length" => 1i16,
+            "Blocks" => vec![140u8 as i8],
+            "Data" => vec![0i8],
+            "TileEntities" => JList::Compound(vec![jcompound! {
+                "id" => "FlowerPot",
+                "x" => 0,
+                "y" => 0,
+                "z" => 0,
+                "Item" => "minecraft:red_flower",
+                "Data" => 0,
+            }]),
+            "Entities" => JList::Compound(vec![jcompound! {
+                "id" => "PigZombie",
+                "Pos" => JList::Double(vec![0.5, 0.0, 0.5]),
+            }]),
+        };
+        let from_version = detect_mcedit_version(&schematic);
+        assert_eq!(99, from_version);
+
+        let structure = mcedit_to_structure(&schematic, from_version, 3700).unwrap();
+        let Some(JValue::List(JList::Compound(palette))) = structure.get("palette") else {
+            panic!("structure has no palette");
+        };
+        let Some(JValue::List(JList::Compound(blocks))) = structure.get("blocks") else {
+            panic!("structure has no blocks");
+        };
+        let state = blocks[0].get("state").and_then(|state| state.as_i32());
+        assert_eq!(
+            Some(&JValue::from("minecraft:potted_poppy")),
+            palette[state.unwrap() as usize].get("Name")
+        );
+        let Some(JValue::List(JList::Compound(entities))) = structure.get("entities") else {
+            panic!("structure has no entities");
+        };
+        let Some(JValue::Compound(entity)) = entities[0].get("nbt") else {
+            panic!("entity has no nbt");
+        };
+        assert_eq!(
+            Some(&JValue::from("minecraft:zombified_piglin")),
+            entity.get("id")
+        );
+
+        assert_eq!(
+            Err(SchematicError::UnsupportedDataVersion(1451)),
+            mcedit_to_structure(&schematic, 1451, 3700)
+        );
+    }
+
+    #[test]
+    fn test_mcedit_volume_overflow() {
+        let schematic = jcompound! {
+            "Width" => i32::MAX,
+            "Height" => 256,
+            "Length" => i32::MAX,
+            "Blocks" => Vec::<i8>::new(),
+            "Data" => Vec::<i8>::new(),
+        };
+        assert!(mcedit_to_structure(&schematic, 1343, 3700).is_err());
+    }
+}
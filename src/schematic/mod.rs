@@ -0,0 +1,160 @@
+use crate::{convert_map, convert_value};
+use std::fmt::{Display, Formatter};
+use world_transmuter_engine::{
+    AbstractMapDataType, AbstractValueDataType, DataVersion, JCompound, JValue, JValueMut,
+};
+
+pub mod litematica;
+pub mod mcedit;
+pub mod sponge;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchematicError {
+    UnsupportedVersion(i32),
+    /// The data version can't be converted from by this format.
+    UnsupportedDataVersion(u32),
+    MissingField(&'static str),
+    /// The block or biome data doesn't match the palette.
+    InvalidData(&'static str),
+}
+
+impl Display for SchematicError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchematicError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported schematic version {version}")
+            }
+            SchematicError::UnsupportedDataVersion(version) => {
+                write!(f, "Unsupported data version {version}")
+            }
+            SchematicError::MissingField(field) => write!(f, "Missing field {field}"),
+            SchematicError::InvalidData(field) => write!(f, "Invalid {field}"),
+        }
+    }
+}
+
+impl std::error::Error for SchematicError {}
+
+/// Reads the varint encoded palette indexes used by Sponge schematics.
+pub(crate) fn read_varints(data: &[i8]) -> Option<Vec<u32>> {
+    let mut result = Vec::with_capacity(data.len());
+    let mut value = 0u32;
+    let mut shift = 0;
+    for &byte in data {
+        if shift >= 32 {
+            return None;
+        }
+        value |= ((byte as u8 & 0x7f) as u32) << shift;
+        if byte as u8 & 0x80 == 0 {
+            result.push(value);
+            value = 0;
+            shift = 0;
+        } else {
+            shift += 7;
+        }
+    }
+    if shift != 0 {
+        return None;
+    }
+    Some(result)
+}
+
+pub(crate) fn write_varints(values: impl IntoIterator<Item = u32>) -> Vec<i8> {
+    let mut result = Vec::new();
+    for mut value in values {
+        while value >= 0x80 {
+            result.push((value as u8 | 0x80) as i8);
+            value >>= 7;
+        }
+        result.push(value as i8);
+    }
+    result
+}
+
+/// Converts the names of a palette mapping names to indexes. Returns the new index of each old index if any names were
+/// merged, in which case the remaining indexes are compacted in their old order.
+pub(crate) fn convert_palette(
+    palette: &mut JCompound,
+    typ: impl AbstractValueDataType + Copy,
+    from_version: DataVersion,
+    to_version: DataVersion,
+) -> Result<Option<Vec<u32>>, &'static str> {
+    // read every index before changing the palette, so that it's left alone if one is invalid
+    let mut entries = palette
+        .iter()
+        .map(|(name, index)| {
+            let index = index.as_i32().and_then(|index| u32::try_from(index).ok())?;
+            Some((index, name.clone()))
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or("palette")?;
+    palette.clear();
+    for (_, name) in &mut entries {
+        convert_value(typ, &mut JValueMut::String(name), from_version, to_version);
+    }
+    entries.sort_by_key(|(index, _)| *index);
+
+    let merged = {
+        let mut names: Vec<_> = entries.iter().map(|(_, name)| name).collect();
+        names.sort();
+        names.dedup();
+        names.len() != entries.len()
+    };
+    if !merged {
+        for (index, name) in entries {
+            palette.insert(name, index as i32);
+        }
+        return Ok(None);
+    }
+
+    let max_index = entries.last().map_or(0, |(index, _)| *index);
+    let mut remap = vec![0; max_index as usize + 1];
+    for (index, name) in entries {
+        let next_index = palette.len() as i32;
+        let new_index = match palette.get(name.as_java_str()) {
+            Some(new_index) => new_index.as_i32().unwrap_or(0),
+            None => {
+                palette.insert(name, next_index);
+                next_index
+            }
+        };
+        remap[index as usize] = new_index as u32;
+    }
+    Ok(Some(remap))
+}
+
+/// Converts an object whose id is stored next to it, as `Id`, instead of as its `id` field.
+pub(crate) fn convert_with_id(
+    typ: impl AbstractMapDataType,
+    data: &mut JCompound,
+    id: &mut JValue,
+    from_version: DataVersion,
+    to_version: DataVersion,
+) {
+    let had_id = data.contains_key("id");
+    if !had_id {
+        data.insert("id", id.clone());
+    }
+    convert_map(typ, data, from_version, to_version);
+    let new_id = if had_id {
+        data.get("id").cloned()
+    } else {
+        data.remove("id")
+    };
+    if let Some(new_id) = new_id {
+        *id = new_id;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varints() {
+        let values = [0, 1, 127, 128, 300, 70000];
+        let encoded = write_varints(values);
+        assert_eq!(Some(values.to_vec()), read_varints(&encoded));
+        assert_eq!(None, read_varints(&[0x80u8 as i8]));
+    }
+}
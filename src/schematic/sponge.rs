@@ -0,0 +1,416 @@
+use crate::schematic::{
+    convert_palette, convert_with_id, read_varints, write_varints, SchematicError,
+};
+use crate::types;
+use ahash::AHashSet;
+use world_transmuter_engine::{
+    AbstractMapDataType, AbstractValueDataType, DataVersion, JCompound, JList, JValue,
+};
+
+/// Version 1 schematics have no `DataVersion`, WorldEdit assumes 1.13.2.
+const V1_DATA_VERSION: u32 = 1631;
+
+/// Upgrades a Sponge schematic (`.schem`), as written by WorldEdit, to `to_version`. `root` is the root compound of the
+/// file. Versions 1, 2 and 3 of the format are supported.
+///
+/// The block and biome palettes, block entities and entities are converted, and the block and biome data is re-encoded
+/// if palette entries were merged. Version 1 schematics, which have no `DataVersion`, are upgraded to version 2.
+pub fn upgrade_sponge_schematic(
+    root: &mut JCompound,
+    to_version: impl Into<DataVersion>,
+) -> Result<(), SchematicError> {
+    let to_version = to_version.into();
+    // version 3 wraps the schematic in a Schematic compound
+    let schematic = if matches!(root.get("Schematic"), Some(JValue::Compound(_))) {
+        let Some(JValue::Compound(schematic)) = root.get_mut("Schematic") else {
+            unreachable!()
+        };
+        schematic
+    } else {
+        root
+    };
+
+    let version = schematic
+        .get("Version")
+        .and_then(|version| version.as_i32())
+        .ok_or(SchematicError::MissingField("Version"))?;
+    let from_version = match version {
+        1 => V1_DATA_VERSION,
+        2 | 3 => {
+            let data_version = schematic
+                .get("DataVersion")
+                .and_then(|version| version.as_i32())
+                .ok_or(SchematicError::MissingField("DataVersion"))?;
+            u32::try_from(data_version).map_err(|_| SchematicError::InvalidData("DataVersion"))?
+        }
+        _ => return Err(SchematicError::UnsupportedVersion(version)),
+    };
+    let from_version = DataVersion::from(from_version);
+    if from_version >= to_version {
+        return Ok(());
+    }
+
+    if version == 3 {
+        // check the block and biome data before changing either, so that nothing is changed if one is invalid
+        let block_data = match schematic.get("Blocks") {
+            Some(JValue::Compound(blocks)) => read_palette_data(blocks, "Palette", "Data")?,
+            _ => None,
+        };
+        let biome_data = match schematic.get("Biomes") {
+            Some(JValue::Compound(biomes)) => read_palette_data(biomes, "Palette", "Data")?,
+            _ => None,
+        };
+
+        if let Some(JValue::Compound(blocks)) = schematic.get_mut("Blocks") {
+            convert_palette_data(
+                blocks,
+                "Palette",
+                "Data",
+                block_data,
+                types::flat_block_state_ref(),
+                from_version,
+                to_version,
+            );
+            convert_objects(
+                blocks,
+                "BlockEntities",
+                types::tile_entity_ref(),
+                true,
+                from_version,
+                to_version,
+            );
+        }
+        if let Some(JValue::Compound(biomes)) = schematic.get_mut("Biomes") {
+            convert_palette_data(
+                biomes,
+                "Palette",
+                "Data",
+                biome_data,
+                types::biome_ref(),
+                from_version,
+                to_version,
+            );
+        }
+        convert_objects(
+            schematic,
+            "Entities",
+            types::entity_ref(),
+            true,
+            from_version,
+            to_version,
+        );
+    } else {
+        let block_data = read_palette_data(schematic, "Palette", "BlockData")?;
+        let biome_data = read_palette_data(schematic, "BiomePalette", "BiomeData")?;
+
+        if version == 1 {
+            schematic.insert("Version", 2);
+            if let Some(tile_entities) = schematic.remove("TileEntities") {
+                schematic.insert("BlockEntities", tile_entities);
+            }
+        }
+        let palette_max = convert_palette_data(
+            schematic,
+            "Palette",
+            "BlockData",
+            block_data,
+            types::flat_block_state_ref(),
+            from_version,
+            to_version,
+        );
+        if let Some(palette_max) = palette_max {
+            schematic.insert("PaletteMax", palette_max);
+        }
+        let biome_palette_max = convert_palette_data(
+            schematic,
+            "BiomePalette",
+            "BiomeData",
+            biome_data,
+            types::biome_ref(),
+            from_version,
+            to_version,
+        );
+        if let Some(biome_palette_max) = biome_palette_max {
+            if schematic.contains_key("BiomePaletteMax") {
+                schematic.insert("BiomePaletteMax", biome_palette_max);
+            }
+        }
+        convert_objects(
+            schematic,
+            "BlockEntities",
+            types::tile_entity_ref(),
+            false,
+            from_version,
+            to_version,
+        );
+        convert_objects(
+            schematic,
+            "Entities",
+            types::entity_ref(),
+            false,
+            from_version,
+            to_version,
+        );
+    }
+
+    schematic.insert("DataVersion", to_version.get_version() as i32);
+    Ok(())
+}
+
+/// Checks a palette and decodes the varint data that refers to it, returning the palette indexes.
+fn read_palette_data(
+    container: &JCompound,
+    palette_key: &'static str,
+    data_key: &'static str,
+) -> Result<Option<Vec<u32>>, SchematicError> {
+    let Some(JValue::Compound(palette)) = container.get(palette_key) else {
+        return Ok(None);
+    };
+    let mut indexes = AHashSet::with_capacity(palette.len());
+    for index in palette.values() {
+        let index = index
+            .as_i32()
+            .and_then(|index| u32::try_from(index).ok())
+            .ok_or(SchematicError::InvalidData(palette_key))?;
+        indexes.insert(index);
+    }
+
+    let Some(JValue::ByteArray(data)) = container.get(data_key) else {
+        return Ok(None);
+    };
+    let data = read_varints(data).ok_or(SchematicError::InvalidData(data_key))?;
+    // the palette can have gaps, which the data must not refer to
+    if data.iter().any(|index| !indexes.contains(index)) {
+        return Err(SchematicError::InvalidData(data_key));
+    }
+    Ok(Some(data))
+}
+
+/// Converts a palette and re-encodes the data that refers to it, which has been checked by [`read_palette_data`],
+/// returning the new size of the palette.
+fn convert_palette_data(
+    container: &mut JCompound,
+    palette_key: &'static str,
+    data_key: &'static str,
+    data: Option<Vec<u32>>,
+    typ: impl AbstractValueDataType + Copy,
+    from_version: DataVersion,
+    to_version: DataVersion,
+) -> Option<i32> {
+    let Some(JValue::Compound(palette)) = container.get_mut(palette_key) else {
+        return None;
+    };
+    let Ok(remap) = convert_palette(palette, typ, from_version, to_version) else {
+        unreachable!()
+    };
+    let palette_len = palette.len() as i32;
+
+    if let (Some(remap), Some(data)) = (remap, data) {
+        let data = data.into_iter().map(|index| remap[index as usize]);
+        container.insert(data_key, write_varints(data));
+    }
+    Some(palette_len)
+}
+
+/// Converts block entities or entities, whose data is either next to their `Id` and `Pos`, or in a `Data` compound.
+fn convert_objects(
+    container: &mut JCompound,
+    key: &str,
+    typ: impl AbstractMapDataType + Copy,
+    nested: bool,
+    from_version: DataVersion,
+    to_version: DataVersion,
+) {
+    let Some(JValue::List(JList::Compound(objects))) = container.get_mut(key) else {
+        return;
+    };
+    for object in objects {
+        let Some(mut id) = object.remove("Id") else {
+            continue;
+        };
+        if nested {
+            if let Some(JValue::Compound(data)) = object.get_mut("Data") {
+                convert_with_id(typ, data, &mut id, from_version, to_version);
+            }
+        } else {
+            // keep the position out of the way of the converters, block entities store theirs as x, y and z
+            let pos = object.remove("Pos");
+            convert_with_id(typ, object, &mut id, from_version, to_version);
+            if let Some(pos) = pos {
+                object.insert("Pos", pos);
+            }
+        }
+        object.insert("Id", id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use valence_nbt::{compound, jcompound};
+
+    #[test]
+    fn test_upgrade_v2() {
+        // grass was renamed to short_grass, merging the two palette entries
+        let mut schematic = jcompound! {
+            "Version" => 2,
+            "DataVersion" => 1631,
+            "Width" => 3i16,
+            "Height" => 1i16,
+            "Length" => 1i16,
+            "PaletteMax" => 3,
+            "Palette" => jcompound! {
+                "minecraft:air" => 0,
+                "minecraft:grass" => 1,
+                "minecraft:short_grass" => 2,
+            },
+            "BlockData" => write_varints([0, 1, 2]),
+            "BlockEntities" => JList::Compound(vec![jcompound! {
+                "Pos" => vec![0, 0, 0],
+                "Id" => "minecraft:sign",
+                "Text1" => r#"{"text":"hello"}"#,
+            }]),
+        };
+        upgrade_sponge_schematic(&mut schematic, 3700).unwrap();
+
+        assert_eq!(Some(&JValue::Int(3700)), schematic.get("DataVersion"));
+        assert_eq!(Some(&JValue::Int(2)), schematic.get("PaletteMax"));
+        let expected_palette = jcompound! {
+            "minecraft:air" => 0,
+            "minecraft:short_grass" => 1,
+        };
+        assert_eq!(
+            Some(&JValue::Compound(expected_palette)),
+            schematic.get("Palette")
+        );
+        assert_eq!(
+            Some(&JValue::ByteArray(write_varints([0, 1, 1]))),
+            schematic.get("BlockData")
+        );
+
+        let Some(JValue::List(JList::Compound(block_entities))) = schematic.get("BlockEntities")
+        else {
+            panic!("schematic lost its block entities");
+        };
+        assert_eq!(
+            Some(&JValue::from("minecraft:sign")),
+            block_entities[0].get("Id")
+        );
+        assert!(block_entities[0].contains_key("front_text"));
+        assert!(block_entities[0].contains_key("Pos"));
+        assert!(!block_entities[0].contains_key("id"));
+    }
+
+    #[test]
+    fn test_upgrade_v3() {
+        let mut root = jcompound! {
+            "Schematic" => jcompound! {
+                "Version" => 3,
+                "DataVersion" => 2230,
+                "Blocks" => jcompound! {
+                    "Palette" => jcompound! {
+                        "minecraft:stone" => 0,
+                    },
+                    "Data" => write_varints([0]),
+                },
+                "Entities" => JList::Compound(vec![jcompound! {
+                    "Pos" => JList::Double(vec![0.5, 0.0, 0.5]),
+                    "Id" => "minecraft:zombie_pigman",
+                    "Data" => jcompound! {
+                        "Health" => 20.0f32,
+                    },
+                }]),
+            },
+        };
+        upgrade_sponge_schematic(&mut root, 3700).unwrap();
+
+        let Some(JValue::Compound(schematic)) = root.get("Schematic") else {
+            panic!("root lost its schematic");
+        };
+        assert_eq!(Some(&JValue::Int(3700)), schematic.get("DataVersion"));
+        let Some(JValue::List(JList::Compound(entities))) = schematic.get("Entities") else {
+            panic!("schematic lost its entities");
+        };
+        assert_eq!(
+            Some(&JValue::from("minecraft:zombified_piglin")),
+            entities[0].get("Id")
+        );
+        assert!(entities[0].contains_key("Pos"));
+
+        assert_eq!(
+            Err(SchematicError::UnsupportedVersion(4)),
+            upgrade_sponge_schematic(&mut jcompound! { "Version" => 4, }, 3700)
+        );
+    }
+
+    #[test]
+    fn test_invalid_data_changes_nothing() {
+        // the blocks can be converted, but the biome data refers to a biome missing from the palette
+        let mut root = jcompound! {
+            "Schematic" => jcompound! {
+                "Version" => 3,
+                "DataVersion" => 1631,
+                "Blocks" => jcompound! {
+                    "Palette" => jcompound! {
+                        "minecraft:grass" => 0,
+                        "minecraft:short_grass" => 1,
+                    },
+                    "Data" => write_varints([0, 1]),
+                },
+                "Biomes" => jcompound! {
+                    "Palette" => jcompound! {
+                        "minecraft:plains" => 0,
+                    },
+                    "Data" => write_varints([0, 1]),
+                },
+            },
+        };
+        let original = root.clone();
+        assert_eq!(
+            Err(SchematicError::InvalidData("Data")),
+            upgrade_sponge_schematic(&mut root, 3700)
+        );
+        assert_eq!(original, root);
+
+        let mut schematic = jcompound! {
+            "Version" => 1,
+            "Palette" => jcompound! {
+                "minecraft:stone" => -1,
+            },
+            "BlockData" => write_varints([0]),
+            "TileEntities" => JList::Compound(Vec::new()),
+        };
+        let original = schematic.clone();
+        assert_eq!(
+            Err(SchematicError::InvalidData("Palette")),
+            upgrade_sponge_schematic(&mut schematic, 3700)
+        );
+        assert_eq!(original, schematic);
+
+        // the data refers to a gap in the palette
+        let mut schematic = jcompound! {
+            "Version" => 2,
+            "DataVersion" => 1631,
+            "Palette" => jcompound! {
+                "minecraft:stone" => 0,
+                "minecraft:grass" => 5,
+            },
+            "BlockData" => write_varints([0, 3]),
+        };
+        let original = schematic.clone();
+        assert_eq!(
+            Err(SchematicError::InvalidData("BlockData")),
+            upgrade_sponge_schematic(&mut schematic, 3700)
+        );
+        assert_eq!(original, schematic);
+
+        let mut schematic = jcompound! {
+            "Version" => 2,
+            "DataVersion" => -1,
+        };
+        assert_eq!(
+            Err(SchematicError::InvalidData("DataVersion")),
+            upgrade_sponge_schematic(&mut schematic, 3700)
+        );
+    }
+}
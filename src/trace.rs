@@ -0,0 +1,870 @@
+use crate::instrument;
+use crate::nbt_io::write_snbt;
+use crate::types::TypeKind;
+use java_string::JavaString;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::ptr::NonNull;
+use world_transmuter_engine::{
+    DataVersion, DynamicDataConverterFunc, DynamicDataHook, JCompound, JList, JValue, JValueMut,
+    JValueRef, MapDataConverterFunc, MapDataHook, ValueDataConverterFunc, ValueDataHook,
+};
+
+/// An object converted through the types by a converter, as it was before and after it was converted.
+struct Nested {
+    /// Where the object was when it was converted.
+    address: usize,
+    before: JValue,
+    after: JValue,
+}
+
+/// The objects converted through the types by a converter, in the order they were converted.
+type Converted = Vec<Nested>;
+
+thread_local! {
+    static TRACE: RefCell<Option<Vec<TraceEntry>>> = const { RefCell::new(None) };
+    /// For each converter or nested object being converted, from the outermost, the objects converted by the converter,
+    /// or `None` for a nested object.
+    static NESTED: RefCell<Vec<Option<Converted>>> = const { RefCell::new(Vec::new()) };
+    static RUNNING: Cell<Option<NonNull<ConverterInfo>>> = const { Cell::new(None) };
+}
+
+/// A change to a single value made by a converter. `before` is `None` for added values and `after` is `None` for
+/// removed values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NbtChange {
+    /// The path to the value, relative to the object being converted, e.g. `tag.pages[0]`.
+    pub path: String,
+    pub before: Option<JValue>,
+    pub after: Option<JValue>,
+}
+
+impl Display for NbtChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let path = if self.path.is_empty() {
+            "<root>"
+        } else {
+            &self.path
+        };
+        match (&self.before, &self.after) {
+            (None, Some(after)) => write!(f, "+ {path}: {}", write_snbt(after)),
+            (Some(before), None) => write!(f, "- {path}: {}", write_snbt(before)),
+            (Some(before), Some(after)) => write!(
+                f,
+                "~ {path}: {} -> {}",
+                write_snbt(before),
+                write_snbt(after)
+            ),
+            (None, None) => write!(f, "  {path}"),
+        }
+    }
+}
+
+/// Whether a trace entry was made by a converter or by a hook.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceSource {
+    Converter,
+    PreHook,
+    PostHook,
+}
+
+impl Display for TraceSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TraceSource::Converter => "converter",
+            TraceSource::PreHook => "pre hook",
+            TraceSource::PostHook => "post hook",
+        })
+    }
+}
+
+/// The changes made by one converter or hook.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceEntry {
+    /// The type of the object the converter was registered with.
+    pub kind: TypeKind,
+    pub source: TraceSource,
+    pub version: DataVersion,
+    /// The id the converter is specific to, if any.
+    pub id: Option<JavaString>,
+    pub changes: Vec<NbtChange>,
+}
+
+impl Display for TraceEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} {} ", self.kind, self.source)?;
+        write_converter(f, self.version, &self.id)?;
+        for change in &self.changes {
+            write!(f, "\n  {change}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs `f`, recording the changes made by every converter that runs on this thread while it runs. Like
+/// [`crate::diagnostics::collect`], tracing can be nested.
+pub fn collect<R>(f: impl FnOnce() -> R) -> (R, Vec<TraceEntry>) {
+    struct RestoreGuard(Option<Vec<TraceEntry>>);
+
+    impl Drop for RestoreGuard {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            TRACE.with_borrow_mut(|trace| *trace = previous);
+        }
+    }
+
+    let mut guard = RestoreGuard(TRACE.with_borrow_mut(|trace| trace.replace(Vec::new())));
+    let result = instrument::activate(f);
+    let entries = TRACE
+        .with_borrow_mut(|trace| trace.take())
+        .unwrap_or_default();
+    if let Some(previous) = &mut guard.0 {
+        previous.extend(entries.iter().cloned());
+    }
+    (result, entries)
+}
+
+/// Writes the name of a converter, such as `V1451.1` or `V99 for minecraft:chest`.
+pub(crate) fn write_converter(
+    f: &mut Formatter<'_>,
+    version: DataVersion,
+    id: &Option<JavaString>,
+) -> std::fmt::Result {
+    write!(f, "V{}", version.get_version())?;
+    if version.get_step() != 0 {
+        write!(f, ".{}", version.get_step())?;
+    }
+    if let Some(id) = id {
+        write!(f, " for {id}")?;
+    }
+    Ok(())
+}
+
+fn is_tracing() -> bool {
+    instrument::is_active() && TRACE.with_borrow(|trace| trace.is_some())
+}
+
+fn record(info: &ConverterInfo, source: TraceSource, changes: Vec<NbtChange>) {
+    if changes.is_empty() {
+        return;
+    }
+    TRACE.with_borrow_mut(|trace| {
+        if let Some(trace) = trace {
+            trace.push(TraceEntry {
+                kind: info.kind,
+                source,
+                version: info.version,
+                id: info.id.clone(),
+                changes,
+            });
+        }
+    });
+}
+
+struct PopGuard;
+
+impl Drop for PopGuard {
+    fn drop(&mut self) {
+        NESTED.with_borrow_mut(|nested| nested.pop());
+    }
+}
+
+/// Runs the converter `f`, returning the objects it converted through the types while it ran.
+fn run_converter(f: impl FnOnce()) -> Converted {
+    NESTED.with_borrow_mut(|nested| nested.push(Some(Vec::new())));
+    let _guard = PopGuard;
+    f();
+    NESTED
+        .with_borrow_mut(|nested| nested.last_mut().and_then(Option::take))
+        .unwrap_or_default()
+}
+
+/// An object that can be converted by a traced converter, or through the types by one.
+pub(crate) trait TracedData {
+    fn to_value(&self) -> JValue;
+
+    /// Where the object is, which identifies it among the objects it contains and is contained in.
+    fn address(&self) -> usize;
+
+    /// Finds the objects contained in this one, including itself, which were converted through the types.
+    fn walk(&self, walker: &mut Walker);
+}
+
+impl TracedData for JCompound {
+    fn to_value(&self) -> JValue {
+        JValue::Compound(self.clone())
+    }
+
+    fn address(&self) -> usize {
+        address_of(self)
+    }
+
+    fn walk(&self, walker: &mut Walker) {
+        walker.visit(self.address());
+        walker.compound(self);
+    }
+}
+
+impl TracedData for JValue {
+    fn to_value(&self) -> JValue {
+        self.clone()
+    }
+
+    fn address(&self) -> usize {
+        address_of(self)
+    }
+
+    fn walk(&self, walker: &mut Walker) {
+        walker.value(self);
+    }
+}
+
+impl TracedData for JValueMut<'_> {
+    fn to_value(&self) -> JValue {
+        JValueMut::to_value(self)
+    }
+
+    /// The address of the value this refers to, so that it is the same as that of the compound or list element
+    /// it refers to.
+    fn address(&self) -> usize {
+        macro_rules! address {
+            ($($variant:ident),*) => {
+                match self {
+                    $(JValueMut::$variant(value) => address_of(&**value),)*
+                }
+            };
+        }
+        address!(
+            Byte, Short, Int, Long, Float, Double, ByteArray, String, List, Compound, IntArray,
+            LongArray
+        )
+    }
+
+    fn walk(&self, walker: &mut Walker) {
+        walker.visit(self.address());
+        match self {
+            JValueMut::Compound(compound) => walker.compound(compound),
+            JValueMut::List(list) => walker.list(list),
+            _ => {}
+        }
+    }
+}
+
+fn address_of<T: ?Sized>(value: &T) -> usize {
+    value as *const T as *const () as usize
+}
+
+/// The address of the value inside `value`, which is what a [`JValueMut`] of it refers to.
+fn payload_address(value: &JValue) -> usize {
+    macro_rules! address {
+        ($($variant:ident),*) => {
+            match value {
+                $(JValue::$variant(value) => address_of(value),)*
+            }
+        };
+    }
+    address!(
+        Byte, Short, Int, Long, Float, Double, ByteArray, String, List, Compound, IntArray,
+        LongArray
+    )
+}
+
+#[derive(Clone, Debug)]
+enum PathSegment {
+    Key(JavaString),
+    Index(usize),
+}
+
+/// Finds the paths to the objects converted by a converter, by where they are.
+pub(crate) struct Walker {
+    /// The index in the converted objects of each of their addresses.
+    indexes: HashMap<usize, usize>,
+    path: Vec<PathSegment>,
+    found: Vec<Option<Vec<PathSegment>>>,
+}
+
+impl Walker {
+    fn visit(&mut self, address: usize) {
+        if let Some(&index) = self.indexes.get(&address) {
+            self.found[index].get_or_insert_with(|| self.path.clone());
+        }
+    }
+
+    fn value(&mut self, value: &JValue) {
+        self.visit(address_of(value));
+        self.visit(payload_address(value));
+        match value {
+            JValue::Compound(compound) => self.compound(compound),
+            JValue::List(list) => self.list(list),
+            _ => {}
+        }
+    }
+
+    fn compound(&mut self, compound: &JCompound) {
+        for (key, value) in compound {
+            self.path.push(PathSegment::Key(key.clone()));
+            self.value(value);
+            self.path.pop();
+        }
+    }
+
+    fn list(&mut self, list: &JList) {
+        macro_rules! visit_elements {
+            ($($variant:ident),*) => {
+                match list {
+                    $(
+                    JList::$variant(elements) => {
+                        for (index, element) in elements.iter().enumerate() {
+                            self.path.push(PathSegment::Index(index));
+                            self.visit(address_of(element));
+                            self.path.pop();
+                        }
+                    }
+                    )*
+                    JList::End => {}
+                }
+            };
+        }
+        visit_elements!(
+            Byte, Short, Int, Long, Float, Double, ByteArray, String, List, Compound, IntArray,
+            LongArray
+        );
+        match list {
+            JList::List(lists) => {
+                for (index, list) in lists.iter().enumerate() {
+                    self.path.push(PathSegment::Index(index));
+                    self.list(list);
+                    self.path.pop();
+                }
+            }
+            JList::Compound(compounds) => {
+                for (index, compound) in compounds.iter().enumerate() {
+                    self.path.push(PathSegment::Index(index));
+                    self.compound(compound);
+                    self.path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Runs `f`, which converts `data`, an object of a type. If a traced converter is converting `data` as part of its own
+/// data, the changes made by `f` are left out of that converter's trace entry, as they are traced by the converters of
+/// the object's type.
+pub(crate) fn convert_nested<T: TracedData + ?Sized>(data: &mut T, f: impl FnOnce(&mut T)) {
+    if !NESTED.with_borrow(|nested| matches!(nested.last(), Some(Some(_)))) {
+        return f(data);
+    }
+    let before = data.to_value();
+    NESTED.with_borrow_mut(|nested| nested.push(None));
+    {
+        let _guard = PopGuard;
+        f(data);
+    }
+    let mut object = Nested {
+        address: data.address(),
+        before,
+        after: data.to_value(),
+    };
+    NESTED.with_borrow_mut(|nested| {
+        let Some(Some(converted)) = nested.last_mut() else {
+            return;
+        };
+        // an object converted again without changes in between keeps its first state, anything else at the same
+        // address is a different object
+        if let Some(index) = converted
+            .iter()
+            .position(|other| other.address == object.address)
+        {
+            let other = converted.remove(index);
+            if other.after == object.before {
+                object.before = other.before;
+            }
+        }
+        converted.push(object);
+    });
+}
+
+/// Puts back the objects in `after`, a copy of `data`, that a converter converted through the types as they were before
+/// they were converted, so that only the converter's own changes are left. The objects are found where they were
+/// converted, and only the parts the converter hasn't changed since are put back, so an object that was moved or that
+/// is equal to another one is never mistaken for it.
+fn undo_nested<T: TracedData + ?Sized>(data: &T, after: &mut JValue, converted: Converted) {
+    if converted.is_empty() {
+        return;
+    }
+    let mut walker = Walker {
+        indexes: converted
+            .iter()
+            .enumerate()
+            .map(|(index, object)| (object.address, index))
+            .collect(),
+        path: Vec::new(),
+        found: vec![None; converted.len()],
+    };
+    data.walk(&mut walker);
+    for (object, path) in converted.into_iter().zip(walker.found).rev() {
+        if let Some(path) = path {
+            undo_at(after, &path, object, true);
+        }
+    }
+}
+
+/// Puts back the parts of `value` that are the same as `after` as they were in `before`, leaving the parts the
+/// converter changed since.
+fn revert(value: &mut JValue, before: JValue, after: &JValue, keep_tag: bool) {
+    if value == after {
+        if !keep_tag || value.tag() == before.tag() {
+            *value = before;
+        }
+        return;
+    }
+    let (JValue::Compound(value), JValue::Compound(before), JValue::Compound(after)) =
+        (value, before, after)
+    else {
+        return;
+    };
+    for (key, after_value) in after {
+        if !before.contains_key(key.as_java_str())
+            && value.get(key.as_java_str()) == Some(after_value)
+        {
+            value.remove(key.as_java_str());
+        }
+    }
+    for (key, before_value) in before {
+        match (
+            value.get_mut(key.as_java_str()),
+            after.get(key.as_java_str()),
+        ) {
+            (Some(value), Some(after_value)) => revert(value, before_value, after_value, false),
+            (None, None) => {
+                value.insert(key, before_value);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn undo_at(value: &mut JValue, path: &[PathSegment], object: Nested, keep_tag: bool) {
+    let Some((segment, path)) = path.split_first() else {
+        return revert(value, object.before, &object.after, keep_tag);
+    };
+    match (segment, value) {
+        (PathSegment::Key(key), JValue::Compound(compound)) => {
+            if let Some(value) = compound.get_mut(key.as_java_str()) {
+                undo_at(value, path, object, false);
+            }
+        }
+        (PathSegment::Index(index), JValue::List(list)) => {
+            macro_rules! undo_in_list {
+                ($($variant:ident),*) => {
+                    match list {
+                        $(
+                        JList::$variant(elements) => {
+                            let Some(element) = elements.get_mut(*index) else {
+                                return;
+                            };
+                            let mut value = JValue::$variant(std::mem::take(element));
+                            undo_at(&mut value, path, object, true);
+                            let JValue::$variant(value) = value else {
+                                unreachable!()
+                            };
+                            *element = value;
+                        }
+                        )*
+                        JList::End => {}
+                    }
+                };
+            }
+            undo_in_list!(
+                Byte, Short, Int, Long, Float, Double, ByteArray, String, List, Compound, IntArray,
+                LongArray
+            )
+        }
+        _ => {}
+    }
+}
+
+/// Returns the changes between two compounds.
+pub fn diff(before: &JCompound, after: &JCompound) -> Vec<NbtChange> {
+    let mut changes = Vec::new();
+    diff_compounds(&mut String::new(), before, after, &mut changes);
+    changes
+}
+
+fn diff_values(
+    path: &mut String,
+    before: JValueRef,
+    after: JValueRef,
+    changes: &mut Vec<NbtChange>,
+) {
+    match (before, after) {
+        (JValueRef::Compound(before), JValueRef::Compound(after)) => {
+            diff_compounds(path, before, after, changes)
+        }
+        (JValueRef::List(before), JValueRef::List(after))
+            if before.len() == after.len() && before.element_tag() == after.element_tag() =>
+        {
+            for (index, (before, after)) in before.iter().zip(after.iter()).enumerate() {
+                let len = path.len();
+                path.push_str(&format!("[{index}]"));
+                diff_values(path, before, after, changes);
+                path.truncate(len);
+            }
+        }
+        (before, after) => {
+            if before != after {
+                changes.push(NbtChange {
+                    path: path.clone(),
+                    before: Some(before.to_value()),
+                    after: Some(after.to_value()),
+                });
+            }
+        }
+    }
+}
+
+fn diff_compounds(
+    path: &mut String,
+    before: &JCompound,
+    after: &JCompound,
+    changes: &mut Vec<NbtChange>,
+) {
+    let len = path.len();
+    let mut push_key = |path: &mut String, key: &JavaString| {
+        path.truncate(len);
+        if len != 0 {
+            path.push('.');
+        }
+        path.push_str(&key.to_string());
+    };
+    for (key, before_value) in before {
+        push_key(path, key);
+        match after.get(key.as_java_str()) {
+            Some(after_value) => {
+                diff_values(path, before_value.into(), after_value.into(), changes)
+            }
+            None => changes.push(NbtChange {
+                path: path.clone(),
+                before: Some(before_value.clone()),
+                after: None,
+            }),
+        }
+    }
+    for (key, after_value) in after {
+        if !before.contains_key(key.as_java_str()) {
+            push_key(path, key);
+            changes.push(NbtChange {
+                path: path.clone(),
+                before: None,
+                after: Some(after_value.clone()),
+            });
+        }
+    }
+    path.truncate(len);
+}
+
+/// What a converter was registered as.
+pub(crate) struct ConverterInfo {
+    pub(crate) kind: TypeKind,
+    pub(crate) version: DataVersion,
+    /// The id the converter is specific to, if any.
+    pub(crate) id: Option<JavaString>,
+}
+
+impl ConverterInfo {
+    /// Runs `f`, the converter this describes, so that [`running_converter`] returns it while it runs.
+    fn run<R>(&self, f: impl FnOnce() -> R) -> R {
+        struct RestoreGuard(Option<NonNull<ConverterInfo>>);
+
+        impl Drop for RestoreGuard {
+            fn drop(&mut self) {
+                RUNNING.set(self.0);
+            }
+        }
+
+        let _guard = RestoreGuard(RUNNING.replace(Some(NonNull::from(self))));
+        f()
+    }
+}
+
+/// Calls `f` with the innermost converter running on this thread, if any.
+pub(crate) fn running_converter<R>(f: impl FnOnce(Option<&ConverterInfo>) -> R) -> R {
+    // SAFETY: the pointer is set by ConverterInfo::run, which borrows the info for as long as it is set.
+    f(RUNNING.get().map(|info| unsafe { info.as_ref() }))
+}
+
+/// Wraps a converter or hook, making it the [running converter](running_converter) while it runs, so that diagnostics
+/// know which converter reported them, and recording the changes it makes while tracing.
+pub(crate) struct Traced<F> {
+    info: ConverterInfo,
+    func: F,
+}
+
+impl<F> Traced<F> {
+    pub(crate) fn new(
+        kind: TypeKind,
+        version: DataVersion,
+        id: Option<JavaString>,
+        func: F,
+    ) -> Self {
+        Self {
+            info: ConverterInfo { kind, version, id },
+            func,
+        }
+    }
+
+    fn run<T: TracedData + ?Sized>(
+        &self,
+        source: TraceSource,
+        data: &mut T,
+        f: impl FnOnce(&mut T),
+    ) {
+        self.info.run(|| {
+            if !is_tracing() {
+                return f(data);
+            }
+            let before = data.to_value();
+            let converted = run_converter(|| f(data));
+            let mut after = data.to_value();
+            undo_nested(data, &mut after, converted);
+            let mut changes = Vec::new();
+            diff_values(
+                &mut String::new(),
+                before.as_value_ref(),
+                after.as_value_ref(),
+                &mut changes,
+            );
+            record(&self.info, source, changes);
+        })
+    }
+}
+
+impl<F: MapDataConverterFunc> MapDataConverterFunc for Traced<F> {
+    fn convert(&self, data: &mut JCompound, from_version: DataVersion, to_version: DataVersion) {
+        self.run(TraceSource::Converter, data, |data| {
+            self.func.convert(data, from_version, to_version)
+        })
+    }
+}
+
+impl<F: ValueDataConverterFunc> ValueDataConverterFunc for Traced<F> {
+    fn convert(&self, data: &mut JValueMut, from_version: DataVersion, to_version: DataVersion) {
+        self.run(TraceSource::Converter, data, |data| {
+            self.func.convert(data, from_version, to_version)
+        })
+    }
+}
+
+impl<F: DynamicDataConverterFunc> DynamicDataConverterFunc for Traced<F> {
+    fn convert(&self, data: &mut JValue, from_version: DataVersion, to_version: DataVersion) {
+        self.run(TraceSource::Converter, data, |data| {
+            self.func.convert(data, from_version, to_version)
+        })
+    }
+}
+
+impl<F: MapDataHook> MapDataHook for Traced<F> {
+    fn pre_hook(&self, data: &mut JCompound, from_version: DataVersion, to_version: DataVersion) {
+        self.run(TraceSource::PreHook, data, |data| {
+            self.func.pre_hook(data, from_version, to_version)
+        })
+    }
+
+    fn post_hook(&self, data: &mut JCompound, from_version: DataVersion, to_version: DataVersion) {
+        self.run(TraceSource::PostHook, data, |data| {
+            self.func.post_hook(data, from_version, to_version)
+        })
+    }
+}
+
+impl<F: ValueDataHook> ValueDataHook for Traced<F> {
+    fn pre_hook(&self, data: &mut JValueMut, from_version: DataVersion, to_version: DataVersion) {
+        self.run(TraceSource::PreHook, data, |data| {
+            self.func.pre_hook(data, from_version, to_version)
+        })
+    }
+
+    fn post_hook(&self, data: &mut JValueMut, from_version: DataVersion, to_version: DataVersion) {
+        self.run(TraceSource::PostHook, data, |data| {
+            self.func.post_hook(data, from_version, to_version)
+        })
+    }
+}
+
+impl<F: DynamicDataHook> DynamicDataHook for Traced<F> {
+    fn pre_hook(&self, data: &mut JValue, from_version: DataVersion, to_version: DataVersion) {
+        self.run(TraceSource::PreHook, data, |data| {
+            self.func.pre_hook(data, from_version, to_version)
+        })
+    }
+
+    fn post_hook(&self, data: &mut JValue, from_version: DataVersion, to_version: DataVersion) {
+        self.run(TraceSource::PostHook, data, |data| {
+            self.func.post_hook(data, from_version, to_version)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MinecraftTypes;
+    use crate::{convert_map_traced, types};
+    use java_string::JavaStr;
+    use valence_nbt::{compound, jcompound};
+    use world_transmuter_engine::{
+        convert_map_in_map, map_data_converter_func, rename_key, AbstractMapDataType, JList,
+    };
+
+    #[test]
+    fn test_trace_item_components() {
+        let mut item = jcompound! {
+            "id" => "minecraft:stone",
+            "Count" => 2i8,
+            "tag" => jcompound! {
+                "display" => jcompound! {
+                    "Name" => r#"{"text":"Rock"}"#,
+                },
+            },
+        };
+        let trace = convert_map_traced(types::item_stack_ref(), &mut item, 3700, 3837);
+        let entry = trace
+            .iter()
+            .find(|entry| entry.kind == TypeKind::ItemStack && entry.version.get_version() == 3818)
+            .unwrap();
+        assert!(entry.changes.iter().any(|change| change.path == "Count"
+            && change.before == Some(JValue::Byte(2))
+            && change.after.is_none()));
+        assert!(entry
+            .changes
+            .iter()
+            .any(|change| change.path == "components" && change.before.is_none()));
+    }
+
+    #[test]
+    fn test_trace_nested_conversion() {
+        // a converter which converts an item itself shouldn't have the changes to the item in its entry
+        let types = MinecraftTypes::builder()
+            .extension(|| {
+                types::entity_mut().add_converter_for_id(
+                    "mymod:mob",
+                    3818,
+                    map_data_converter_func(|data, from_version, to_version| {
+                        rename_key(data, "Item", "item");
+                        convert_map_in_map(
+                            types::item_stack_ref(),
+                            data,
+                            "item",
+                            from_version,
+                            to_version,
+                        );
+                    }),
+                );
+            })
+            .build();
+        let item = jcompound! {
+            "id" => "minecraft:stone",
+            "Count" => 2i8,
+        };
+        let mut mob = jcompound! {
+            "id" => "mymod:mob",
+            "Item" => item.clone(),
+        };
+        let trace = types.enter(|| convert_map_traced(types::entity_ref(), &mut mob, 3700, 3837));
+
+        let entry = trace
+            .iter()
+            .find(|entry| entry.id.as_deref() == Some(JavaStr::from_str("mymod:mob")))
+            .unwrap();
+        assert_eq!(
+            vec![
+                NbtChange {
+                    path: "Item".to_owned(),
+                    before: Some(JValue::Compound(item.clone())),
+                    after: None,
+                },
+                NbtChange {
+                    path: "item".to_owned(),
+                    before: None,
+                    after: Some(JValue::Compound(item)),
+                },
+            ],
+            entry.changes
+        );
+        assert!(trace.iter().any(|entry| entry.kind == TypeKind::ItemStack
+            && entry.changes.iter().any(|change| change.path == "Count")));
+    }
+
+    #[test]
+    fn test_trace_identical_nested_objects() {
+        // only the second item is converted by the converter, the first one is already in the new format
+        let types = MinecraftTypes::builder()
+            .extension(|| {
+                types::entity_mut().add_converter_for_id(
+                    "mymod:mob",
+                    3818,
+                    map_data_converter_func(|data, from_version, to_version| {
+                        let Some(JValue::List(JList::Compound(items))) = data.get_mut("Items")
+                        else {
+                            return;
+                        };
+                        types::item_stack_ref().convert(&mut items[1], from_version, to_version);
+                        items[1].insert("Slot", 1i8);
+                    }),
+                );
+            })
+            .build();
+        let item = jcompound! {
+            "id" => "minecraft:stone",
+            "Count" => 2i8,
+        };
+        let mut converted_item = item.clone();
+        types.enter(|| {
+            types::item_stack_ref().convert(&mut converted_item, 3700.into(), 3837.into())
+        });
+        let mut mob = jcompound! {
+            "id" => "mymod:mob",
+            "Items" => JList::Compound(vec![converted_item, item]),
+        };
+        let trace = types.enter(|| convert_map_traced(types::entity_ref(), &mut mob, 3700, 3837));
+
+        let entry = trace
+            .iter()
+            .find(|entry| entry.id.as_deref() == Some(JavaStr::from_str("mymod:mob")))
+            .unwrap();
+        assert_eq!(
+            vec![NbtChange {
+                path: "Items[1].Slot".to_owned(),
+                before: None,
+                after: Some(JValue::Byte(1)),
+            }],
+            entry.changes
+        );
+    }
+
+    #[test]
+    fn test_trace_hook() {
+        let mut item = jcompound! {
+            "id" => "stone",
+            "Count" => 2i8,
+        };
+        let trace = convert_map_traced(types::item_stack_ref(), &mut item, 3700, 3837);
+        let entry = trace
+            .iter()
+            .find(|entry| entry.source == TraceSource::PreHook)
+            .unwrap();
+        assert_eq!(TypeKind::ItemStack, entry.kind);
+        assert_eq!(99, entry.version.get_version());
+        assert_eq!(
+            vec![NbtChange {
+                path: "id".to_owned(),
+                before: Some(JValue::String("stone".into())),
+                after: Some(JValue::String("minecraft:stone".into())),
+            }],
+            entry.changes
+        );
+    }
+}
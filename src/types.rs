@@ -1,126 +1,627 @@
-use std::cell::Cell;
-use std::mem::MaybeUninit;
-use std::sync::{Once, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
-use world_transmuter_engine::{DynamicDataType, IdDataType, MapDataType, ObjectDataType};
-
-fn types() -> &'static MinecraftTypes {
-    static mut TYPES: MaybeUninit<MinecraftTypes> = MaybeUninit::uninit();
-    thread_local! {
-        static IS_INITIALIZING_ON_THIS_THREAD: Cell<bool> = const { Cell::new(false) };
-    }
-    static TYPES_INITIALIZER: Once = Once::new();
-    if !IS_INITIALIZING_ON_THIS_THREAD.get() {
-        TYPES_INITIALIZER.call_once(|| {
-            IS_INITIALIZING_ON_THIS_THREAD.set(true);
-
-            unsafe {
-                // SAFETY: we are inside a call_once, so not possible for this line to be reached
-                // multiple times concurrently. Also, since call_once is blocking, this line
-                // must be reached before the only read of this value, which is below.
-                TYPES = MaybeUninit::new(MinecraftTypes::create_empty());
-            }
+use crate::diagnostics;
+use crate::instrument;
+use crate::introspect::{Registration, RegistrationKind};
+use crate::legacy::LegacyTables;
+use crate::trace;
+use crate::trace::Traced;
+use crate::visitor;
+use crate::visitor::VisitedMut;
+use java_string::{JavaStr, JavaString};
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::ops::Deref;
+use std::sync::{
+    Arc, Mutex, OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError, Weak,
+};
+use world_transmuter_engine::{
+    AbstractDynamicDataType, AbstractMapDataType, AbstractValueDataType, DataVersion,
+    DynamicDataConverterFunc, DynamicDataHook, DynamicDataType, DynamicDataWalker, IdDataType,
+    JCompound, JValue, JValueMut, MapDataConverterFunc, MapDataHook, MapDataType, MapDataWalker,
+    ObjectDataType, ValueDataConverterFunc, ValueDataHook,
+};
 
-            // General notes:
-            // - Structure converters run before everything.
-            // - ID specific converters run after structure converters.
-            // - Structure walkers run after id specific converters.
-            // - ID specific walkers run after structure walkers.
-            crate::versions::register_versions();
+thread_local! {
+    static CURRENT_TYPES: RefCell<Option<Arc<MinecraftTypes>>> = const { RefCell::new(None) };
+}
+
+type Extension = Box<dyn FnOnce() + Send>;
+
+struct Extensions {
+    pending: Vec<Extension>,
+    closed: bool,
+}
+
+static EXTENSIONS: Mutex<Extensions> = Mutex::new(Extensions {
+    pending: Vec::new(),
+    closed: false,
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypesInitializedError;
+
+impl Display for TypesInitializedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Extensions must be registered before the Minecraft types are first used"
+        )
+    }
+}
+
+impl std::error::Error for TypesInitializedError {}
+
+/// Registers an extension with the [default registry](default_types), which adds converters, walkers or hooks to the
+/// Minecraft types, for example for modded entities or block entities. Extensions run once, right after the vanilla
+/// versions have been registered, when the types are first used. Inside the extension, the `*_mut` functions of this
+/// module can be used freely, as well as the helpers in [`crate::rename`].
+///
+/// Returns an error if the default registry has already been initialized.
+pub fn register_extension(
+    extension: impl FnOnce() + Send + 'static,
+) -> Result<(), TypesInitializedError> {
+    let mut extensions = EXTENSIONS.lock().unwrap_or_else(|err| err.into_inner());
+    if extensions.closed {
+        return Err(TypesInitializedError);
+    }
+    extensions.pending.push(Box::new(extension));
+    Ok(())
+}
+
+fn run_extensions() {
+    let pending = {
+        let mut extensions = EXTENSIONS.lock().unwrap_or_else(|err| err.into_inner());
+        extensions.closed = true;
+        std::mem::take(&mut extensions.pending)
+    };
+    for extension in pending {
+        extension();
+    }
+}
+
+/// The types used by the global functions of this module: the registry currently being built or converted with on this
+/// thread, or the default registry otherwise.
+fn types() -> Arc<MinecraftTypes> {
+    CURRENT_TYPES
+        .with_borrow(|types| types.clone())
+        .unwrap_or_else(default_types)
+}
+
+/// Calls `f` with the types used by the global functions of this module, like [`types`], without cloning the `Arc`.
+pub(crate) fn with_types<R>(f: impl FnOnce(&MinecraftTypes) -> R) -> R {
+    match CURRENT_TYPES.with_borrow(|types| types.as_ref().map(Arc::as_ptr)) {
+        // SAFETY: the current registry is kept alive by the `enter` call that made it current, which can't return
+        // before `f` does. A nested `enter` keeps it alive too, until it makes it current again.
+        Some(types) => f(unsafe { &*types }),
+        None => f(default_types_ref()),
+    }
+}
+
+/// The default registry, containing the vanilla versions and any extensions registered with [`register_extension`].
+pub fn default_types() -> Arc<MinecraftTypes> {
+    default_types_ref().clone()
+}
+
+fn default_types_ref() -> &'static Arc<MinecraftTypes> {
+    static DEFAULT_TYPES: OnceLock<Arc<MinecraftTypes>> = OnceLock::new();
+    DEFAULT_TYPES.get_or_init(|| MinecraftTypes::build(vec![Box::new(run_extensions)]))
+}
+
+/// A lock around a data type, which knows which registry it belongs to. Converting with it makes its registry current
+/// for the duration of the conversion, so that nested conversions use the same registry. Conversions through a
+/// `TypeLock` are also reported to the active [visitor](crate::visitor), if any.
+pub struct TypeLock<T> {
+    kind: TypeKind,
+    types: Weak<MinecraftTypes>,
+    lock: RwLock<T>,
+    registrations: Mutex<Vec<Registration>>,
+}
+
+impl<T> TypeLock<T> {
+    fn new(kind: TypeKind, value: T, types: Weak<MinecraftTypes>) -> Self {
+        Self {
+            kind,
+            types,
+            lock: RwLock::new(value),
+            registrations: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn kind(&self) -> TypeKind {
+        self.kind
+    }
+
+    pub fn types(&self) -> Arc<MinecraftTypes> {
+        self.types
+            .upgrade()
+            .expect("the registry of a TypeLock should be alive while it is borrowed")
+    }
 
-            IS_INITIALIZING_ON_THIS_THREAD.set(false);
+    /// Runs `f` with the registry of this type current, unless it already is, which is the case for all but the
+    /// top-level conversion.
+    fn enter<R>(&self, f: impl FnOnce() -> R) -> R {
+        let is_current = CURRENT_TYPES.with_borrow(|types| {
+            types
+                .as_ref()
+                .is_some_and(|types| Arc::as_ptr(types) == self.types.as_ptr())
         });
+        if is_current {
+            f()
+        } else {
+            self.types().enter(f)
+        }
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.lock.read().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn write(&self) -> TypeWriteGuard<'_, T> {
+        TypeWriteGuard {
+            kind: self.kind,
+            guard: self.lock.write().unwrap_or_else(|err| panic!("{}", err)),
+            registrations: &self.registrations,
+            _types: None,
+        }
+    }
+
+    /// Returns the converters, walkers and hooks that have been registered with this type, in the order they were
+    /// registered.
+    pub fn registrations(&self) -> Vec<Registration> {
+        self.registrations
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+}
+
+/// Read access to a data type of the current registry, as returned by the global functions of this module.
+pub struct TypeReadGuard<T: 'static> {
+    guard: RwLockReadGuard<'static, T>,
+    /// Keeps the registry alive. Fields are dropped in declaration order, so this is dropped after the guard.
+    _types: Arc<MinecraftTypes>,
+}
+
+impl<T> Deref for TypeReadGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+/// Write access to a data type, which records what is registered with it, so that it can be listed by
+/// [`crate::introspect`]. Registries are shared between threads, so everything registered must be `Send` and `Sync`.
+pub struct TypeWriteGuard<'a, T> {
+    kind: TypeKind,
+    guard: RwLockWriteGuard<'a, T>,
+    registrations: &'a Mutex<Vec<Registration>>,
+    /// Keeps the registry alive for guards returned by the global functions of this module. Fields are dropped in
+    /// declaration order, so this is dropped after the borrows above.
+    _types: Option<Arc<MinecraftTypes>>,
+}
+
+impl<T> TypeWriteGuard<'_, T> {
+    fn record(&self, kind: RegistrationKind, version: DataVersion, id: Option<JavaString>) {
+        self.registrations
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(Registration { version, kind, id });
     }
-    unsafe {
-        // SAFETY: this line cannot be reached without the initialization above. See the safety comment there for details.
-        // the warning is ok here because the static is local and therefore cannot be accessed mutably from elsewhere, or this function (see safety comment above)
-        #[allow(static_mut_refs)]
-        TYPES.assume_init_ref()
+}
+
+impl<T> Deref for TypeWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+macro_rules! recorded_registrations {
+    ($type:ident { converter: $converter:path, hook: $hook:path $(, walker: $walker:path)? $(,)? }) => {
+        impl TypeWriteGuard<'_, $type<'static>> {
+            pub fn add_structure_converter(
+                &mut self,
+                version: impl Into<DataVersion>,
+                func: impl $converter + Send + Sync + 'static,
+            ) {
+                let version = version.into();
+                self.record(RegistrationKind::Converter, version, None);
+                self.guard
+                    .add_structure_converter(version, Traced::new(self.kind, version, None, func));
+            }
+
+            pub fn add_structure_hook(
+                &mut self,
+                version: impl Into<DataVersion>,
+                hook: impl $hook + Send + Sync + 'static,
+            ) {
+                let version = version.into();
+                self.record(RegistrationKind::Hook, version, None);
+                self.guard
+                    .add_structure_hook(version, Traced::new(self.kind, version, None, hook));
+            }
+
+            $(
+            pub fn add_structure_walker(
+                &mut self,
+                version: impl Into<DataVersion>,
+                walker: impl $walker + Send + Sync + 'static,
+            ) {
+                let version = version.into();
+                self.record(RegistrationKind::Walker, version, None);
+                self.guard.add_structure_walker(version, walker);
+            }
+            )?
+        }
+    };
+}
+
+recorded_registrations!(MapDataType {
+    converter: MapDataConverterFunc,
+    hook: MapDataHook,
+    walker: MapDataWalker,
+});
+recorded_registrations!(IdDataType {
+    converter: MapDataConverterFunc,
+    hook: MapDataHook,
+    walker: MapDataWalker,
+});
+recorded_registrations!(ObjectDataType {
+    converter: ValueDataConverterFunc,
+    hook: ValueDataHook,
+});
+recorded_registrations!(DynamicDataType {
+    converter: DynamicDataConverterFunc,
+    hook: DynamicDataHook,
+    walker: DynamicDataWalker,
+});
+
+impl TypeWriteGuard<'_, IdDataType<'static>> {
+    pub fn add_converter_for_id(
+        &mut self,
+        id: impl Into<JavaString>,
+        version: impl Into<DataVersion>,
+        converter_func: impl MapDataConverterFunc + Send + Sync + 'static,
+    ) {
+        let id = id.into();
+        let version = version.into();
+        self.record(RegistrationKind::Converter, version, Some(id.clone()));
+        let converter_func = Traced::new(self.kind, version, Some(id.clone()), converter_func);
+        self.guard.add_converter_for_id(id, version, converter_func);
+    }
+
+    pub fn add_walker_for_id(
+        &mut self,
+        version: impl Into<DataVersion>,
+        id: impl Into<JavaString>,
+        walker: impl MapDataWalker + Send + Sync + 'static,
+    ) {
+        let id = id.into();
+        let version = version.into();
+        self.record(RegistrationKind::Walker, version, Some(id.clone()));
+        self.guard.add_walker_for_id(version, id, walker);
+    }
+
+    pub fn copy_walkers(
+        &mut self,
+        version: impl Into<DataVersion> + Clone,
+        from_id: impl AsRef<JavaStr>,
+        to_id: impl Into<JavaString> + Clone,
+    ) {
+        let has_walkers = self
+            .registrations
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .iter()
+            .any(|registration| {
+                registration.kind == RegistrationKind::Walker
+                    && registration.id.as_deref() == Some(from_id.as_ref())
+                    && registration.version <= version.clone().into()
+            });
+        if has_walkers {
+            self.record(
+                RegistrationKind::Walker,
+                version.clone().into(),
+                Some(to_id.clone().into()),
+            );
+        }
+        self.guard.copy_walkers(version, from_id, to_id);
+    }
+}
+
+impl<T: AbstractMapDataType> AbstractMapDataType for TypeLock<T> {
+    fn convert(&self, data: &mut JCompound, from_version: DataVersion, to_version: DataVersion) {
+        if !instrument::is_active() {
+            return self.enter(|| self.read().convert(data, from_version, to_version));
+        }
+        visitor::notify(self.kind, VisitedMut::Map(data));
+        diagnostics::in_object(self.kind, || {
+            trace::convert_nested(data, |data| {
+                self.enter(|| self.read().convert(data, from_version, to_version))
+            })
+        })
+    }
+}
+
+impl<T: AbstractValueDataType> AbstractValueDataType for TypeLock<T> {
+    fn convert(&self, data: &mut JValueMut, from_version: DataVersion, to_version: DataVersion) {
+        if !instrument::is_active() {
+            return self.enter(|| self.read().convert(data, from_version, to_version));
+        }
+        visitor::notify(self.kind, VisitedMut::Value(data));
+        diagnostics::in_object(self.kind, || {
+            trace::convert_nested(data, |data| {
+                self.enter(|| self.read().convert(data, from_version, to_version))
+            })
+        })
+    }
+}
+
+impl<T: AbstractDynamicDataType> AbstractDynamicDataType for TypeLock<T> {
+    fn convert(&self, data: &mut JValue, from_version: DataVersion, to_version: DataVersion) {
+        if !instrument::is_active() {
+            return self.enter(|| self.read().convert(data, from_version, to_version));
+        }
+        visitor::notify(self.kind, VisitedMut::Dynamic(data));
+        diagnostics::in_object(self.kind, || {
+            trace::convert_nested(data, |data| {
+                self.enter(|| self.read().convert(data, from_version, to_version))
+            })
+        })
+    }
+}
+
+/// A handle to one of the types of the registry that is current on this thread, or the default registry otherwise,
+/// as returned by the global `*_ref` functions of this module. Unlike a [`TypeLock`], it doesn't belong to a registry,
+/// so walkers and converters can keep it and still convert with the registry they are run by.
+pub struct TypeRef<T: 'static> {
+    kind: TypeKind,
+    lock: fn(&MinecraftTypes) -> &TypeLock<T>,
+}
+
+impl<T> TypeRef<T> {
+    pub fn kind(&self) -> TypeKind {
+        self.kind
+    }
+
+    /// Returns the lock of this type in `types`.
+    pub fn lock<'a>(&self, types: &'a MinecraftTypes) -> &'a TypeLock<T> {
+        (self.lock)(types)
+    }
+
+    /// Returns the converters, walkers and hooks that have been registered with this type in the current registry.
+    pub fn registrations(&self) -> Vec<Registration> {
+        self.lock(&types()).registrations()
+    }
+}
+
+impl<T: AbstractMapDataType> AbstractMapDataType for TypeRef<T> {
+    fn convert(&self, data: &mut JCompound, from_version: DataVersion, to_version: DataVersion) {
+        with_types(|types| self.lock(types).convert(data, from_version, to_version))
+    }
+}
+
+impl<T: AbstractValueDataType> AbstractValueDataType for TypeRef<T> {
+    fn convert(&self, data: &mut JValueMut, from_version: DataVersion, to_version: DataVersion) {
+        with_types(|types| self.lock(types).convert(data, from_version, to_version))
+    }
+}
+
+impl<T: AbstractDynamicDataType> AbstractDynamicDataType for TypeRef<T> {
+    fn convert(&self, data: &mut JValue, from_version: DataVersion, to_version: DataVersion) {
+        with_types(|types| self.lock(types).convert(data, from_version, to_version))
+    }
+}
+
+#[derive(Default)]
+pub struct MinecraftTypesBuilder {
+    extensions: Vec<Box<dyn FnOnce()>>,
+}
+
+impl MinecraftTypesBuilder {
+    /// Adds an extension, which runs after the vanilla versions have been registered with the new registry being
+    /// current, so that the global `*_mut` functions of this module modify the new registry.
+    pub fn extension(mut self, extension: impl FnOnce() + 'static) -> Self {
+        self.extensions.push(Box::new(extension));
+        self
+    }
+
+    /// Builds the registry, which is freed when the last reference to it is dropped.
+    pub fn build(self) -> Arc<MinecraftTypes> {
+        MinecraftTypes::build(self.extensions)
     }
 }
 
 macro_rules! define_minecraft_types {
-    ($($field_name:ident $field_name_mut:ident $field_name_ref:ident : $type:ident ($name:literal)),* $(,)?) => {
+    ($($field_name:ident $field_name_mut:ident $field_name_ref:ident $kind:ident : $type:ident ($name:literal)),* $(,)?) => {
 
-        struct MinecraftTypes {
+        /// Identifies one of the types of a [`MinecraftTypes`] registry.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum TypeKind {
             $(
-                $field_name: RwLock<$type<'static>>,
+                $kind,
             )*
         }
 
+        /// A registry of all the Minecraft data types, with their converters, walkers and hooks.
+        pub struct MinecraftTypes {
+            $(
+                $field_name: TypeLock<$type<'static>>,
+            )*
+            legacy: LegacyTables,
+        }
+
         impl MinecraftTypes {
-            fn create_empty() -> Self {
-                Self {
+            fn create_empty() -> Arc<Self> {
+                Arc::new_cyclic(|types| Self {
                     $(
-                        $field_name: RwLock::new($type::new($name)),
+                        $field_name: TypeLock::new(TypeKind::$kind, $type::new($name), types.clone()),
                     )*
+                    legacy: LegacyTables::default(),
+                })
+            }
+
+            $(
+            pub fn $field_name(&self) -> RwLockReadGuard<'_, $type<'static>> {
+                match self.$field_name.lock.try_read() {
+                    Ok(guard) => guard,
+                    Err(TryLockError::WouldBlock) => panic!(concat!("Tried to get an immutable reference to ", stringify!($field_name), " while there is a mutable reference")),
+                    Err(TryLockError::Poisoned(err)) => panic!("{}", err),
                 }
             }
+
+            pub fn $field_name_mut(&self) -> TypeWriteGuard<'_, $type<'static>> {
+                match self.$field_name.lock.try_write() {
+                    Ok(guard) => TypeWriteGuard {
+                        kind: self.$field_name.kind,
+                        guard,
+                        registrations: &self.$field_name.registrations,
+                        _types: None,
+                    },
+                    Err(TryLockError::WouldBlock) => panic!(concat!("Tried to get an mutable reference to ", stringify!($field_name), " while there are immutable references")),
+                    Err(TryLockError::Poisoned(err)) => panic!("{}", err),
+                }
+            }
+
+            pub fn $field_name_ref(&self) -> &TypeLock<$type<'static>> {
+                &self.$field_name
+            }
+            )*
         }
 
         $(
-        pub fn $field_name() -> RwLockReadGuard<'static, $type<'static>> {
-            match types().$field_name.try_read() {
-                Ok(guard) => guard,
-                Err(TryLockError::WouldBlock) => panic!(concat!("Tried to get an immutable reference to ", stringify!($field_name), " while there is a mutable reference")),
-                Err(TryLockError::Poisoned(err)) => panic!("{}", err),
-            }
+        pub fn $field_name() -> TypeReadGuard<$type<'static>> {
+            let types = types();
+            // SAFETY: only the lifetime is extended. The guard borrows from the registry, which is kept alive by the
+            // Arc in the returned guard, and released before that Arc is dropped.
+            let guard = unsafe {
+                std::mem::transmute::<
+                    RwLockReadGuard<'_, $type<'static>>,
+                    RwLockReadGuard<'static, $type<'static>>,
+                >(types.$field_name())
+            };
+            TypeReadGuard { guard, _types: types }
         }
 
-        pub(crate) fn $field_name_mut() -> RwLockWriteGuard<'static, $type<'static>> {
-            match types().$field_name.try_write() {
-                Ok(guard) => guard,
-                Err(TryLockError::WouldBlock) => panic!(concat!("Tried to get an mutable reference to ", stringify!($field_name), " while there are immutable references")),
-                Err(TryLockError::Poisoned(err)) => panic!("{}", err),
-            }
+        pub fn $field_name_mut() -> TypeWriteGuard<'static, $type<'static>> {
+            let types = types();
+            // SAFETY: as above, the borrows of the guard are extended, and the guard keeps the registry alive.
+            let guard = unsafe {
+                std::mem::transmute::<
+                    TypeWriteGuard<'_, $type<'static>>,
+                    TypeWriteGuard<'static, $type<'static>>,
+                >(types.$field_name_mut())
+            };
+            TypeWriteGuard { _types: Some(types), ..guard }
         }
 
-        #[allow(unused)]
-        pub(crate) fn $field_name_ref() -> &'static RwLock<$type<'static>> {
-            &types().$field_name
+        pub fn $field_name_ref() -> &'static TypeRef<$type<'static>> {
+            static TYPE: TypeRef<$type<'static>> = TypeRef {
+                kind: TypeKind::$kind,
+                lock: MinecraftTypes::$field_name_ref,
+            };
+            &TYPE
         }
         )*
     }
 }
 
+// SAFETY: the engine types aren't Send or Sync for two reasons:
+// - Their converters, walkers and hooks are boxed trait objects without Send and Sync bounds. Everything is registered
+//   through a TypeWriteGuard, which requires them to be Send and Sync, and doesn't give mutable access to the engine
+//   types otherwise.
+// - IdDataType keeps the walkers for each id in an Rc. Those are only cloned by IdDataType::copy_walkers, into the same
+//   IdDataType, and only dropped along with it, both of which need the write lock of the type. Converting only borrows
+//   the walkers. So the reference counts are never accessed by two threads at the same time, and no Rc escapes.
+unsafe impl Sync for MinecraftTypes {}
+unsafe impl Send for MinecraftTypes {}
+
+impl MinecraftTypes {
+    pub(crate) fn legacy(&self) -> &LegacyTables {
+        &self.legacy
+    }
+
+    /// Creates a new registry with only the vanilla versions registered.
+    pub fn new() -> Arc<Self> {
+        Self::builder().build()
+    }
+
+    pub fn builder() -> MinecraftTypesBuilder {
+        MinecraftTypesBuilder::default()
+    }
+
+    fn build(extensions: Vec<Box<dyn FnOnce()>>) -> Arc<Self> {
+        let types = Self::create_empty();
+        types.enter(|| {
+            // General notes:
+            // - Structure converters run before everything.
+            // - ID specific converters run after structure converters.
+            // - Structure walkers run after id specific converters.
+            // - ID specific walkers run after structure walkers.
+            crate::versions::register_versions();
+            for extension in extensions {
+                extension();
+            }
+        });
+        types
+    }
+
+    /// Makes this registry current on this thread while running `f`, so that the global functions of this module use
+    /// it.
+    pub fn enter<R>(self: &Arc<Self>, f: impl FnOnce() -> R) -> R {
+        struct RestoreGuard(Option<Arc<MinecraftTypes>>);
+
+        impl Drop for RestoreGuard {
+            fn drop(&mut self) {
+                CURRENT_TYPES.set(self.0.take());
+            }
+        }
+
+        let _guard = RestoreGuard(CURRENT_TYPES.replace(Some(self.clone())));
+        f()
+    }
+}
+
 define_minecraft_types! {
-    level level_mut level_ref: MapDataType("Level"),
-    player player_mut player_ref: MapDataType("Player"),
-    chunk chunk_mut chunk_ref: MapDataType("Chunk"),
-    hotbar hotbar_mut hotbar_ref: MapDataType("Hotbar"),
-    options options_mut options_ref: MapDataType("Options"),
-    structure structure_mut structure_ref: MapDataType("Structure"),
-    stats stats_mut stats_ref: MapDataType("Stats"),
-    advancements advancements_mut advancements_ref: MapDataType("Advancements"),
-    poi_chunk poi_chunk_mut poi_chunk_ref: MapDataType("PoiChunk"),
-    entity_chunk entity_chunk_mut entity_chunk_ref: MapDataType("EntityChunk"),
-    tile_entity tile_entity_mut tile_entity_ref: IdDataType("TileEntity"),
-    item_stack item_stack_mut item_stack_ref: IdDataType("ItemStack"),
-    block_state block_state_mut block_state_ref: MapDataType("BlockName"),
-    flat_block_state flat_block_state_mut flat_block_state_ref: ObjectDataType("FlatBlockState"),
-    data_components data_components_mut data_components_ref: MapDataType("DataComponents"),
-    villager_trade villager_trade_mut villager_trade_ref: MapDataType("VillagerTrade"),
-    particle particle_mut particle_ref: DynamicDataType("Particle"),
-    entity_name entity_name_mut entity_name_ref: ObjectDataType("EntityName"),
-    entity entity_mut entity_ref: IdDataType("Entity"),
-    block_name block_name_mut block_name_ref: ObjectDataType("BlockName"),
-    item_name item_name_mut item_name_ref: ObjectDataType("ItemName"),
-    untagged_spawner untagged_spawner_mut untagged_spawner_ref: MapDataType("Spawner"),
-    structure_feature structure_feature_mut structure_feature_ref: MapDataType("StructureFeature"),
-    objective objective_mut objective_ref: MapDataType("Objective"),
-    team team_mut team_ref: MapDataType("Team"),
-    recipe recipe_mut recipe_ref: ObjectDataType("RecipeName"),
-    biome biome_mut biome_ref: ObjectDataType("Biome"),
-    world_gen_settings world_gen_settings_mut world_gen_settings_ref: MapDataType("WorldGenSettings"),
-    game_event_name game_event_name_mut game_event_name_ref: ObjectDataType("GameEventName"),
-
-    multi_noise_biome_source_parameter_list multi_noise_biome_source_parameter_list_mut multi_noise_biome_source_parameter_list_ref: ObjectDataType("MultiNoiseBiomeSourceParameterList"),
-
-    saved_data_random_sequences saved_data_random_sequences_mut saved_data_random_sequences_ref: MapDataType("SavedData/RandomSequences"),
-    saved_data_scoreboard saved_data_scoreboard_mut saved_data_scoreboard_ref: MapDataType("SavedData/Scoreboard"),
-    saved_data_structure_feature_indices saved_data_structure_feature_indices_mut saved_data_structure_feature_indices_ref: MapDataType("SavedData/StructureFeatureIndices"),
-    saved_data_map_data saved_data_map_data_mut saved_data_map_data_ref: MapDataType("SavedData/MapData"),
-    saved_data_raids saved_data_raids_mut saved_data_raids_ref: MapDataType("SavedData/Raids"),
-    saved_data_command_storage saved_data_command_strage_mut saved_data_command_storage_ref: MapDataType("SavedData/CommandStorage"),
-    saved_data_forced_chunks saved_data_forced_chunks_mut saved_data_forced_chunks_ref: MapDataType("SavedData/Chunks"),
-    saved_data_map_index saved_data_map_index_mut saved_data_map_index_ref: MapDataType("SavedData/IdCounts"),
+    level level_mut level_ref Level: MapDataType("Level"),
+    player player_mut player_ref Player: MapDataType("Player"),
+    chunk chunk_mut chunk_ref Chunk: MapDataType("Chunk"),
+    hotbar hotbar_mut hotbar_ref Hotbar: MapDataType("Hotbar"),
+    options options_mut options_ref Options: MapDataType("Options"),
+    structure structure_mut structure_ref Structure: MapDataType("Structure"),
+    stats stats_mut stats_ref Stats: MapDataType("Stats"),
+    advancements advancements_mut advancements_ref Advancements: MapDataType("Advancements"),
+    poi_chunk poi_chunk_mut poi_chunk_ref PoiChunk: MapDataType("PoiChunk"),
+    entity_chunk entity_chunk_mut entity_chunk_ref EntityChunk: MapDataType("EntityChunk"),
+    tile_entity tile_entity_mut tile_entity_ref TileEntity: IdDataType("TileEntity"),
+    item_stack item_stack_mut item_stack_ref ItemStack: IdDataType("ItemStack"),
+    block_state block_state_mut block_state_ref BlockState: MapDataType("BlockName"),
+    flat_block_state flat_block_state_mut flat_block_state_ref FlatBlockState: ObjectDataType("FlatBlockState"),
+    data_components data_components_mut data_components_ref DataComponents: MapDataType("DataComponents"),
+    villager_trade villager_trade_mut villager_trade_ref VillagerTrade: MapDataType("VillagerTrade"),
+    particle particle_mut particle_ref Particle: DynamicDataType("Particle"),
+    entity_name entity_name_mut entity_name_ref EntityName: ObjectDataType("EntityName"),
+    entity entity_mut entity_ref Entity: IdDataType("Entity"),
+    block_name block_name_mut block_name_ref BlockName: ObjectDataType("BlockName"),
+    item_name item_name_mut item_name_ref ItemName: ObjectDataType("ItemName"),
+    untagged_spawner untagged_spawner_mut untagged_spawner_ref UntaggedSpawner: MapDataType("Spawner"),
+    structure_feature structure_feature_mut structure_feature_ref StructureFeature: MapDataType("StructureFeature"),
+    objective objective_mut objective_ref Objective: MapDataType("Objective"),
+    team team_mut team_ref Team: MapDataType("Team"),
+    recipe recipe_mut recipe_ref Recipe: ObjectDataType("RecipeName"),
+    biome biome_mut biome_ref Biome: ObjectDataType("Biome"),
+    world_gen_settings world_gen_settings_mut world_gen_settings_ref WorldGenSettings: MapDataType("WorldGenSettings"),
+    game_event_name game_event_name_mut game_event_name_ref GameEventName: ObjectDataType("GameEventName"),
+
+    multi_noise_biome_source_parameter_list multi_noise_biome_source_parameter_list_mut multi_noise_biome_source_parameter_list_ref MultiNoiseBiomeSourceParameterList: ObjectDataType("MultiNoiseBiomeSourceParameterList"),
+
+    saved_data_random_sequences saved_data_random_sequences_mut saved_data_random_sequences_ref SavedDataRandomSequences: MapDataType("SavedData/RandomSequences"),
+    saved_data_scoreboard saved_data_scoreboard_mut saved_data_scoreboard_ref SavedDataScoreboard: MapDataType("SavedData/Scoreboard"),
+    saved_data_structure_feature_indices saved_data_structure_feature_indices_mut saved_data_structure_feature_indices_ref SavedDataStructureFeatureIndices: MapDataType("SavedData/StructureFeatureIndices"),
+    saved_data_map_data saved_data_map_data_mut saved_data_map_data_ref SavedDataMapData: MapDataType("SavedData/MapData"),
+    saved_data_raids saved_data_raids_mut saved_data_raids_ref SavedDataRaids: MapDataType("SavedData/Raids"),
+    saved_data_command_storage saved_data_command_storage_mut saved_data_command_storage_ref SavedDataCommandStorage: MapDataType("SavedData/CommandStorage"),
+    saved_data_forced_chunks saved_data_forced_chunks_mut saved_data_forced_chunks_ref SavedDataForcedChunks: MapDataType("SavedData/Chunks"),
+    saved_data_map_index saved_data_map_index_mut saved_data_map_index_ref SavedDataMapIndex: MapDataType("SavedData/IdCounts"),
 }
@@ -1,7 +1,20 @@
+use crate::helpers::json_parser::{parse_compound, ParseError};
+use java_string::JavaStr;
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
-use std::sync::OnceLock;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use std::ops::RangeBounds;
+use std::path::Path;
+use std::sync::{OnceLock, RwLock, RwLockReadGuard};
 use world_transmuter_engine::DataVersion;
+use world_transmuter_engine::{JCompound, JList, JValue};
+use zip::result::ZipError;
+use zip::ZipArchive;
 
+#[derive(Default)]
 struct VersionData {
     versions_by_id: BTreeMap<u32, Version>,
     versions_by_name: BTreeMap<&'static str, Version>,
@@ -18,20 +31,31 @@ impl VersionData {
         self.versions_by_id.insert(data_version, version);
         self.versions_by_name.insert(name, version);
     }
+    /// Adds a breakpoint, at which conversions are split so that data is fully converted to it before any later
+    /// converter runs. These are the breakpoints registered by DataConverter's `MCVersionRegistry`.
     fn bp(&mut self, version: impl Into<DataVersion>) {
         self.breakpoints.push(version.into());
     }
 }
 
-fn version_data() -> &'static VersionData {
-    static VERSION_DATA: OnceLock<VersionData> = OnceLock::new();
+struct VersionTable {
+    /// The versions, which grow when versions are loaded at runtime.
+    data: RwLock<VersionData>,
+    breakpoints: Vec<DataVersion>,
+}
 
-    VERSION_DATA.get_or_init(|| {
-        let mut data = VersionData {
-            versions_by_id: BTreeMap::new(),
-            versions_by_name: BTreeMap::new(),
-            breakpoints: Vec::new(),
-        };
+fn version_data() -> RwLockReadGuard<'static, VersionData> {
+    version_table()
+        .data
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+}
+
+fn version_table() -> &'static VersionTable {
+    static VERSION_TABLE: OnceLock<VersionTable> = OnceLock::new();
+
+    VERSION_TABLE.get_or_init(|| {
+        let mut data = VersionData::default();
 
         data.v("1.8", 99, VersionType::Release);
         data.v("15w32a", 100, VersionType::Snapshot);
@@ -562,12 +586,215 @@ fn version_data() -> &'static VersionData {
         data.v("1.20.5", 3837, VersionType::Release);
         data.v("1.20.6-rc1", 3838, VersionType::Snapshot);
         data.v("1.20.6", 3839, VersionType::Release);
+        // The last breakpoint registered by MCVersionRegistry as of 24w34a. The converters of later versions don't need
+        // one, see test_converters_after_last_breakpoint.
         data.bp(3840);
+        data.v("24w18a", 3940, VersionType::Snapshot);
+        data.v("24w19a", 3941, VersionType::Snapshot);
+        data.v("24w19b", 3942, VersionType::Snapshot);
+        data.v("24w20a", 3944, VersionType::Snapshot);
+        data.v("24w21a", 3946, VersionType::Snapshot);
+        data.v("24w21b", 3947, VersionType::Snapshot);
+        data.v("1.21-pre1", 3948, VersionType::Snapshot);
+        data.v("1.21-pre2", 3949, VersionType::Snapshot);
+        data.v("1.21-pre3", 3950, VersionType::Snapshot);
+        data.v("1.21-pre4", 3951, VersionType::Snapshot);
+        data.v("1.21-rc1", 3952, VersionType::Snapshot);
+        data.v("1.21", 3953, VersionType::Release);
+        data.v("1.21.1-rc1", 3954, VersionType::Snapshot);
+        data.v("1.21.1", 3955, VersionType::Release);
+        data.v("24w33a", 4058, VersionType::Snapshot);
+        data.v("24w34a", 4060, VersionType::Snapshot);
 
-        data
+        let breakpoints = std::mem::take(&mut data.breakpoints);
+        VersionTable {
+            data: RwLock::new(data),
+            breakpoints,
+        }
     })
 }
 
+impl VersionData {
+    /// Adds versions to the table, skipping names that are already known. If a data version is already known under
+    /// another name, the new name is only used for lookups by name. Returns the versions that were added.
+    ///
+    /// The names of added versions are leaked, as versions are kept for the rest of the program, but each name is only
+    /// leaked once.
+    fn add_versions(
+        &mut self,
+        versions: impl IntoIterator<Item = (String, u32, VersionType)>,
+    ) -> Vec<Version> {
+        let mut added = Vec::new();
+        for (name, data_version, typ) in versions {
+            if self.versions_by_name.contains_key(&name[..]) {
+                continue;
+            }
+            let version = Version {
+                name: String::leak(name),
+                data_version,
+                typ,
+            };
+            self.versions_by_id.entry(data_version).or_insert(version);
+            self.versions_by_name.insert(version.name, version);
+            added.push(version);
+        }
+        added
+    }
+}
+
+fn add_versions(versions: impl IntoIterator<Item = (String, u32, VersionType)>) -> Vec<Version> {
+    version_table()
+        .data
+        .write()
+        .unwrap_or_else(|err| err.into_inner())
+        .add_versions(versions)
+}
+
+/// Registers a version that isn't known yet, such as a snapshot released after this version of the library. Returns
+/// `None` if a version with that name is already known.
+pub fn register_version(name: &str, data_version: u32, typ: VersionType) -> Option<Version> {
+    add_versions([(name.to_owned(), data_version, typ)])
+        .into_iter()
+        .next()
+}
+
+/// Registers the versions found in a file on disk, without accessing the network. The file can be:
+/// - A client or server jar, or the `version.json` inside one.
+/// - A launcher version manifest, such as `version_manifest_v2.json`. The manifest doesn't contain data versions, so
+///   they are taken from a `world_version` or `data_version` field of the entry if there is one, or else from the
+///   launcher jar at `<id>/<id>.jar` next to the manifest. Entries without either, or of an unknown type, are skipped.
+///
+/// Returns the versions that weren't known before.
+pub fn load_versions(path: impl AsRef<Path>) -> Result<Vec<Version>, VersionLoadError> {
+    Ok(add_versions(read_versions(path.as_ref())?))
+}
+
+/// Reads the versions found in a file, see [`load_versions`].
+fn read_versions(path: &Path) -> Result<Vec<(String, u32, VersionType)>, VersionLoadError> {
+    if path.extension().is_some_and(|extension| extension == "jar") {
+        return Ok(vec![read_jar_version(path)?]);
+    }
+
+    let json = parse_json_file(File::open(path)?)?;
+    let Some(JValue::List(entries)) = json.get("versions") else {
+        return Ok(vec![read_version_json(&json)?]);
+    };
+    let JList::Compound(entries) = entries else {
+        return Ok(Vec::new());
+    };
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut versions = Vec::new();
+    for entry in entries {
+        let Some(JValue::String(id)) = entry.get("id") else {
+            continue;
+        };
+        let id = id.to_string();
+        let data_version = match get_data_version(entry) {
+            Some(data_version) => data_version,
+            None => {
+                let jar = dir.join(&id).join(format!("{id}.jar"));
+                if !jar.is_file() {
+                    continue;
+                }
+                read_jar_version(&jar)?.1
+            }
+        };
+        let typ = match entry.get("type") {
+            Some(JValue::String(typ)) if typ == "release" => VersionType::Release,
+            Some(JValue::String(typ)) if typ == "snapshot" => VersionType::Snapshot,
+            Some(JValue::String(typ)) if typ == "old_beta" => VersionType::OldBeta,
+            Some(JValue::String(typ)) if typ == "old_alpha" => VersionType::OldAlpha,
+            _ => continue,
+        };
+        versions.push((id, data_version, typ));
+    }
+    Ok(versions)
+}
+
+fn parse_json_file(mut file: impl Read) -> Result<JCompound, VersionLoadError> {
+    let mut json = Vec::new();
+    file.read_to_end(&mut json)?;
+    let json = JavaStr::from_semi_utf8(&json).map_err(|_| VersionLoadError::InvalidUtf8)?;
+    Ok(parse_compound(json, false)?)
+}
+
+fn get_data_version(json: &JCompound) -> Option<u32> {
+    ["world_version", "data_version"]
+        .into_iter()
+        .find_map(|key| json.get(key)?.as_i64())
+        .and_then(|data_version| u32::try_from(data_version).ok())
+}
+
+fn read_version_json(json: &JCompound) -> Result<(String, u32, VersionType), VersionLoadError> {
+    let Some(JValue::String(name)) = json.get("id").or_else(|| json.get("name")) else {
+        return Err(VersionLoadError::MissingField("id"));
+    };
+    let data_version =
+        get_data_version(json).ok_or(VersionLoadError::MissingField("world_version"))?;
+    let typ = if json.get("stable").and_then(|stable| stable.as_bool()) == Some(true) {
+        VersionType::Release
+    } else {
+        VersionType::Snapshot
+    };
+    Ok((name.to_string(), data_version, typ))
+}
+
+fn read_jar_version(path: &Path) -> Result<(String, u32, VersionType), VersionLoadError> {
+    let mut jar = ZipArchive::new(File::open(path)?)?;
+    let json = parse_json_file(jar.by_name("version.json")?)?;
+    read_version_json(&json)
+}
+
+#[derive(Debug)]
+pub enum VersionLoadError {
+    Io(std::io::Error),
+    Zip(ZipError),
+    Json(ParseError),
+    InvalidUtf8,
+    MissingField(&'static str),
+}
+
+impl Display for VersionLoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VersionLoadError::Io(err) => Display::fmt(err, f),
+            VersionLoadError::Zip(err) => Display::fmt(err, f),
+            VersionLoadError::Json(err) => write!(f, "Invalid JSON: {err}"),
+            VersionLoadError::InvalidUtf8 => write!(f, "Invalid UTF-8"),
+            VersionLoadError::MissingField(field) => write!(f, "Missing field {field}"),
+        }
+    }
+}
+
+impl std::error::Error for VersionLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VersionLoadError::Io(err) => Some(err),
+            VersionLoadError::Zip(err) => Some(err),
+            VersionLoadError::Json(err) => Some(err),
+            VersionLoadError::InvalidUtf8 | VersionLoadError::MissingField(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for VersionLoadError {
+    fn from(value: std::io::Error) -> Self {
+        VersionLoadError::Io(value)
+    }
+}
+
+impl From<ZipError> for VersionLoadError {
+    fn from(value: ZipError) -> Self {
+        VersionLoadError::Zip(value)
+    }
+}
+
+impl From<ParseError> for VersionLoadError {
+    fn from(value: ParseError) -> Self {
+        VersionLoadError::Json(value)
+    }
+}
+
 pub fn get_version_by_id(id: u32) -> Option<Version> {
     version_data().versions_by_id.get(&id).copied()
 }
@@ -577,13 +804,51 @@ pub fn get_version_by_name(name: &str) -> Option<Version> {
 }
 
 pub fn get_versions() -> impl DoubleEndedIterator<Item = Version> {
-    version_data().versions_by_id.values().copied()
+    let versions: Vec<_> = version_data().versions_by_id.values().copied().collect();
+    versions.into_iter()
+}
+
+/// Returns the versions whose data versions are in `range`, in order.
+pub fn get_versions_in_range(
+    range: impl RangeBounds<u32>,
+) -> impl DoubleEndedIterator<Item = Version> {
+    let versions: Vec<_> = version_data()
+        .versions_by_id
+        .range(range)
+        .map(|(_, version)| *version)
+        .collect();
+    versions.into_iter()
+}
+
+/// Returns the last release at or before `data_version`, i.e. the release that data of that version was most likely
+/// upgraded from.
+pub fn get_release_at_or_before(data_version: u32) -> Option<Version> {
+    get_versions_in_range(..=data_version)
+        .rev()
+        .find(|version| version.typ == VersionType::Release)
+}
+
+/// Returns the first release at or after `data_version`, i.e. the release that a snapshot of that version led up to.
+pub fn get_release_at_or_after(data_version: u32) -> Option<Version> {
+    get_versions_in_range(data_version..).find(|version| version.typ == VersionType::Release)
+}
+
+pub fn latest() -> Option<Version> {
+    get_versions().next_back()
+}
+
+pub fn latest_release() -> Option<Version> {
+    get_versions()
+        .rev()
+        .find(|version| version.typ == VersionType::Release)
 }
 
 pub fn get_breakpoints() -> &'static [DataVersion] {
-    &version_data().breakpoints
+    &version_table().breakpoints
 }
 
+/// A named version. Versions are compared by their data version only, so versions sharing a data version, such as a
+/// version loaded under another name, are equal.
 #[derive(Debug, Copy, Clone, Eq)]
 #[non_exhaustive]
 pub struct Version {
@@ -598,15 +863,215 @@ impl PartialEq for Version {
     }
 }
 
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.data_version.cmp(&other.data_version)
+    }
+}
+
+impl Hash for Version {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.data_version.hash(state);
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+/// Looks up a version by its name, such as `1.20.5-pre1` or `24w14a`, ignoring case, or by the data version of a known
+/// version.
+pub fn find_version(name_or_data_version: &str) -> Result<Version, UnknownVersionError> {
+    let name = name_or_data_version.trim();
+    get_version_by_name(name)
+        .or_else(|| get_version_by_name(&name.to_ascii_lowercase()))
+        .or_else(|| name.parse().ok().and_then(get_version_by_id))
+        .ok_or_else(|| UnknownVersionError {
+            name: name.to_owned(),
+        })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownVersionError {
+    pub name: String,
+}
+
+impl Display for UnknownVersionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown version {}", self.name)
+    }
+}
+
+impl std::error::Error for UnknownVersionError {}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum VersionType {
     Release,
+    /// Snapshots, pre-releases and release candidates.
     Snapshot,
+    /// Beta versions, which have no data version, so they are only known if one is given when they are loaded.
+    OldBeta,
+    /// Alpha versions, which have no data version, so they are only known if one is given when they are loaded.
+    OldAlpha,
 }
 
 #[cfg(test)]
-#[cfg(feature = "update_checks")]
 mod tests {
+    use crate::test_util::TempDir;
+    use crate::version_names::*;
+    use std::io::Write;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    #[test]
+    fn test_load_versions() {
+        // load into a table of this test, rather than the one shared by all tests
+        let mut data = VersionData::default();
+        data.v("1.21", 3953, VersionType::Release);
+        let mut load_versions = |path: &Path| data.add_versions(read_versions(path).unwrap());
+
+        let dir = TempDir::new("load-versions");
+        let version_json = dir.path().join("version.json");
+        std::fs::write(
+            &version_json,
+            r#"{"id": "test-version-json", "world_version": 3943, "stable": false}"#,
+        )
+        .unwrap();
+        let loaded = load_versions(&version_json);
+        assert_eq!(1, loaded.len());
+        assert_eq!(3943, loaded[0].data_version);
+        assert_eq!("test-version-json", loaded[0].name);
+        assert!(load_versions(&version_json).is_empty());
+
+        std::fs::create_dir(dir.path().join("test-jar")).unwrap();
+        let mut jar =
+            ZipWriter::new(File::create(dir.path().join("test-jar/test-jar.jar")).unwrap());
+        jar.start_file("version.json", FileOptions::default())
+            .unwrap();
+        jar.write_all(br#"{"id": "test-jar", "world_version": 3945, "stable": true}"#)
+            .unwrap();
+        jar.finish().unwrap();
+        let manifest = dir.path().join("version_manifest_v2.json");
+        std::fs::write(
+            &manifest,
+            r#"{"versions": [
+                {"id": "test-jar", "type": "release"},
+                {"id": "test-missing-jar", "type": "snapshot"},
+                {"id": "test-manifest", "type": "snapshot", "world_version": 3939},
+                {"id": "test-beta", "type": "old_beta", "world_version": 3938},
+                {"id": "test-unknown-type", "type": "pending", "world_version": 3937},
+                {"id": "test-shared", "type": "snapshot", "world_version": 3953}
+            ]}"#,
+        )
+        .unwrap();
+        let loaded = load_versions(&manifest);
+        assert_eq!(4, loaded.len());
+        assert_eq!(VersionType::Release, loaded[0].typ);
+        assert_eq!(3945, loaded[0].data_version);
+        assert_eq!(VersionType::Snapshot, loaded[1].typ);
+        assert_eq!(VersionType::OldBeta, loaded[2].typ);
+        assert!(!data.versions_by_name.contains_key("test-unknown-type"));
+        assert!(!data.versions_by_name.contains_key("test-missing-jar"));
+        assert_eq!(
+            Some("test-manifest"),
+            data.versions_by_id.get(&3939).map(|version| version.name)
+        );
+        assert_eq!(
+            Some("1.21"),
+            data.versions_by_id.get(&3953).map(|version| version.name)
+        );
+        assert_eq!(
+            Some(3953),
+            data.versions_by_name
+                .get("test-shared")
+                .map(|version| version.data_version)
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(3829, find_version("1.20.5-pre1").unwrap().data_version);
+        assert_eq!(3827, find_version("24W14A").unwrap().data_version);
+        assert_eq!("1.21", find_version("3953").unwrap().name);
+        assert!(find_version("1.21.99").is_err());
+
+        // versions sharing a data version are equal
+        let release = get_version_by_name("1.21").unwrap();
+        let shared = Version {
+            name: "test-same-data-version",
+            typ: VersionType::Snapshot,
+            ..release
+        };
+        assert_eq!(release, shared);
+        assert_eq!(Ordering::Equal, release.cmp(&shared));
+        assert!(get_version_by_name("1.20.6").unwrap() < shared);
+    }
+
+    #[test]
+    fn test_releases() {
+        assert_eq!("1.20.4", get_release_at_or_before(3824).unwrap().name);
+        assert_eq!("1.20.5", get_release_at_or_after(3824).unwrap().name);
+        assert_eq!("1.20.5", get_release_at_or_before(3837).unwrap().name);
+        assert!(get_release_at_or_before(98).is_none());
+        assert!(latest_release().unwrap() <= latest().unwrap());
+
+        let from = get_version_by_name("1.20.4").unwrap();
+        let to = get_version_by_name("1.20.5").unwrap();
+        let snapshots: Vec<_> = get_versions_in_range(from.data_version..to.data_version)
+            .filter(|version| version.typ == VersionType::Snapshot)
+            .collect();
+        assert_eq!("23w51a", snapshots.first().unwrap().name);
+        assert_eq!("1.20.5-rc3", snapshots.last().unwrap().name);
+    }
+
+    #[test]
+    fn test_converters_covered() {
+        let highest_converter = crate::versions::VERSIONS
+            .iter()
+            .map(|version| version[1..].parse::<u32>().unwrap())
+            .max()
+            .unwrap();
+        let latest_version = latest().unwrap();
+        assert!(
+            latest_version.data_version >= highest_converter,
+            "The latest named version {} ({}) doesn't include the converters for {}",
+            latest_version.name,
+            latest_version.data_version,
+            highest_converter
+        );
+    }
+
+    #[test]
+    fn test_converters_after_last_breakpoint() {
+        // Converters after the last breakpoint which deliberately don't have one. A converter that isn't listed here
+        // either needs a breakpoint, or must be checked against MCVersionRegistry and added here.
+        const WITHOUT_BREAKPOINT: &[u32] = &[3938, 3939, 3943, 3945, 4054, 4055, 4057, 4059];
+
+        let last_breakpoint = get_breakpoints().last().unwrap().get_version();
+        let unchecked: Vec<_> = crate::versions::VERSIONS
+            .iter()
+            .map(|version| version[1..].parse::<u32>().unwrap())
+            .filter(|version| *version > last_breakpoint && !WITHOUT_BREAKPOINT.contains(version))
+            .collect();
+        assert!(
+            unchecked.is_empty(),
+            "The converters for {unchecked:?} come after the last breakpoint {last_breakpoint}"
+        );
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "update_checks")]
+mod update_checks {
     use crate::version_names::{get_version_by_id, VersionType};
     use serde::Deserialize;
     use time::macros::datetime;
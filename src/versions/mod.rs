@@ -4,6 +4,9 @@ macro_rules! versions {
             pub(crate) mod $version;
         )*
 
+        /// The names of the version modules, which are `v` followed by the data version.
+        pub(crate) const VERSIONS: &[&str] = &[$(stringify!($version)),*];
+
         pub(crate) fn register_versions() {
             $(
                 $version::register();
@@ -1,7 +1,8 @@
+use crate::diagnostics::{DiagnosticKind, Severity};
 use crate::helpers::item_name_v102;
-use crate::types;
+use crate::legacy::id_map;
+use crate::{diagnostics, types};
 use java_string::JavaStr;
-use tracing::warn;
 use world_transmuter_engine::{map_data_converter_func, JCompound, JValue};
 
 const VERSION: u32 = 102;
@@ -20,10 +21,18 @@ pub(crate) fn register() {
         VERSION,
         map_data_converter_func(|data, _from_version, _to_version| {
             if let Some(item) = data.get("Item").and_then(|v| v.as_i32()) {
-                let name = item_name_v102::get_name_from_id(item).unwrap_or_else(|| {
-                    warn!("Unknown legacy integer id (V102): {}", item);
-                    item_name_v102::get_name_from_id(0).unwrap()
-                });
+                let name = item_name_v102::get_name_from_id(item)
+                    .map(JavaStr::to_owned)
+                    .or_else(|| id_map::item_name(item))
+                    .unwrap_or_else(|| {
+                        diagnostics::report(
+                            DiagnosticKind::UnknownLegacyId,
+                            Severity::DataLoss,
+                            "Item",
+                            format!("Unknown legacy integer id: {}", item),
+                        );
+                        item_name_v102::get_name_from_id(0).unwrap().to_owned()
+                    });
                 data.insert("Item", name);
             }
         }),
@@ -33,10 +42,18 @@ pub(crate) fn register() {
         VERSION,
         map_data_converter_func(|data, _from_version, _to_version| {
             if let Some(id) = data.get("id").and_then(|v| v.as_i32()) {
-                let name = item_name_v102::get_name_from_id(id).unwrap_or_else(|| {
-                    warn!("Unknown legacy integer id (V102): {}", id);
-                    item_name_v102::get_name_from_id(0).unwrap()
-                });
+                let name = item_name_v102::get_name_from_id(id)
+                    .map(JavaStr::to_owned)
+                    .or_else(|| id_map::item_name(id))
+                    .unwrap_or_else(|| {
+                        diagnostics::report(
+                            DiagnosticKind::UnknownLegacyId,
+                            Severity::DataLoss,
+                            "id",
+                            format!("Unknown legacy integer id: {}", id),
+                        );
+                        item_name_v102::get_name_from_id(0).unwrap().to_owned()
+                    });
                 data.insert("id", name);
             }
         }),
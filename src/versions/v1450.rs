@@ -1,4 +1,5 @@
 use crate::helpers::block_flattening_v1450;
+use crate::legacy::flattening;
 use crate::types;
 use world_transmuter_engine::map_data_converter_func;
 
@@ -8,7 +9,9 @@ pub(crate) fn register() {
     types::block_state_mut().add_structure_converter(
         VERSION,
         map_data_converter_func(|data, _from_version, _to_version| {
-            if let Some(new_data) = block_flattening_v1450::flatten_nbt(data) {
+            if let Some(new_data) =
+                block_flattening_v1450::flatten_nbt(data).or_else(|| flattening::flatten_nbt(data))
+            {
                 *data = new_data;
             }
         }),
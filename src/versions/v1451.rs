@@ -515,7 +515,7 @@ pub(crate) fn register() {
         DataVersion::new(VERSION, 3),
         "minecraft:spawner_minecart",
         map_data_walker(move |data, from_version, to_version| {
-            types::untagged_spawner().convert(data, from_version, to_version);
+            types::untagged_spawner_ref().convert(data, from_version, to_version);
         }),
     );
     types::entity_mut().add_walker_for_id(
@@ -1110,7 +1110,7 @@ fn convert_entity_state(
     entity_id: impl Into<JavaString>,
     id_path: &'static str,
     data_path: &'static str,
-    output_path: impl Into<JavaString> + Clone + 'static,
+    output_path: impl Into<JavaString> + Clone + Send + Sync + 'static,
 ) {
     types::entity_mut().add_converter_for_id(
         entity_id,
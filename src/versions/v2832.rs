@@ -343,15 +343,13 @@ pub(crate) fn register() {
                     generator.insert("settings", "minecraft:large_biomes");
                 }
             }
-            Some(JValue::String(str)) if str == "minecraft:flat" => {
-                if !has_increased_height {
-                    let Some(JValue::Compound(settings)) = generator.get_mut("settings") else { return };
-                    let Some(JValue::List(layers)) = settings.get_mut("layers") else { return };
-                    if !matches!(layers, JList::End | JList::Compound(_)) {
-                        return;
-                    }
-                    update_layers(layers);
+            Some(JValue::String(str)) if str == "minecraft:flat" && !has_increased_height => {
+                let Some(JValue::Compound(settings)) = generator.get_mut("settings") else { return };
+                let Some(JValue::List(layers)) = settings.get_mut("layers") else { return };
+                if !matches!(layers, JList::End | JList::Compound(_)) {
+                    return;
                 }
+                update_layers(layers);
             }
             _ => {
                 // do nothing
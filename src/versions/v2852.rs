@@ -1,5 +1,5 @@
-use crate::types;
-use tracing::error;
+use crate::diagnostics::{DiagnosticKind, Severity};
+use crate::{diagnostics, types};
 use world_transmuter_engine::{map_data_converter_func, JValue};
 
 const VERSION: u32 = 2852;
@@ -7,10 +7,15 @@ const VERSION: u32 = 2852;
 pub(crate) fn register() {
     types::world_gen_settings_mut().add_structure_converter(VERSION, map_data_converter_func(|data, _from_version, _to_version| {
         if let Some(JValue::Compound(dimensions)) = data.get("dimensions") {
-            for dimension in dimensions.values() {
+            for (key, dimension) in dimensions {
                 if let JValue::Compound(dimension) = dimension {
                     if !dimension.contains_key("type") {
-                        error!("Unable to load old custom worlds. Conversion may clobber the world!");
+                        diagnostics::report(
+                            DiagnosticKind::UnsupportedCustomWorld,
+                            Severity::Guess,
+                            format!("dimensions.{key}"),
+                            "Unable to load old custom worlds. Conversion may clobber the world!",
+                        );
                     }
                 }
             }
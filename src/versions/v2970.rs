@@ -1,9 +1,10 @@
+use crate::diagnostics::{DiagnosticKind, Severity};
 use crate::helpers::mc_namespace_map::McNamespaceMap;
-use crate::types;
+use crate::{diagnostics, types};
 use java_string::JavaStr;
 use std::collections::BTreeMap;
 use std::sync::OnceLock;
-use tracing::{error, warn};
+use tracing::error;
 use world_transmuter_engine::{get_mut_multi, map_data_converter_func, JCompound, JList, JValue};
 
 const VERSION: u32 = 2970;
@@ -185,7 +186,12 @@ pub(crate) fn register() {
                     }
 
                     let Some(remapped) = get_structure_converted(&key[..], &biome_counts) else {
-                        warn!("Encountered unknown structure in dataconverter: {key}");
+                        diagnostics::report(
+                            DiagnosticKind::UnknownStructure,
+                            Severity::DataLoss,
+                            format!("structures.starts.{key}"),
+                            format!("Encountered unknown structure: {key}"),
+                        );
                         continue;
                     };
                     value.insert("id", remapped.to_owned());
@@ -208,7 +214,12 @@ pub(crate) fn register() {
                     }
 
                     let Some(remapped) = get_structure_converted(&key[..], &biome_counts) else {
-                        warn!("Encountered unknown structure reference in dataconverter: {key}");
+                        diagnostics::report(
+                            DiagnosticKind::UnknownStructure,
+                            Severity::DataLoss,
+                            format!("structures.References.{key}"),
+                            format!("Encountered unknown structure reference: {key}"),
+                        );
                         continue;
                     };
                     new_references.insert(remapped, value);
@@ -247,10 +258,7 @@ fn get_structure_converted(
     biome_count: &BTreeMap<&JavaStr, u32>,
 ) -> Option<&'static JavaStr> {
     let id = id.to_lowercase();
-    let Some(remap) = conversion_map().get(&id[..]) else {
-        error!("Unknown structure {}", id);
-        return None;
-    };
+    let remap = conversion_map().get(&id[..])?;
 
     if remap.biome_to_new_structure.is_empty() || biome_count.is_empty() {
         return Some(remap.dfl);
@@ -213,7 +213,7 @@ pub(crate) fn register() {
                 to_version: DataVersion,
             ) {
                 match predicate.get_mut("blocks") {
-                    Some(JValue::String(blocks)) => types::block_name().convert(
+                    Some(JValue::String(blocks)) => types::block_name_ref().convert(
                         &mut JValueMut::String(blocks),
                         from_version,
                         to_version,
@@ -288,7 +288,7 @@ pub(crate) fn register() {
         VERSION,
         "minecraft:mob_spawner",
         map_data_walker(move |data, from_version, to_version| {
-            types::untagged_spawner().convert(data, from_version, to_version);
+            types::untagged_spawner_ref().convert(data, from_version, to_version);
         }),
     );
     register_inventory("minecraft:brewing_stand");
@@ -370,7 +370,7 @@ pub(crate) fn register() {
                     }
                 }
 
-                types::entity().convert(entity_tag, from_version, to_version);
+                types::entity_ref().convert(entity_tag, from_version, to_version);
             }
 
             if let Some(JValue::Compound(block_entity_tag)) = tag.get_mut("BlockEntityTag") {
@@ -390,7 +390,7 @@ pub(crate) fn register() {
                     }
                 }
 
-                types::tile_entity().convert(block_entity_tag, from_version, to_version);
+                types::tile_entity_ref().convert(block_entity_tag, from_version, to_version);
             }
 
             convert_object_list_in_map(
@@ -251,7 +251,7 @@ pub(crate) fn register() {
         VERSION,
         "minecraft:spawner_minecart",
         map_data_walker(move |data, from_version, to_version| {
-            types::untagged_spawner().convert(data, from_version, to_version);
+            types::untagged_spawner_ref().convert(data, from_version, to_version);
         }),
     );
     register_throwable_projectile("minecraft:spectral_arrow");
@@ -128,7 +128,7 @@ pub(crate) fn register() {
             VERSION,
             minecart_type,
             map_data_walker(move |data, from_version, to_version| {
-                types::untagged_spawner().convert(data, from_version, to_version);
+                types::untagged_spawner_ref().convert(data, from_version, to_version);
             }),
         );
     }
@@ -242,7 +242,7 @@ pub(crate) fn register() {
         VERSION,
         "MobSpawner",
         map_data_walker(move |data, from_version, to_version| {
-            types::untagged_spawner().convert(data, from_version, to_version);
+            types::untagged_spawner_ref().convert(data, from_version, to_version);
         }),
     );
     register_inventory("Cauldron");
@@ -319,7 +319,7 @@ pub(crate) fn register() {
                     false
                 };
 
-                types::entity().convert(entity_tag, from_version, to_version);
+                types::entity_ref().convert(entity_tag, from_version, to_version);
 
                 if remove_id {
                     entity_tag.remove("id");
@@ -345,7 +345,7 @@ pub(crate) fn register() {
                     false
                 };
 
-                types::tile_entity().convert(block_entity_tag, from_version, to_version);
+                types::tile_entity_ref().convert(block_entity_tag, from_version, to_version);
 
                 if remove_id {
                     block_entity_tag.remove("id");
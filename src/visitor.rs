@@ -0,0 +1,218 @@
+use crate::instrument;
+use crate::types::TypeKind;
+use java_string::JavaStr;
+use std::cell::Cell;
+use std::ptr::NonNull;
+use valence_nbt::value::ValueRef;
+use world_transmuter_engine::{
+    AbstractDynamicDataType, AbstractMapDataType, DataVersion, JCompound, JValue, JValueMut,
+    JValueRef,
+};
+
+type RawVisitor = dyn for<'a, 'b> FnMut(TypeKind, VisitedMut<'a, 'b>);
+
+thread_local! {
+    static VISITOR: Cell<Option<NonNull<RawVisitor>>> = const { Cell::new(None) };
+}
+
+/// An object of a known type found while visiting data.
+#[derive(Debug, Clone)]
+pub enum Visited<'a> {
+    Map(&'a JCompound),
+    Value(JValueRef<'a>),
+    Dynamic(&'a JValue),
+}
+
+impl<'a> Visited<'a> {
+    pub fn as_compound(&self) -> Option<&'a JCompound> {
+        match *self {
+            Visited::Map(data)
+            | Visited::Value(ValueRef::Compound(data))
+            | Visited::Dynamic(JValue::Compound(data)) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Returns the string of a visited name, such as an item or block name.
+    pub fn as_str(&self) -> Option<&'a JavaStr> {
+        match *self {
+            Visited::Value(ValueRef::String(data)) | Visited::Dynamic(JValue::String(data)) => {
+                Some(data)
+            }
+            _ => None,
+        }
+    }
+}
+
+pub(crate) enum VisitedMut<'a, 'b> {
+    Map(&'a mut JCompound),
+    Value(&'a mut JValueMut<'b>),
+    Dynamic(&'a mut JValue),
+}
+
+impl<'a> VisitedMut<'a, '_> {
+    fn into_visited(self) -> Visited<'a> {
+        match self {
+            VisitedMut::Map(data) => Visited::Map(data),
+            VisitedMut::Value(data) => Visited::Value(value_ref(data)),
+            VisitedMut::Dynamic(data) => Visited::Dynamic(data),
+        }
+    }
+}
+
+fn value_ref<'a>(value: &'a JValueMut) -> JValueRef<'a> {
+    match value {
+        JValueMut::Byte(v) => JValueRef::Byte(v),
+        JValueMut::Short(v) => JValueRef::Short(v),
+        JValueMut::Int(v) => JValueRef::Int(v),
+        JValueMut::Long(v) => JValueRef::Long(v),
+        JValueMut::Float(v) => JValueRef::Float(v),
+        JValueMut::Double(v) => JValueRef::Double(v),
+        JValueMut::ByteArray(v) => JValueRef::ByteArray(v),
+        JValueMut::String(v) => JValueRef::String(v),
+        JValueMut::List(v) => JValueRef::List(v),
+        JValueMut::Compound(v) => JValueRef::Compound(v),
+        JValueMut::IntArray(v) => JValueRef::IntArray(v),
+        JValueMut::LongArray(v) => JValueRef::LongArray(v),
+    }
+}
+
+/// Runs `f` with `visitor` receiving every object converted through a [`TypeLock`](crate::types::TypeLock) on this
+/// thread, before it is converted.
+pub(crate) fn with_visitor<R>(
+    visitor: &mut dyn for<'a, 'b> FnMut(TypeKind, VisitedMut<'a, 'b>),
+    f: impl FnOnce() -> R,
+) -> R {
+    struct RestoreGuard(Option<NonNull<RawVisitor>>);
+
+    impl Drop for RestoreGuard {
+        fn drop(&mut self) {
+            VISITOR.set(self.0);
+        }
+    }
+
+    // SAFETY: only the lifetime is erased. The pointer is removed from the thread local by the guard before the
+    // visitor goes out of scope.
+    let visitor: NonNull<RawVisitor> = unsafe { std::mem::transmute(NonNull::from(visitor)) };
+    let _guard = RestoreGuard(VISITOR.replace(Some(visitor)));
+    instrument::activate(f)
+}
+
+pub(crate) fn notify(kind: TypeKind, data: VisitedMut) {
+    struct RestoreGuard(NonNull<RawVisitor>);
+
+    impl Drop for RestoreGuard {
+        fn drop(&mut self) {
+            VISITOR.set(Some(self.0));
+        }
+    }
+
+    // The visitor is taken out while it runs, so that it can't be called reentrantly
+    let Some(mut visitor) = VISITOR.take() else {
+        return;
+    };
+    let _guard = RestoreGuard(visitor);
+    // SAFETY: the visitor is alive for as long as it is set, see with_visitor, and nothing else can access it while it
+    // is taken out.
+    unsafe { visitor.as_mut()(kind, data) }
+}
+
+/// Calls `visitor` for every object of a known type inside `data`, without converting anything. `version` is the data
+/// version of `data`, which decides which walkers are used to find the objects. If `typ` is one of the types of a
+/// registry, such as [`types::chunk_ref`](crate::types::chunk_ref), `data` itself is also visited.
+///
+/// Objects are visited before the objects nested inside them. Some walkers only find nested objects after hooks have
+/// normalized the data, for example by namespacing ids, so the visitor may see the normalized form of nested objects.
+pub fn visit_map(
+    typ: impl AbstractMapDataType,
+    data: &JCompound,
+    version: impl Into<DataVersion>,
+    mut visitor: impl FnMut(TypeKind, Visited),
+) {
+    let version = version.into();
+    let mut data = data.clone();
+    with_visitor(&mut |kind, data| visitor(kind, data.into_visited()), || {
+        typ.convert(&mut data, version, version)
+    });
+}
+
+/// Like [`visit_map`], but for dynamic data types.
+pub fn visit_dyn(
+    typ: impl AbstractDynamicDataType,
+    data: &JValue,
+    version: impl Into<DataVersion>,
+    mut visitor: impl FnMut(TypeKind, Visited),
+) {
+    let version = version.into();
+    let mut data = data.clone();
+    with_visitor(&mut |kind, data| visitor(kind, data.into_visited()), || {
+        typ.convert(&mut data, version, version)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types;
+    use valence_nbt::{compound, jcompound};
+    use world_transmuter_engine::JList;
+
+    #[test]
+    fn test_visit_nested_items() {
+        let player = jcompound! {
+            "Inventory" => JList::Compound(vec![
+                jcompound! {
+                    "id" => "minecraft:shulker_box",
+                    "Count" => 1i8,
+                    "Slot" => 0i8,
+                    "tag" => jcompound! {
+                        "BlockEntityTag" => jcompound! {
+                            "Items" => JList::Compound(vec![
+                                jcompound! {
+                                    "id" => "minecraft:diamond",
+                                    "Count" => 64i8,
+                                    "Slot" => 0i8,
+                                },
+                            ]),
+                        },
+                    },
+                },
+                jcompound! {
+                    "id" => "minecraft:stone",
+                    "Count" => 1i8,
+                    "Slot" => 1i8,
+                },
+            ]),
+        };
+        let original = player.clone();
+
+        let mut items = Vec::new();
+        let mut players = 0;
+        visit_map(
+            types::player_ref(),
+            &player,
+            3700,
+            |kind, data| match kind {
+                TypeKind::Player => players += 1,
+                TypeKind::ItemStack => {
+                    items.push(data.as_compound().and_then(|item| match item.get("id") {
+                        Some(JValue::String(id)) => Some(id.to_string()),
+                        _ => None,
+                    }))
+                }
+                _ => {}
+            },
+        );
+
+        assert_eq!(1, players);
+        assert_eq!(
+            vec![
+                Some("minecraft:shulker_box".to_owned()),
+                Some("minecraft:diamond".to_owned()),
+                Some("minecraft:stone".to_owned()),
+            ],
+            items
+        );
+        assert_eq!(original, player);
+    }
+}
@@ -0,0 +1,925 @@
+use crate::alpha::find_alpha_chunks;
+use crate::diagnostics::{Diagnostic, DiagnosticKind, LossyConversionError, Severity};
+use crate::helpers::json_parser::{parse_compound, stringify_compound, ParseError};
+use crate::legacy::id_map::{with_id_map, LegacyIdMap};
+use crate::mcregion::convert_mcregion_chunk;
+use crate::nbt_io::{read_nbt_file, write_nbt_file, CompressionType, NbtIoError};
+use crate::players::{offline_uuid, PlayerUuids, PlayerUuidsError};
+use crate::region::{parse_region_file_name, RegionError, RegionFile};
+use crate::types::{MinecraftTypes, TypeKind, TypeRef};
+use crate::{convert_map, diagnostics, types};
+use java_string::JavaString;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use world_transmuter_engine::{DataVersion, JCompound, JValue, MapDataType};
+
+// Data without a DataVersion predates 15w32a, so treat it as 1.8.
+const DEFAULT_DATA_VERSION: u32 = 99;
+// Vanilla assumes 1.12.2 for saved data, stats and advancements without a DataVersion.
+const DEFAULT_SAVED_DATA_VERSION: u32 = 1343;
+// POI files didn't exist before 1.14, vanilla assumes 19w11a for those without a DataVersion.
+const DEFAULT_POI_DATA_VERSION: u32 = 1945;
+// The version field of level.dat, rather than its DataVersion, tells MCRegion worlds apart from Anvil worlds.
+const MCREGION_VERSION: i32 = 19132;
+const ANVIL_VERSION: i32 = 19133;
+
+type TypeFn = fn() -> &'static TypeRef<MapDataType<'static>>;
+
+#[derive(Debug)]
+pub enum WorldError {
+    Io(std::io::Error),
+    Nbt(NbtIoError),
+    Region(RegionError),
+    Json(ParseError),
+    Lossy(LossyConversionError),
+    PlayerUuids(PlayerUuidsError),
+}
+
+impl Display for WorldError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorldError::Io(err) => Display::fmt(err, f),
+            WorldError::Nbt(err) => Display::fmt(err, f),
+            WorldError::Region(err) => Display::fmt(err, f),
+            WorldError::Json(err) => write!(f, "Invalid JSON: {err}"),
+            WorldError::Lossy(err) => Display::fmt(err, f),
+            WorldError::PlayerUuids(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+impl std::error::Error for WorldError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WorldError::Io(err) => Some(err),
+            WorldError::Nbt(err) => Some(err),
+            WorldError::Region(err) => Some(err),
+            WorldError::Json(err) => Some(err),
+            WorldError::Lossy(err) => Some(err),
+            WorldError::PlayerUuids(err) => Some(err),
+        }
+    }
+}
+
+impl From<std::io::Error> for WorldError {
+    fn from(value: std::io::Error) -> Self {
+        WorldError::Io(value)
+    }
+}
+
+impl From<NbtIoError> for WorldError {
+    fn from(value: NbtIoError) -> Self {
+        WorldError::Nbt(value)
+    }
+}
+
+impl From<RegionError> for WorldError {
+    fn from(value: RegionError) -> Self {
+        WorldError::Region(value)
+    }
+}
+
+impl From<ParseError> for WorldError {
+    fn from(value: ParseError) -> Self {
+        WorldError::Json(value)
+    }
+}
+
+impl From<LossyConversionError> for WorldError {
+    fn from(value: LossyConversionError) -> Self {
+        WorldError::Lossy(value)
+    }
+}
+
+impl From<PlayerUuidsError> for WorldError {
+    fn from(value: PlayerUuidsError) -> Self {
+        WorldError::PlayerUuids(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct UpgradeFailure {
+    pub path: PathBuf,
+    pub chunk: Option<(i32, i32)>,
+    pub error: WorldError,
+}
+
+#[derive(Debug)]
+pub struct UpgradeDiagnostic {
+    pub path: PathBuf,
+    pub chunk: Option<(i32, i32)>,
+    pub diagnostic: Diagnostic,
+}
+
+#[derive(Debug, Default)]
+pub struct UpgradeReport {
+    pub files_upgraded: usize,
+    pub files_skipped: usize,
+    pub chunks_upgraded: usize,
+    pub chunks_skipped: usize,
+    pub failures: Vec<UpgradeFailure>,
+    pub diagnostics: Vec<UpgradeDiagnostic>,
+}
+
+impl UpgradeReport {
+    fn add_diagnostics(
+        &mut self,
+        path: &Path,
+        chunk: Option<(i32, i32)>,
+        diagnostics: Vec<Diagnostic>,
+    ) {
+        self.diagnostics
+            .extend(diagnostics.into_iter().map(|diagnostic| UpgradeDiagnostic {
+                path: path.to_path_buf(),
+                chunk,
+                diagnostic,
+            }));
+    }
+}
+
+/// Upgrades every file of a world directory in place to the target data version.
+///
+/// Each file is converted from the `DataVersion` stored in it. Files that are already at or above the target version
+/// are skipped. Failures in individual files or chunks don't stop the upgrade, they are collected in the report.
+///
+/// Like vanilla, MCRegion (`.mcr`) region files from before 1.2 are first converted to Anvil (`.mca`) files next to
+/// them, unless the Anvil file already exists. The chunk files of Alpha worlds, from before region files, are moved
+/// into Anvil region files too, unless the world has already been converted to MCRegion.
+///
+/// Player files from before 1.7.6, in `players/<name>.dat`, are moved to `playerdata/<uuid>.dat`. See
+/// [`WorldUpgrader::player_uuids`].
+pub struct WorldUpgrader {
+    world_dir: PathBuf,
+    to_version: DataVersion,
+    strict: bool,
+    types: Arc<MinecraftTypes>,
+    legacy_ids: Option<Arc<LegacyIdMap>>,
+    player_uuids: Option<PlayerUuids>,
+    offline_players: bool,
+}
+
+impl WorldUpgrader {
+    pub fn new(world_dir: impl Into<PathBuf>, to_version: impl Into<DataVersion>) -> Self {
+        Self {
+            world_dir: world_dir.into(),
+            to_version: to_version.into(),
+            strict: false,
+            types: types::default_types(),
+            legacy_ids: None,
+            player_uuids: None,
+            offline_players: false,
+        }
+    }
+
+    /// In strict mode, files and chunks for which a converter would have to throw away data or guess are left
+    /// untouched and reported as failures.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets the registry to convert with, instead of the default one.
+    pub fn types(mut self, types: Arc<MinecraftTypes>) -> Self {
+        self.types = types;
+        self
+    }
+
+    /// Sets the numeric block and item ids of a modded world from before the flattening, instead of reading them from
+    /// `level.dat`. This can be used to remap the modded ids first.
+    pub fn legacy_ids(mut self, legacy_ids: impl Into<Arc<LegacyIdMap>>) -> Self {
+        self.legacy_ids = Some(legacy_ids.into());
+        self
+    }
+
+    /// Sets the UUIDs of the players whose files are named after them. By default, they are read from the
+    /// `usercache.json`, `whitelist.json` and `ops.json` files of the server the world is in, that is in the parent
+    /// directory of the world. The files of players that aren't in them are left in place and reported as
+    /// diagnostics, unless [offline players](Self::offline_players) are enabled.
+    pub fn player_uuids(mut self, player_uuids: PlayerUuids) -> Self {
+        self.player_uuids = Some(player_uuids);
+        self
+    }
+
+    /// Gives the players that aren't in the [player UUIDs](Self::player_uuids) their
+    /// [offline UUID](crate::players::offline_uuid). Only enable this for servers in offline mode, the files of
+    /// players on servers in online mode would be moved to UUIDs they never log in with.
+    pub fn offline_players(mut self, offline_players: bool) -> Self {
+        self.offline_players = offline_players;
+        self
+    }
+
+    pub fn upgrade(&self) -> Result<UpgradeReport, WorldError> {
+        let legacy_ids = self.legacy_ids.clone().or_else(|| {
+            let (root, _) = read_nbt_file(self.world_dir.join("level.dat")).ok()?;
+            LegacyIdMap::from_level_dat(&root).map(Arc::new)
+        });
+        self.types.enter(|| match legacy_ids {
+            Some(legacy_ids) => with_id_map(legacy_ids, || self.upgrade_inner()),
+            None => self.upgrade_inner(),
+        })
+    }
+
+    fn upgrade_inner(&self) -> Result<UpgradeReport, WorldError> {
+        if !self.world_dir.is_dir() {
+            return Err(WorldError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{} is not a directory", self.world_dir.display()),
+            )));
+        }
+
+        let mut report = UpgradeReport::default();
+
+        let dimension_dirs = self.dimension_dirs();
+        let failure_count = report.failures.len();
+        for dimension_dir in &dimension_dirs {
+            self.convert_alpha_chunks(dimension_dir, &mut report);
+            self.convert_mcregion_dir(&dimension_dir.join("region"), &mut report);
+        }
+        // if some chunks couldn't be converted to Anvil, level.dat is left alone, so that the world is still in the
+        // older format and the conversion is tried again next time
+        let converted_to_anvil = report.failures.len() == failure_count;
+
+        self.upgrade_file(&self.world_dir.join("level.dat"), &mut report, |path| {
+            if !converted_to_anvil {
+                return Ok(false);
+            }
+            self.upgrade_level(path)
+        });
+        for path in list_files(&self.world_dir.join("playerdata"), "dat") {
+            self.upgrade_file(&path, &mut report, |path| {
+                self.upgrade_nbt_file(path, types::player_ref, DEFAULT_DATA_VERSION)
+            });
+        }
+        self.migrate_player_files(&mut report);
+        for path in list_files(&self.world_dir.join("stats"), "json") {
+            self.upgrade_file(&path, &mut report, |path| {
+                self.upgrade_json_file(path, types::stats_ref, false)
+            });
+        }
+        for path in list_files(&self.world_dir.join("advancements"), "json") {
+            self.upgrade_file(&path, &mut report, |path| {
+                self.upgrade_json_file(path, types::advancements_ref, true)
+            });
+        }
+
+        for dimension_dir in dimension_dirs {
+            self.upgrade_region_dir(
+                &dimension_dir.join("region"),
+                types::chunk_ref,
+                DEFAULT_DATA_VERSION,
+                &mut report,
+            );
+            self.upgrade_region_dir(
+                &dimension_dir.join("entities"),
+                types::entity_chunk_ref,
+                DEFAULT_DATA_VERSION,
+                &mut report,
+            );
+            self.upgrade_region_dir(
+                &dimension_dir.join("poi"),
+                types::poi_chunk_ref,
+                DEFAULT_POI_DATA_VERSION,
+                &mut report,
+            );
+            for path in list_files(&dimension_dir.join("data"), "dat") {
+                let Some(typ) = path
+                    .file_stem()
+                    .and_then(|name| name.to_str())
+                    .and_then(saved_data_type)
+                else {
+                    continue;
+                };
+                self.upgrade_file(&path, &mut report, |path| {
+                    self.upgrade_nbt_file(path, typ, DEFAULT_SAVED_DATA_VERSION)
+                });
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn dimension_dirs(&self) -> Vec<PathBuf> {
+        fn find_custom_dimensions(dir: &Path, result: &mut Vec<PathBuf>) {
+            let Ok(entries) = fs::read_dir(dir) else {
+                return;
+            };
+            let mut children: Vec<_> = entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect();
+            children.sort();
+            if ["region", "entities", "poi", "data"]
+                .iter()
+                .any(|name| dir.join(name).is_dir())
+            {
+                result.push(dir.to_path_buf());
+            }
+            for child in children {
+                if !matches!(
+                    child.file_name().and_then(|name| name.to_str()),
+                    Some("region" | "entities" | "poi" | "data")
+                ) {
+                    find_custom_dimensions(&child, result);
+                }
+            }
+        }
+
+        let mut result = vec![self.world_dir.clone()];
+        for dir in ["DIM-1", "DIM1"] {
+            let dir = self.world_dir.join(dir);
+            if dir.is_dir() {
+                result.push(dir);
+            }
+        }
+        find_custom_dimensions(&self.world_dir.join("dimensions"), &mut result);
+        result
+    }
+
+    fn upgrade_file(
+        &self,
+        path: &Path,
+        report: &mut UpgradeReport,
+        upgrade: impl FnOnce(&Path) -> Result<bool, WorldError>,
+    ) {
+        if !path.is_file() {
+            return;
+        }
+        let (result, diagnostics) = diagnostics::collect(|| upgrade(path));
+        report.add_diagnostics(path, None, diagnostics);
+        match result {
+            Ok(true) => report.files_upgraded += 1,
+            Ok(false) => report.files_skipped += 1,
+            Err(error) => report.failures.push(UpgradeFailure {
+                path: path.to_path_buf(),
+                chunk: None,
+                error,
+            }),
+        }
+    }
+
+    fn upgrade_level(&self, path: &Path) -> Result<bool, WorldError> {
+        let (mut root, compression) = read_nbt_file(path)?;
+        let Some(JValue::Compound(data)) = root.get_mut("Data") else {
+            return Ok(false);
+        };
+        let from_version = get_data_version(data, DEFAULT_DATA_VERSION);
+        if from_version >= self.to_version {
+            return Ok(false);
+        }
+
+        self.convert(data, |data| {
+            convert_level(data, from_version, self.to_version)
+        })?;
+        data.insert("DataVersion", self.to_version.get_version() as i32);
+        // Alpha worlds have no version
+        if matches!(
+            data.get("version").and_then(|version| version.as_i32()),
+            None | Some(MCREGION_VERSION)
+        ) {
+            data.insert("version", ANVIL_VERSION);
+        }
+
+        write_nbt_file_atomic(path, &root, compression)?;
+        Ok(true)
+    }
+
+    fn migrate_player_files(&self, report: &mut UpgradeReport) {
+        let players_dir = self.world_dir.join("players");
+        let paths = list_files(&players_dir, "dat");
+        if paths.is_empty() {
+            return;
+        }
+        let loaded_uuids;
+        let player_uuids = match &self.player_uuids {
+            Some(player_uuids) => player_uuids,
+            None => {
+                let server_dir = self.world_dir.parent().unwrap_or(Path::new(""));
+                // rather than guessing UUIDs, leave the files alone if the server's lists can't be read
+                match PlayerUuids::from_server_dir(server_dir) {
+                    Ok(player_uuids) => loaded_uuids = player_uuids,
+                    Err(error) => {
+                        report.failures.push(UpgradeFailure {
+                            path: server_dir.to_path_buf(),
+                            chunk: None,
+                            error: error.into(),
+                        });
+                        return;
+                    }
+                }
+                &loaded_uuids
+            }
+        };
+
+        let playerdata_dir = self.world_dir.join("playerdata");
+        for path in paths {
+            let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let uuid = match player_uuids.get(name) {
+                Some(uuid) => uuid,
+                None if self.offline_players => offline_uuid(name),
+                None => {
+                    report.diagnostics.push(UpgradeDiagnostic {
+                        path: path.clone(),
+                        chunk: None,
+                        diagnostic: Diagnostic {
+                            kind: DiagnosticKind::UnknownPlayer,
+                            severity: Severity::Warning,
+                            version: DEFAULT_DATA_VERSION.into(),
+                            typ: TypeKind::Player,
+                            id: None,
+                            path: String::new(),
+                            message: format!(
+                                "Unknown UUID of player {name}, leaving their file in place"
+                            ),
+                            objects: Vec::new(),
+                        },
+                    });
+                    continue;
+                }
+            };
+            let new_path = playerdata_dir.join(format!("{uuid}.dat"));
+            self.upgrade_file(&path, report, |path| {
+                // a newer file of the same player takes priority
+                if new_path.exists() {
+                    return Ok(false);
+                }
+                let (mut data, compression) = read_nbt_file(path)?;
+                self.upgrade_compound(types::player_ref, &mut data, DEFAULT_DATA_VERSION)?;
+                fs::create_dir_all(&playerdata_dir)?;
+                write_nbt_file_atomic(&new_path, &data, compression)?;
+                fs::remove_file(path)?;
+                Ok(true)
+            });
+        }
+    }
+
+    fn upgrade_nbt_file(
+        &self,
+        path: &Path,
+        typ: TypeFn,
+        default_version: u32,
+    ) -> Result<bool, WorldError> {
+        let (mut data, compression) = read_nbt_file(path)?;
+        if !self.upgrade_compound(typ, &mut data, default_version)? {
+            return Ok(false);
+        }
+        write_nbt_file_atomic(path, &data, compression)?;
+        Ok(true)
+    }
+
+    fn upgrade_json_file(
+        &self,
+        path: &Path,
+        typ: TypeFn,
+        pretty: bool,
+    ) -> Result<bool, WorldError> {
+        let json = JavaString::from(fs::read_to_string(path)?);
+        let mut data = parse_compound(&json, true)?;
+        if !self.upgrade_compound(typ, &mut data, DEFAULT_SAVED_DATA_VERSION)? {
+            return Ok(false);
+        }
+        let json = stringify_compound(data, true, pretty);
+        write_atomic(path, json.as_bytes())?;
+        Ok(true)
+    }
+
+    fn convert_alpha_chunks(&self, dimension_dir: &Path, report: &mut UpgradeReport) {
+        let region_dir = dimension_dir.join("region");
+        if !list_files(&region_dir, "mcr").is_empty() {
+            return;
+        }
+        let mut regions = BTreeMap::<_, Vec<_>>::new();
+        for ((x, z), path) in find_alpha_chunks(dimension_dir) {
+            regions
+                .entry((x >> 5, z >> 5))
+                .or_default()
+                .push(((x, z), path));
+        }
+        if regions.is_empty() {
+            return;
+        }
+        if let Err(error) = fs::create_dir_all(&region_dir) {
+            report.failures.push(UpgradeFailure {
+                path: region_dir,
+                chunk: None,
+                error: error.into(),
+            });
+            return;
+        }
+
+        for ((region_x, region_z), chunks) in regions {
+            let region_path = region_dir.join(format!("r.{region_x}.{region_z}.mca"));
+            let mut region = match RegionFile::open(&region_path) {
+                Ok(region) => region,
+                Err(error) => {
+                    report.failures.push(UpgradeFailure {
+                        path: region_path,
+                        chunk: None,
+                        error: error.into(),
+                    });
+                    continue;
+                }
+            };
+            for ((x, z), path) in chunks {
+                // chunks that were moved before are left alone
+                if region.has_chunk(x, z) {
+                    continue;
+                }
+                let result =
+                    read_nbt_file(&path)
+                        .map_err(WorldError::from)
+                        .and_then(|(mut chunk, _)| {
+                            convert_mcregion_chunk(&mut chunk);
+                            region.write_chunk(x, z, &chunk, CompressionType::Zlib)?;
+                            Ok(())
+                        });
+                if let Err(error) = result {
+                    report.failures.push(UpgradeFailure {
+                        path,
+                        chunk: Some((x, z)),
+                        error,
+                    });
+                }
+            }
+            if let Err(error) = region.flush() {
+                report.failures.push(UpgradeFailure {
+                    path: region_path,
+                    chunk: None,
+                    error: error.into(),
+                });
+            }
+        }
+    }
+
+    fn convert_mcregion_dir(&self, dir: &Path, report: &mut UpgradeReport) {
+        for path in list_files(dir, "mcr") {
+            let is_region = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(parse_region_file_name)
+                .is_some();
+            let anvil_path = path.with_extension("mca");
+            if !is_region || anvil_path.exists() {
+                continue;
+            }
+            if let Err(error) = self.convert_mcregion_file(&path, &anvil_path, report) {
+                report.failures.push(UpgradeFailure {
+                    path,
+                    chunk: None,
+                    error,
+                });
+            }
+        }
+    }
+
+    fn convert_mcregion_file(
+        &self,
+        path: &Path,
+        anvil_path: &Path,
+        report: &mut UpgradeReport,
+    ) -> Result<(), WorldError> {
+        let mut region = RegionFile::open_read_only(path)?;
+        // write to a temporary file first, so that an interrupted conversion is started over
+        let temp_path = temp_path(anvil_path);
+        if temp_path.exists() {
+            fs::remove_file(&temp_path)?;
+        }
+        let mut anvil_region = RegionFile::open(&temp_path)?;
+        let mut failed = false;
+        let chunks: Vec<_> = region.chunks().collect();
+        for (x, z) in chunks {
+            let result = region
+                .read_chunk_with_compression(x, z)
+                .map_err(WorldError::from)
+                .and_then(|chunk| {
+                    let Some((mut chunk, compression)) = chunk else {
+                        return Ok(());
+                    };
+                    convert_mcregion_chunk(&mut chunk);
+                    anvil_region.write_chunk_with_timestamp(
+                        x,
+                        z,
+                        &chunk,
+                        compression,
+                        region.timestamp(x, z),
+                    )?;
+                    Ok(())
+                });
+            if let Err(error) = result {
+                failed = true;
+                report.failures.push(UpgradeFailure {
+                    path: path.to_path_buf(),
+                    chunk: Some((x, z)),
+                    error,
+                });
+            }
+        }
+        anvil_region.flush()?;
+        drop(anvil_region);
+        // keep only the MCRegion file if a chunk is missing, rather than losing the chunk once the Anvil file exists
+        if failed {
+            fs::remove_file(temp_path)?;
+        } else {
+            fs::rename(temp_path, anvil_path)?;
+        }
+        Ok(())
+    }
+
+    fn upgrade_region_dir(
+        &self,
+        dir: &Path,
+        typ: TypeFn,
+        default_version: u32,
+        report: &mut UpgradeReport,
+    ) {
+        for path in list_files(dir, "mca") {
+            let is_region = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(parse_region_file_name)
+                .is_some();
+            if !is_region {
+                continue;
+            }
+            if let Err(error) = self.upgrade_region_file(&path, typ, default_version, report) {
+                report.failures.push(UpgradeFailure {
+                    path,
+                    chunk: None,
+                    error,
+                });
+            }
+        }
+    }
+
+    fn upgrade_region_file(
+        &self,
+        path: &Path,
+        typ: TypeFn,
+        default_version: u32,
+        report: &mut UpgradeReport,
+    ) -> Result<(), WorldError> {
+        let mut region = RegionFile::open(path)?;
+        let chunks: Vec<_> = region.chunks().collect();
+        for (x, z) in chunks {
+            let (result, diagnostics) = diagnostics::collect(|| {
+                let Some((mut chunk, compression)) = region.read_chunk_with_compression(x, z)?
+                else {
+                    return Ok(false);
+                };
+                if !self.upgrade_compound(typ, &mut chunk, default_version)? {
+                    return Ok(false);
+                }
+                region.write_chunk(x, z, &chunk, compression)?;
+                Ok::<_, WorldError>(true)
+            });
+            report.add_diagnostics(path, Some((x, z)), diagnostics);
+            match result {
+                Ok(true) => report.chunks_upgraded += 1,
+                Ok(false) => report.chunks_skipped += 1,
+                Err(error) => report.failures.push(UpgradeFailure {
+                    path: path.to_path_buf(),
+                    chunk: Some((x, z)),
+                    error,
+                }),
+            }
+        }
+        region.flush()?;
+        Ok(())
+    }
+
+    fn upgrade_compound(
+        &self,
+        typ: TypeFn,
+        data: &mut JCompound,
+        default_version: u32,
+    ) -> Result<bool, WorldError> {
+        let from_version = get_data_version(data, default_version);
+        if from_version >= self.to_version {
+            return Ok(false);
+        }
+        self.convert(data, |data| {
+            convert_map(typ(), data, from_version, self.to_version)
+        })?;
+        data.insert("DataVersion", self.to_version.get_version() as i32);
+        Ok(true)
+    }
+
+    fn convert(
+        &self,
+        data: &mut JCompound,
+        f: impl FnOnce(&mut JCompound),
+    ) -> Result<(), WorldError> {
+        if self.strict {
+            diagnostics::run_strict(data, f)?;
+        } else {
+            f(data);
+        }
+        Ok(())
+    }
+}
+
+/// Converts the `Data` compound of a `level.dat` file, including the singleplayer player stored inside it.
+pub fn convert_level(
+    data: &mut JCompound,
+    from_version: impl Into<DataVersion>,
+    to_version: impl Into<DataVersion>,
+) {
+    let from_version = from_version.into();
+    let to_version = to_version.into();
+    // The singleplayer player is converted separately with the level's version.
+    if let Some(JValue::Compound(player)) = data.get_mut("Player") {
+        convert_map(types::player_ref(), player, from_version, to_version);
+    }
+    convert_map(types::level_ref(), data, from_version, to_version);
+}
+
+fn saved_data_type(name: &str) -> Option<TypeFn> {
+    let typ: TypeFn = match name {
+        "raids" | "raids_nether" | "raids_end" => types::saved_data_raids_ref,
+        "scoreboard" => types::saved_data_scoreboard_ref,
+        "idcounts" => types::saved_data_map_index_ref,
+        "random_sequences" => types::saved_data_random_sequences_ref,
+        "chunks" => types::saved_data_forced_chunks_ref,
+        "Fortress" | "Mineshaft" | "Monument" | "Stronghold" | "Temple" | "Village" | "EndCity"
+        | "Mansion" => types::saved_data_structure_feature_indices_ref,
+        _ if name.starts_with("map_") => types::saved_data_map_data_ref,
+        _ if name.starts_with("command_storage_") => types::saved_data_command_storage_ref,
+        _ => return None,
+    };
+    Some(typ)
+}
+
+fn get_data_version(data: &JCompound, default_version: u32) -> DataVersion {
+    data.get("DataVersion")
+        .and_then(|version| version.as_i32())
+        .and_then(|version| u32::try_from(version).ok())
+        .unwrap_or(default_version)
+        .into()
+}
+
+fn list_files(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == extension))
+        .collect();
+    files.sort();
+    files
+}
+
+fn write_nbt_file_atomic(
+    path: &Path,
+    data: &JCompound,
+    compression: CompressionType,
+) -> Result<(), WorldError> {
+    let temp_path = temp_path(path);
+    write_nbt_file(&temp_path, data, compression)?;
+    fs::rename(temp_path, path)?;
+    Ok(())
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> Result<(), WorldError> {
+    let temp_path = temp_path(path);
+    fs::write(&temp_path, data)?;
+    fs::rename(temp_path, path)?;
+    Ok(())
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".tmp");
+    path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::types::TypeKind;
+
+    #[test]
+    fn test_upgrade_world() {
+        let dir = TempDir::new("world-upgrade");
+
+        let mut player = JCompound::new();
+        player.insert("DataVersion", 1343);
+        player.insert("Health", 20.0f32);
+        let mut data = JCompound::new();
+        data.insert("DataVersion", 1343);
+        data.insert("LevelName", "test");
+        data.insert("Player", player.clone());
+        let mut level = JCompound::new();
+        level.insert("Data", data);
+        write_nbt_file(dir.path().join("level.dat"), &level, CompressionType::Gzip).unwrap();
+
+        // a corrupt DataVersion is treated like a missing one, rather than as newer than the target
+        player.insert("DataVersion", -1);
+        fs::create_dir_all(dir.path().join("playerdata")).unwrap();
+        write_nbt_file(
+            dir.path().join("playerdata/test.dat"),
+            &player,
+            CompressionType::Gzip,
+        )
+        .unwrap();
+
+        fs::create_dir_all(dir.path().join("DIM-1/data")).unwrap();
+        let mut raids = JCompound::new();
+        raids.insert("DataVersion", 3953);
+        raids.insert("data", JCompound::new());
+        write_nbt_file(
+            dir.path().join("DIM-1/data/raids_nether.dat"),
+            &raids,
+            CompressionType::Gzip,
+        )
+        .unwrap();
+
+        let mut chunk = JCompound::new();
+        chunk.insert("DataVersion", 1343);
+        chunk.insert("Level", JCompound::new());
+        fs::create_dir_all(dir.path().join("region")).unwrap();
+        let mut region = RegionFile::open(dir.path().join("region/r.0.0.mca")).unwrap();
+        region
+            .write_chunk(0, 0, &chunk, CompressionType::Zlib)
+            .unwrap();
+        region.flush().unwrap();
+        drop(region);
+
+        let report = WorldUpgrader::new(dir.path(), 3953).upgrade().unwrap();
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        assert_eq!(2, report.files_upgraded);
+        assert_eq!(1, report.files_skipped);
+        assert_eq!(1, report.chunks_upgraded);
+
+        let (level, compression) = read_nbt_file(dir.path().join("level.dat")).unwrap();
+        assert_eq!(CompressionType::Gzip, compression);
+        let Some(JValue::Compound(data)) = level.get("Data") else {
+            panic!("level.dat lost its Data tag");
+        };
+        assert_eq!(Some(3953), data.get("DataVersion").and_then(|v| v.as_i32()));
+
+        let mut region = RegionFile::open(dir.path().join("region/r.0.0.mca")).unwrap();
+        let (chunk, compression) = region.read_chunk_with_compression(0, 0).unwrap().unwrap();
+        assert_eq!(CompressionType::Zlib, compression);
+        assert_eq!(
+            Some(3953),
+            chunk.get("DataVersion").and_then(|v| v.as_i32())
+        );
+    }
+
+    #[test]
+    fn test_convert_level_is_visited() {
+        let mut data = JCompound::new();
+        data.insert("Player", JCompound::new());
+        let mut kinds = Vec::new();
+        crate::visitor::with_visitor(&mut |kind, _| kinds.push(kind), || {
+            convert_level(&mut data, 1343, 1343)
+        });
+        assert!(kinds.contains(&TypeKind::Level));
+        assert!(kinds.contains(&TypeKind::Player));
+    }
+
+    #[test]
+    fn test_failed_mcregion_conversion() {
+        let dir = TempDir::new("failed-mcregion");
+        let mut data = JCompound::new();
+        data.insert("version", MCREGION_VERSION);
+        let mut level = JCompound::new();
+        level.insert("Data", data);
+        write_nbt_file(dir.path().join("level.dat"), &level, CompressionType::Gzip).unwrap();
+
+        fs::create_dir_all(dir.path().join("region")).unwrap();
+        let region_path = dir.path().join("region/r.0.0.mcr");
+        let mut region = RegionFile::open(&region_path).unwrap();
+        region
+            .write_chunk(0, 0, &JCompound::new(), CompressionType::Zlib)
+            .unwrap();
+        region.flush().unwrap();
+        drop(region);
+        // corrupt the compressed data of the only chunk, which starts after the headers and the chunk's own header
+        let mut bytes = fs::read(&region_path).unwrap();
+        bytes[8192 + 5..8192 + 9].fill(0xff);
+        fs::write(&region_path, bytes).unwrap();
+
+        let report = WorldUpgrader::new(dir.path(), 3953).upgrade().unwrap();
+        assert_eq!(1, report.failures.len());
+        assert_eq!(Some((0, 0)), report.failures[0].chunk);
+        assert!(region_path.is_file());
+        assert!(!dir.path().join("region/r.0.0.mca").exists());
+        assert!(!dir.path().join("region/r.0.0.mca.tmp").exists());
+
+        let (level, _) = read_nbt_file(dir.path().join("level.dat")).unwrap();
+        let Some(JValue::Compound(data)) = level.get("Data") else {
+            panic!("level.dat lost its Data tag");
+        };
+        assert_eq!(Some(&JValue::Int(MCREGION_VERSION)), data.get("version"));
+        assert_eq!(None, data.get("DataVersion"));
+    }
+}
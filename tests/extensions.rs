@@ -0,0 +1,37 @@
+use java_string::JavaString;
+use valence_nbt::{compound, jcompound};
+use world_transmuter::rename::{rename_entity, simple_rename};
+use world_transmuter::{convert_map, types};
+use world_transmuter_engine::{DataWalkerMapListPaths, JList, JValue};
+
+#[test]
+fn test_extension() {
+    types::register_extension(|| {
+        rename_entity(3000, simple_rename("mymod:old_mob", "mymod:new_mob"));
+        types::entity_mut().add_walker_for_id(
+            99,
+            "mymod:new_mob",
+            DataWalkerMapListPaths::new(types::item_stack_ref(), "Items"),
+        );
+    })
+    .unwrap();
+
+    let mut entity = jcompound! {
+        "id" => "mymod:old_mob",
+        "Items" => JList::Compound(vec![jcompound! {
+            "id" => "minecraft:stone",
+            "Count" => 1i8,
+        }]),
+    };
+    convert_map(&*types::entity(), &mut entity, 2999, 3839);
+    assert_eq!(
+        Some(&JValue::String(JavaString::from("mymod:new_mob"))),
+        entity.get("id")
+    );
+    let Some(JValue::List(JList::Compound(items))) = entity.get("Items") else {
+        panic!("Items was removed");
+    };
+    assert_eq!(Some(&JValue::Int(1)), items[0].get("count"));
+
+    assert!(types::register_extension(|| {}).is_err());
+}
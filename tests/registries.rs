@@ -0,0 +1,44 @@
+use java_string::JavaString;
+use std::sync::Arc;
+use valence_nbt::{compound, jcompound};
+use world_transmuter::rename::{rename_entity, simple_rename};
+use world_transmuter::types::MinecraftTypes;
+use world_transmuter::{convert_map, types};
+use world_transmuter_engine::JValue;
+
+#[test]
+fn test_separate_registries() {
+    let modded = MinecraftTypes::builder()
+        .extension(|| rename_entity(3000, simple_rename("mymod:old_mob", "mymod:new_mob")))
+        .build();
+    let vanilla = MinecraftTypes::new();
+
+    let entity = jcompound! {
+        "id" => "mymod:old_mob",
+    };
+
+    let mut modded_entity = entity.clone();
+    convert_map(modded.entity_ref(), &mut modded_entity, 2999, 3839);
+    assert_eq!(
+        Some(&JValue::String(JavaString::from("mymod:new_mob"))),
+        modded_entity.get("id")
+    );
+
+    let mut vanilla_entity = entity.clone();
+    convert_map(vanilla.entity_ref(), &mut vanilla_entity, 2999, 3839);
+    assert_eq!(entity, vanilla_entity);
+
+    let mut default_entity = entity.clone();
+    convert_map(types::entity_ref(), &mut default_entity, 2999, 3839);
+    assert_eq!(entity, default_entity);
+
+    // the global functions use the registry that is current on this thread
+    let mut entered_entity = entity.clone();
+    modded.enter(|| convert_map(&*types::entity(), &mut entered_entity, 2999, 3839));
+    assert_eq!(modded_entity, entered_entity);
+
+    // registries are freed once they are no longer used
+    let weak_modded = Arc::downgrade(&modded);
+    drop(modded);
+    assert!(weak_modded.upgrade().is_none());
+}